@@ -0,0 +1,177 @@
+use core::marker::PhantomData;
+
+use bevy::{
+    ecs::{event::Event, resource::Resource, system::Command, world::World},
+    platform::prelude::{String, ToString, Vec, format},
+    prelude::Deref,
+};
+use neonex_platform::{
+    NeoNexConfig, NeoNexPlatform, StartupConfigImport, export_startup_config_bundle,
+    preview_startup_config_bundle,
+};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+
+use crate::{
+    DefaultNeoNexConfig,
+    startup_config::{
+        StartupConfigLayers, StartupConfigSaveFailed, replace_startup_config, save_startup_config,
+    },
+};
+
+/// Sent by [`ExportStartupConfig`] with the exported bundle, see
+/// `neonex_platform::StartupConfigBundle`. It's JSON, meant to be written to a `.json` file
+/// or attached to a support ticket.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigExported {
+    pub bundle: Vec<u8>,
+}
+
+/// Sent when a bundle couldn't be exported, or isn't a valid bundle of the app.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigBundleFailed {
+    pub error: String,
+}
+
+/// Sent once [`ResetStartupConfig`] has reset the startup config, with where the platform
+/// kept the previous one, if it could.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigReset {
+    pub backup: Option<String>,
+}
+
+/// Saves the startup config, then exports every persisted namespace into a bundle, sent
+/// with a [`StartupConfigExported`] event:
+/// ```ignore
+/// commands.queue(ExportStartupConfig::<LauncherConfig>::default());
+/// ```
+pub struct ExportStartupConfig<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for ExportStartupConfig<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for ExportStartupConfig<CONFIG> {
+    fn apply(self, world: &mut World) {
+        // When it fails, the entries saved previously are exported.
+        save_startup_config::<CONFIG>(world);
+        let layers = world.resource::<StartupConfigLayers<CONFIG>>();
+        match export_startup_config_bundle::<CONFIG>(&layers.persisted) {
+            Ok(bundle) => {
+                world.send_event(StartupConfigExported { bundle });
+            }
+            Err(error) => {
+                world.send_event(StartupConfigBundleFailed {
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// A bundle about to be imported, inserted by [`PreviewStartupConfigImport`], with the
+/// modifications it makes to the persisted namespaces.
+///
+/// It's imported with [`ApplyStartupConfigImport`], or discarded by removing the resource.
+#[derive(Resource, Deref)]
+pub struct StartupConfigImportPreview<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(
+    #[deref] pub StartupConfigImport<CONFIG::StartupConfig>,
+    PhantomData<CONFIG>,
+);
+
+/// Parses a bundle and inserts its [`StartupConfigImportPreview`], to be shown to the user
+/// before importing it:
+/// ```ignore
+/// commands.queue(PreviewStartupConfigImport::<LauncherConfig>::new(data));
+/// ```
+///
+/// The bundle is upgraded with the migrations of the app, and its invalid entries are
+/// dropped. When it can't be read, a [`StartupConfigBundleFailed`] event is sent instead.
+pub struct PreviewStartupConfigImport<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
+    bundle: Vec<u8>,
+    _config: PhantomData<CONFIG>,
+}
+
+impl<CONFIG: NeoNexConfig> PreviewStartupConfigImport<CONFIG> {
+    pub fn new(bundle: Vec<u8>) -> Self {
+        Self {
+            bundle,
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for PreviewStartupConfigImport<CONFIG> {
+    fn apply(self, world: &mut World) {
+        let layers = world.resource::<StartupConfigLayers<CONFIG>>();
+        match preview_startup_config_bundle::<CONFIG>(&self.bundle, &layers.persisted) {
+            Ok(preview) => {
+                world.insert_resource(StartupConfigImportPreview::<CONFIG>(preview, PhantomData))
+            }
+            Err(error) => {
+                world.send_event(StartupConfigBundleFailed {
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Imports the bundle of the [`StartupConfigImportPreview`], replacing every persisted
+/// namespace. Unsaved modifications of the startup config set are dropped.
+///
+/// When it can't be saved, a [`StartupConfigSaveFailed`] event is sent and the preview is
+/// kept, so that it can be applied again.
+pub struct ApplyStartupConfigImport<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(
+    PhantomData<CONFIG>,
+);
+
+impl<CONFIG: NeoNexConfig> Default for ApplyStartupConfigImport<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for ApplyStartupConfigImport<CONFIG> {
+    fn apply(self, world: &mut World) {
+        let Some(preview) = world.remove_resource::<StartupConfigImportPreview<CONFIG>>() else {
+            return;
+        };
+        if !replace_startup_config::<CONFIG>(world, preview.store.clone()) {
+            world.insert_resource(preview);
+        }
+    }
+}
+
+/// Resets every persisted namespace, so that the startup config falls back to the defaults
+/// of the app, its system-wide entries and its environment and command-line overrides.
+///
+/// The platform keeps a copy of the previous startup config first, see
+/// `NeoNexPlatform::back_up_startup_config`, then a [`StartupConfigReset`] event is sent.
+/// When the copy can't be made, nothing is reset and a [`StartupConfigSaveFailed`] event is
+/// sent.
+pub struct ResetStartupConfig<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for ResetStartupConfig<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for ResetStartupConfig<CONFIG> {
+    fn apply(self, world: &mut World) {
+        let backup = match CONFIG::Platform::back_up_startup_config::<CONFIG>() {
+            Ok(backup) => backup,
+            Err(error) => {
+                world.send_event(StartupConfigSaveFailed {
+                    error: format!("unable to back up the startup config: {error}"),
+                });
+                return;
+            }
+        };
+        if replace_startup_config::<CONFIG>(world, NamespacedStartupConfigSet::default()) {
+            world.send_event(StartupConfigReset { backup });
+        }
+    }
+}
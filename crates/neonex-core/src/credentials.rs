@@ -0,0 +1,540 @@
+//! Credential vault of the app, see `neonex_platform::CredentialVault`, kept in the
+//! [`Credentials`] resource once unlocked with the [`UnlockDialog`] or [`UnlockCredentials`].
+//!
+//! The dialog takes the same key presses as the settings screen, and is drawn the same way:
+//! ```ignore
+//! app.add_plugins(CredentialsPlugin::<LauncherConfig>::default());
+//!
+//! fn ask_passphrase(mut commands: Commands) {
+//!     commands.queue(OpenUnlockDialog::<LauncherConfig>::default());
+//! }
+//!
+//! fn forward_keys(mut keys: EventReader<KeyEvent>, mut dialog: EventWriter<UnlockDialogKeyPressed>) {
+//!     // Same as for `SettingsKeyPressed`.
+//! }
+//!
+//! fn launch_game(credentials: Res<Credentials<LauncherConfig>>) {
+//!     let Some(vault) = credentials.vault() else {
+//!         return;
+//!     };
+//!     Command::new(GAME_PATH)
+//!         .envs(vault.handoff_env(["LoginToken"]))
+//!         .spawn();
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        event::{Event, EventReader, EventWriter},
+        resource::Resource,
+        system::{Command, Commands, ResMut},
+        world::World,
+    },
+    platform::prelude::{String, ToString, Vec, format},
+    tasks::{AsyncComputeTaskPool, Task, futures::check_ready},
+};
+use neonex_platform::{
+    CredentialVault, CredentialVaultError, CredentialVaultSecret, NeoNexConfig, fs,
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+use zeroize::Zeroizing;
+
+use crate::{DefaultNeoNexConfig, SettingsKey};
+
+/// The credential vault of the app, `None` until it's unlocked.
+///
+/// Modifications of the vault are written with [`SaveCredentials`].
+#[derive(Resource)]
+pub struct Credentials<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
+    vault: Option<CredentialVault>,
+    _config: PhantomData<CONFIG>,
+}
+
+impl<CONFIG: NeoNexConfig> Default for Credentials<CONFIG> {
+    fn default() -> Self {
+        Self {
+            vault: None,
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Credentials<CONFIG> {
+    pub fn is_unlocked(&self) -> bool {
+        self.vault.is_some()
+    }
+
+    pub fn vault(&self) -> Option<&CredentialVault> {
+        self.vault.as_ref()
+    }
+
+    pub fn vault_mut(&mut self) -> Option<&mut CredentialVault> {
+        self.vault.as_mut()
+    }
+}
+
+/// Sent once the [`Credentials`] have been unlocked.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CredentialsUnlocked;
+
+/// Sent when [`UnlockCredentials`] couldn't unlock the vault.
+#[derive(Event, Debug, Clone)]
+pub struct CredentialsUnlockFailed {
+    pub error: String,
+}
+
+/// Sent when [`SaveCredentials`] couldn't write the vault.
+#[derive(Event, Debug, Clone)]
+pub struct CredentialsSaveFailed {
+    pub error: String,
+}
+
+/// Unlocks the vault of `CONFIG`, creating it with `secret` when it's missing.
+fn open_vault<CONFIG: NeoNexConfig>(
+    secret: &CredentialVaultSecret,
+) -> Result<CredentialVault, CredentialVaultError> {
+    let path = fs::credential_vault_path::<CONFIG>();
+    let created = !CredentialVault::exists(&path);
+    let vault = CredentialVault::open(&path, secret)?;
+    if created {
+        vault.save()?;
+    }
+    Ok(vault)
+}
+
+/// Unlocks the [`Credentials`] with a known secret, e.g. the installation key, then sends
+/// [`CredentialsUnlocked`] or [`CredentialsUnlockFailed`]:
+/// ```ignore
+/// commands.queue(UnlockCredentials::<LauncherConfig>::new(CredentialVaultSecret::Installation));
+/// ```
+///
+/// A missing vault is created with the secret. The key is derived right away, which takes a
+/// while with a passphrase: the [`UnlockDialog`] derives it in a task instead.
+pub struct UnlockCredentials<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
+    secret: CredentialVaultSecret,
+    _config: PhantomData<CONFIG>,
+}
+
+impl<CONFIG: NeoNexConfig> UnlockCredentials<CONFIG> {
+    pub fn new(secret: CredentialVaultSecret) -> Self {
+        Self {
+            secret,
+            _config: PhantomData,
+        }
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for UnlockCredentials<CONFIG> {
+    fn apply(self, world: &mut World) {
+        match open_vault::<CONFIG>(&self.secret) {
+            Ok(vault) => {
+                world.resource_mut::<Credentials<CONFIG>>().vault = Some(vault);
+                world.send_event(CredentialsUnlocked);
+            }
+            Err(error) => {
+                world.send_event(CredentialsUnlockFailed {
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+}
+
+/// Locks the [`Credentials`], dropping the secrets from memory.
+pub struct LockCredentials<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for LockCredentials<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for LockCredentials<CONFIG> {
+    fn apply(self, world: &mut World) {
+        world.resource_mut::<Credentials<CONFIG>>().vault = None;
+    }
+}
+
+/// Writes the modifications of the unlocked [`Credentials`] to the vault. When it fails, a
+/// [`CredentialsSaveFailed`] event is sent.
+pub struct SaveCredentials<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for SaveCredentials<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for SaveCredentials<CONFIG> {
+    fn apply(self, world: &mut World) {
+        let credentials = world.resource::<Credentials<CONFIG>>();
+        if let Some(Err(error)) = credentials.vault.as_ref().map(CredentialVault::save) {
+            world.send_event(CredentialsSaveFailed {
+                error: error.to_string(),
+            });
+        }
+    }
+}
+
+/// Sent by the app for each key press while the [`UnlockDialog`] is open.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UnlockDialogKeyPressed(pub SettingsKey);
+
+/// Sent once the [`UnlockDialog`] has been closed, after the [`Credentials`] have been
+/// unlocked when `unlocked`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UnlockDialogClosed {
+    pub unlocked: bool,
+}
+
+/// What the unlock dialog asks for after a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnlockDialogAction {
+    Edit,
+    Unlock,
+    Cancel,
+}
+
+/// State of the dialog asking for the passphrase of the vault, present while it's open.
+/// When there's no vault yet, it asks for a new passphrase twice instead. Drawn as a ratatui
+/// widget, hiding what's typed.
+///
+/// The key is derived from the passphrase in a task, as it takes a while on purpose. Key
+/// presses are ignored meanwhile.
+#[derive(Resource)]
+pub struct UnlockDialog<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
+    creating: bool,
+    unlocking: Option<Task<Result<CredentialVault, CredentialVaultError>>>,
+    passphrase: Zeroizing<String>,
+    confirmation: Zeroizing<String>,
+    /// 0 for the passphrase, 1 for its confirmation when creating, then the buttons.
+    focus: usize,
+    /// Whether Unlock, rather than Cancel, is selected when the buttons are focused.
+    unlock_selected: bool,
+    error: Option<String>,
+    _config: PhantomData<CONFIG>,
+}
+
+impl<CONFIG: NeoNexConfig> UnlockDialog<CONFIG> {
+    /// Opens the dialog, creating a vault rather than unlocking it when `creating`.
+    pub fn new(creating: bool) -> Self {
+        Self {
+            creating,
+            unlocking: None,
+            passphrase: Zeroizing::default(),
+            confirmation: Zeroizing::default(),
+            focus: 0,
+            unlock_selected: true,
+            error: None,
+            _config: PhantomData,
+        }
+    }
+
+    /// Whether the vault is being unlocked with the typed passphrase.
+    pub fn is_unlocking(&self) -> bool {
+        self.unlocking.is_some()
+    }
+
+    /// Index of the buttons, i.e. the number of fields.
+    fn buttons(&self) -> usize {
+        match self.creating {
+            true => 2,
+            false => 1,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: SettingsKey) -> UnlockDialogAction {
+        let buttons = self.buttons();
+        match key {
+            SettingsKey::Escape => return UnlockDialogAction::Cancel,
+            SettingsKey::Up => self.focus = self.focus.saturating_sub(1),
+            SettingsKey::Down => self.focus = (self.focus + 1).min(buttons),
+            SettingsKey::Enter if self.focus == buttons && !self.unlock_selected => {
+                return UnlockDialogAction::Cancel;
+            }
+            // Enter in the last field unlocks, as in most login forms.
+            SettingsKey::Enter if self.focus + 1 >= buttons => return UnlockDialogAction::Unlock,
+            SettingsKey::Enter => self.focus += 1,
+            SettingsKey::Left | SettingsKey::Right if self.focus == buttons => {
+                self.unlock_selected = key == SettingsKey::Left;
+            }
+            _ if self.focus == buttons => {}
+            SettingsKey::Backspace => {
+                self.field_mut().pop();
+            }
+            SettingsKey::Char(c) => {
+                self.field_mut().push(c);
+                self.error = None;
+            }
+            _ => {}
+        }
+        UnlockDialogAction::Edit
+    }
+
+    fn field_mut(&mut self) -> &mut String {
+        match self.focus {
+            0 => &mut self.passphrase,
+            _ => &mut self.confirmation,
+        }
+    }
+
+    /// The typed passphrase. When it's empty, or doesn't match its confirmation, returns
+    /// `None` and shows why.
+    pub fn secret(&mut self) -> Option<CredentialVaultSecret> {
+        self.error = if self.passphrase.is_empty() {
+            Some("the passphrase can't be empty".to_string())
+        } else if self.creating && self.passphrase != self.confirmation {
+            Some("the passphrases don't match".to_string())
+        } else {
+            None
+        };
+        match self.error {
+            Some(_) => None,
+            None => Some(CredentialVaultSecret::Passphrase(self.passphrase.clone())),
+        }
+    }
+
+    /// Shows why the vault couldn't be unlocked, clearing the typed passphrases.
+    pub fn fail(&mut self, error: &CredentialVaultError) {
+        self.error = Some(error.to_string());
+        self.passphrase.clear();
+        self.confirmation.clear();
+        self.focus = 0;
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Widget for &UnlockDialog<CONFIG> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let focused = Style::default().add_modifier(Modifier::REVERSED);
+        let buttons = self.buttons();
+        let mut fields = Vec::from([("Passphrase", &self.passphrase)]);
+        if self.creating {
+            fields.push(("Confirm", &self.confirmation));
+        }
+
+        let mut lines = Vec::new();
+        for (index, (label, text)) in fields.into_iter().enumerate() {
+            let mut masked = "*".repeat(text.chars().count());
+            if index == self.focus {
+                masked.push('_');
+            }
+            lines.push(Line::from(Vec::from([
+                Span::raw(format!("{label:10}  ")),
+                match index == self.focus {
+                    true => Span::styled(masked, focused),
+                    false => Span::raw(masked),
+                },
+            ])));
+        }
+        if let Some(error) = &self.error {
+            lines.push(Line::styled(
+                error.as_str(),
+                Style::default().fg(Color::Red),
+            ));
+        } else if self.is_unlocking() {
+            lines.push(Line::raw("Unlocking..."));
+        }
+
+        let button = |label: &'static str, selected: bool| match selected {
+            true if self.focus == buttons => Span::styled(label, focused),
+            true => Span::styled(label, Style::default().add_modifier(Modifier::BOLD)),
+            false => Span::raw(label),
+        };
+        lines.push(Line::default());
+        lines.push(Line::from(Vec::from([
+            button(
+                match self.creating {
+                    true => "[ Create ]",
+                    false => "[ Unlock ]",
+                },
+                self.unlock_selected,
+            ),
+            Span::raw("  "),
+            button("[ Cancel ]", !self.unlock_selected),
+        ])));
+
+        let title = match self.creating {
+            true => " New credential vault ",
+            false => " Unlock credentials ",
+        };
+        Paragraph::new(lines)
+            .style(
+                Style::default()
+                    .fg(CONFIG::DEFAULT_FOREGROUND_COLOR)
+                    .bg(CONFIG::DEFAULT_BACKGROUND_COLOR),
+            )
+            .block(Block::bordered().title(title))
+            .render(area, buf);
+    }
+}
+
+/// Opens the [`UnlockDialog`], unless the [`Credentials`] are already unlocked:
+/// ```ignore
+/// commands.queue(OpenUnlockDialog::<LauncherConfig>::default());
+/// ```
+pub struct OpenUnlockDialog<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for OpenUnlockDialog<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for OpenUnlockDialog<CONFIG> {
+    fn apply(self, world: &mut World) {
+        if world.resource::<Credentials<CONFIG>>().is_unlocked() {
+            return;
+        }
+        let creating = !CredentialVault::exists(&fs::credential_vault_path::<CONFIG>());
+        world.insert_resource(UnlockDialog::<CONFIG>::new(creating));
+    }
+}
+
+/// Inserts the locked [`Credentials`], and feeds the [`UnlockDialogKeyPressed`] events to
+/// the open [`UnlockDialog`], unlocking them with its passphrase.
+pub struct CredentialsPlugin<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for CredentialsPlugin<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Plugin for CredentialsPlugin<CONFIG> {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Credentials<CONFIG>>()
+            .add_event::<CredentialsUnlocked>()
+            .add_event::<CredentialsUnlockFailed>()
+            .add_event::<CredentialsSaveFailed>()
+            .add_event::<UnlockDialogKeyPressed>()
+            .add_event::<UnlockDialogClosed>()
+            .add_systems(Update, update_unlock_dialog::<CONFIG>);
+    }
+}
+
+fn update_unlock_dialog<CONFIG: NeoNexConfig>(
+    mut keys: EventReader<UnlockDialogKeyPressed>,
+    dialog: Option<ResMut<UnlockDialog<CONFIG>>>,
+    mut credentials: ResMut<Credentials<CONFIG>>,
+    mut unlocked: EventWriter<CredentialsUnlocked>,
+    mut closed: EventWriter<UnlockDialogClosed>,
+    mut commands: Commands,
+) {
+    let Some(mut dialog) = dialog else {
+        keys.clear();
+        return;
+    };
+
+    if let Some(task) = &mut dialog.unlocking {
+        keys.clear();
+        let Some(opened) = check_ready(task) else {
+            return;
+        };
+        dialog.unlocking = None;
+        match opened {
+            Ok(vault) => {
+                credentials.vault = Some(vault);
+                unlocked.write(CredentialsUnlocked);
+                commands.remove_resource::<UnlockDialog<CONFIG>>();
+                closed.write(UnlockDialogClosed { unlocked: true });
+            }
+            Err(error) => dialog.fail(&error),
+        }
+        return;
+    }
+
+    for UnlockDialogKeyPressed(key) in keys.read() {
+        match dialog.handle_key(*key) {
+            UnlockDialogAction::Edit => {}
+            UnlockDialogAction::Unlock => {
+                let Some(secret) = dialog.secret() else {
+                    continue;
+                };
+                dialog.unlocking = Some(
+                    AsyncComputeTaskPool::get().spawn(async move { open_vault::<CONFIG>(&secret) }),
+                );
+                break;
+            }
+            UnlockDialogAction::Cancel => {
+                commands.remove_resource::<UnlockDialog<CONFIG>>();
+                closed.write(UnlockDialogClosed { unlocked: false });
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::time::{Duration, Instant};
+
+    use bevy::app::TaskPoolPlugin;
+    use neonex_mockplatform::MockPlatform;
+    use neonex_platform::MemoryStartupConfigStore;
+    use neonex_shared::NoStartupConfig;
+
+    use super::*;
+
+    struct VaultApp;
+
+    impl NeoNexConfig for VaultApp {
+        type Platform = MockPlatform;
+        type StartupConfig = NoStartupConfig;
+        type StartupConfigStore = MemoryStartupConfigStore;
+        const APP_ID: &'static str = "org.neonex.test.unlock-dialog";
+    }
+
+    #[test]
+    fn the_dialog_unlocks_in_a_task() {
+        let path = fs::credential_vault_path::<VaultApp>();
+        let _ = std::fs::remove_file(&path);
+        let mut app = App::new();
+        app.add_plugins((
+            TaskPoolPlugin::default(),
+            CredentialsPlugin::<VaultApp>::default(),
+        ));
+        app.insert_resource(UnlockDialog::<VaultApp>::new(true));
+        for key in [
+            SettingsKey::Char('p'),
+            SettingsKey::Down,
+            SettingsKey::Char('p'),
+            SettingsKey::Enter,
+            // Ignored while unlocking.
+            SettingsKey::Escape,
+        ] {
+            app.world_mut().send_event(UnlockDialogKeyPressed(key));
+        }
+
+        app.update();
+        assert!(
+            app.world()
+                .resource::<UnlockDialog<VaultApp>>()
+                .is_unlocking()
+        );
+        let started = Instant::now();
+        while !app
+            .world()
+            .resource::<Credentials<VaultApp>>()
+            .is_unlocked()
+        {
+            assert!(started.elapsed() < Duration::from_secs(60));
+            std::thread::sleep(Duration::from_millis(10));
+            app.update();
+        }
+
+        assert!(!app.world().contains_resource::<UnlockDialog<VaultApp>>());
+        assert!(CredentialVault::exists(&path));
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -3,21 +3,51 @@
 use core::marker::PhantomData;
 
 use bevy::{
-    app::{App, AppExit, PostStartup, Update},
+    app::{App, AppExit, Update},
     ecs::{
         error,
         resource::Resource,
-        system::{NonSendMut, ResMut},
+        system::NonSendMut,
     },
-    platform::prelude::{String, vec::Vec},
+    platform::prelude::{String, format, vec::Vec},
     prelude::{Deref, DerefMut},
 };
 use neonex_mockplatform::MockPlatform;
-use neonex_platform::{NeoNexConfig, NeoNexPlatform};
-use neonex_shared::{NeoNexStartupConfig, NeoNexStartupConfigSet};
+use neonex_platform::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, NeoNexPlatform, PlatformStartupConfigStore,
+    StartupConfigSetOf,
+};
+use neonex_shared::{NoStartupConfig, layers::override_args};
 use neonex_terminal::{RatatuiContext, TerminalContext};
 use ratatui::prelude::Backend;
 
+pub use crate::bundle::{
+    ApplyStartupConfigImport, ExportStartupConfig, PreviewStartupConfigImport,
+    ResetStartupConfig, StartupConfigBundleFailed, StartupConfigExported,
+    StartupConfigImportPreview, StartupConfigReset,
+};
+#[cfg(feature = "vault")]
+pub use crate::credentials::{
+    Credentials, CredentialsPlugin, CredentialsSaveFailed, CredentialsUnlockFailed,
+    CredentialsUnlocked, LockCredentials, OpenUnlockDialog, SaveCredentials, UnlockCredentials,
+    UnlockDialog, UnlockDialogAction, UnlockDialogClosed, UnlockDialogKeyPressed,
+};
+pub use crate::settings::{
+    OpenSettingsScreen, SettingsAction, SettingsKey, SettingsKeyPressed, SettingsScreen,
+    SettingsScreenClosed, SettingsScreenPlugin,
+};
+pub use crate::startup_config::{
+    SaveStartupConfig, StartupConfigChanged, StartupConfigInvalid, StartupConfigLayers,
+    StartupConfigLoadFailed, StartupConfigSaveFailed,
+};
+use crate::startup_config::insert_startup_config;
+
+mod bundle;
+#[cfg(feature = "vault")]
+mod credentials;
+mod settings;
+mod startup_config;
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "desktop")] {
         pub use neonex_desktop::DesktopPlatform as ActivePlatform;
@@ -41,6 +71,8 @@ pub struct DefaultNeoNexConfig;
 
 impl NeoNexConfig for DefaultNeoNexConfig {
     type Platform = ActivePlatform;
+    type StartupConfig = NoStartupConfig;
+    type StartupConfigStore = PlatformStartupConfigStore<Self>;
 }
 
 ///
@@ -49,22 +81,75 @@ pub struct NeoNexInstance<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
     pub app: App,
 }
 
+/// The startup config set seen by the app: the entries of its namespace merged over the global
+/// ones, see `NeoNexConfig::STARTUP_CONFIG_NAMESPACE`. Its modifications are saved to the
+/// namespace of the app.
+///
+/// The persisted entries of any namespace can also be addressed explicitly, e.g. by a
+/// launcher preparing the namespace of a target before starting it:
+/// ```ignore
+/// fn prepare_game(mut startup_config_set: ResMut<SCSWrapper<LauncherConfig>>) {
+///     startup_config_set.namespace_mut(Some("game")).set(LauncherConfig::Volume(8));
+/// }
+/// ```
+/// They're saved along with the set. When the set and its namespace are both modified, the
+/// modifications of the set win.
 #[derive(Resource, Deref, DerefMut)]
 pub struct SCSWrapper<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(
-    #[deref] pub NeoNexStartupConfigSet,
+    #[deref] pub StartupConfigSetOf<CONFIG>,
     pub PhantomData<CONFIG>,
+    pub(crate) NamespacedStartupConfigSetOf<CONFIG>,
 );
 
-impl<CONFIG: NeoNexConfig> From<NeoNexStartupConfigSet> for SCSWrapper<CONFIG> {
-    fn from(value: NeoNexStartupConfigSet) -> Self {
-        Self(value, PhantomData)
+impl<CONFIG: NeoNexConfig> SCSWrapper<CONFIG> {
+    /// Persisted entries of `namespace`, `None` being the global namespace.
+    pub fn namespace(&self, namespace: Option<&str>) -> Option<&StartupConfigSetOf<CONFIG>> {
+        self.2.get(namespace)
+    }
+
+    /// Persisted entries of `namespace`, creating it when needed.
+    pub fn namespace_mut(&mut self, namespace: Option<&str>) -> &mut StartupConfigSetOf<CONFIG> {
+        self.2.get_mut(namespace)
+    }
+
+    /// Removes a namespace along with its entries, returning them.
+    pub fn remove_namespace(&mut self, name: &str) -> Option<StartupConfigSetOf<CONFIG>> {
+        self.2.remove_namespace(name)
+    }
+
+    /// Every namespace, as it will be saved.
+    pub fn namespaces(&self) -> &NamespacedStartupConfigSetOf<CONFIG> {
+        &self.2
+    }
+
+    /// Arguments starting an app in `namespace` with its merged view, i.e. its entries merged
+    /// over the global ones as they will be saved:
+    /// ```ignore
+    /// Command::new(game_path)
+    ///     .args(startup_config_set.launch_args(Some("game")))
+    ///     .spawn()?;
+    /// ```
+    ///
+    /// The entries are handed as `--neonex-*` flags, so they win over what the app reads
+    /// itself until it exits. With the `integrity` feature, the entries of
+    /// `NeoNexConfig::STARTUP_CONFIG_ENCRYPTED_KEYS` are left out, as command lines are
+    /// readable by every user.
+    pub fn launch_args(&self, namespace: Option<&str>) -> Vec<String> {
+        #[cfg_attr(not(feature = "integrity"), allow(unused_mut))]
+        let mut merged = self.2.merged(namespace);
+        #[cfg(feature = "integrity")]
+        for key in CONFIG::STARTUP_CONFIG_ENCRYPTED_KEYS {
+            merged.remove_by_key(key);
+        }
+        let mut args = Vec::from([format!("--neonex-namespace={}", namespace.unwrap_or(""))]);
+        args.extend(override_args(&merged));
+        args
     }
 }
 
-impl<CONFIG: NeoNexConfig> Drop for SCSWrapper<CONFIG> {
-    fn drop(&mut self) {
-        // Update the NeoNexConfig before exiting bevy!
-        let _ = CONFIG::Platform::update_startup_config(self.0.clone());
+impl<CONFIG: NeoNexConfig> From<StartupConfigSetOf<CONFIG>> for SCSWrapper<CONFIG> {
+    fn from(value: StartupConfigSetOf<CONFIG>) -> Self {
+        Self(value, PhantomData, Default::default())
     }
 }
 
@@ -72,11 +157,11 @@ impl NeoNexInstance<DefaultNeoNexConfig> {
     pub fn new() -> Self {
         let mut app = App::new();
 
-        let startup_config_set = ActivePlatform::retrieve_startup_config();
+        // The platform may need to be prepared before the startup config can be retrieved.
+        ActivePlatform::pre_init::<DefaultNeoNexConfig>();
+
         // Insert the resource into bevy_ECS in order to modify it, and save the modified one into bevy when needed.
-        let resource: SCSWrapper = startup_config_set.clone().into();
-        app.insert_resource(resource);
-        app.add_systems(PostStartup, Self::add_scs);
+        let startup_config_set = insert_startup_config::<DefaultNeoNexConfig>(&mut app);
 
         Self::setup_bevy(&mut app, startup_config_set);
 
@@ -88,23 +173,17 @@ impl NeoNexInstance<DefaultNeoNexConfig> {
 }
 
 impl<CONFIG: NeoNexConfig> NeoNexInstance<CONFIG> {
-    fn add_scs(mut scs: ResMut<SCSWrapper<CONFIG>>) {
-        scs.values
-            .insert(NeoNexStartupConfig::Bla(String::from("BlaBlabllalalaa")));
-        scs.values.insert(NeoNexStartupConfig::Test1(19555));
-    }
-
     /// Inits a NeoNex Instance from a config struct.
     /// A NeoNexConfig item should be present in the context, or specified manually:
     /// let mut instance: NeoNexInstance<CustomNeoNexConfig> = NeoNexInstance::new_with_config();
     pub fn new_with_config() -> Self {
         let mut app = App::new();
 
-        let startup_config_set = ActivePlatform::retrieve_startup_config();
+        // The platform may need to be prepared before the startup config can be retrieved.
+        CONFIG::Platform::pre_init::<CONFIG>();
+
         // Insert the resource into bevy_ECS in order to modify it, and save the modified one into bevy when needed.
-        let resource: SCSWrapper<CONFIG> = startup_config_set.clone().into();
-        app.insert_resource(resource);
-        app.add_systems(PostStartup, Self::add_scs);
+        let startup_config_set = insert_startup_config::<CONFIG>(&mut app);
 
         Self::setup_bevy(&mut app, startup_config_set);
 
@@ -115,7 +194,7 @@ impl<CONFIG: NeoNexConfig> NeoNexInstance<CONFIG> {
     }
 
     /// Isn't intended to be public: wrapper around internal bevy init
-    fn setup_bevy(app: &mut App, startup_config_set: NeoNexStartupConfigSet) {
+    fn setup_bevy(app: &mut App, startup_config_set: StartupConfigSetOf<CONFIG>) {
         CONFIG::Platform::setup_bevy::<CONFIG>(app, startup_config_set)
             .expect("Unable to setup platform_specific bevy");
     }
@@ -136,3 +215,137 @@ pub type DefaultRatatuiContext = RatatuiContext<
     <ActivePlatform as NeoNexPlatform>::RatatuiContextGenerics,
     <ActivePlatform as NeoNexPlatform>::RatatuiContextBackend,
 >;
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::error::BevyError, platform::sync::Mutex};
+    use neonex_platform::{
+        MemoryStartupConfigStore, StartupConfigFormatError, StartupConfigLoadError,
+        StartupConfigStore,
+    };
+    use neonex_shared::{NeoNexStartupConfig, UserStartupConfig};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+    enum TestConfig {
+        Volume(u8),
+        Token(String),
+    }
+
+    struct TestApp;
+
+    impl NeoNexConfig for TestApp {
+        type Platform = MockPlatform;
+        type StartupConfig = TestConfig;
+        type StartupConfigStore = MemoryStartupConfigStore;
+        #[cfg(feature = "integrity")]
+        const STARTUP_CONFIG_ENCRYPTED_KEYS: &'static [&'static str] = &["Token"];
+    }
+
+    #[test]
+    fn launch_args_hand_the_merged_view() {
+        let mut startup_config_set = SCSWrapper::<TestApp>::from(
+            StartupConfigSetOf::<TestApp>::default(),
+        );
+        let global = startup_config_set.namespace_mut(None);
+        global.set(TestConfig::Volume(3));
+        global.set(NeoNexStartupConfig::NativeTerminal(true));
+        startup_config_set
+            .namespace_mut(Some("game"))
+            .set(TestConfig::Volume(8));
+
+        let mut args = startup_config_set.launch_args(Some("game"));
+        args.sort();
+        assert_eq!(
+            args,
+            [
+                "--neonex-namespace=game",
+                "--neonex-native-terminal=true",
+                "--neonex-volume=8",
+            ]
+        );
+        assert_eq!(startup_config_set.launch_args(None)[0], "--neonex-namespace=");
+    }
+
+    #[cfg(feature = "integrity")]
+    #[test]
+    fn launch_args_leave_the_encrypted_entries_out() {
+        let mut startup_config_set = SCSWrapper::<TestApp>::from(
+            StartupConfigSetOf::<TestApp>::default(),
+        );
+        let global = startup_config_set.namespace_mut(None);
+        global.set(TestConfig::Volume(3));
+        global.set(TestConfig::Token("hunter2".into()));
+
+        assert_eq!(
+            startup_config_set.launch_args(None),
+            ["--neonex-namespace=", "--neonex-volume=3"]
+        );
+    }
+
+    /// Steps of the launch of [`PreparedApp`], in the order they ran.
+    static STEPS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    struct RecordingStore;
+
+    impl StartupConfigStore for RecordingStore {
+        type Error = StartupConfigFormatError;
+
+        fn load<CONFIG: NeoNexConfig>() -> Result<
+            NamespacedStartupConfigSetOf<CONFIG>,
+            StartupConfigLoadError<StartupConfigFormatError>,
+        > {
+            STEPS.lock().unwrap().push("load");
+            MemoryStartupConfigStore::load::<CONFIG>()
+        }
+
+        fn save<CONFIG: NeoNexConfig>(
+            startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+        ) -> Result<(), StartupConfigFormatError> {
+            MemoryStartupConfigStore::save::<CONFIG>(startup_config_set)
+        }
+    }
+
+    struct RecordingPlatform;
+
+    impl NeoNexPlatform for RecordingPlatform {
+        const PLATFORM: &'static str = "Recording";
+
+        type RatatuiContextBackend = <MockPlatform as NeoNexPlatform>::RatatuiContextBackend;
+        type RatatuiContextGenerics = <MockPlatform as NeoNexPlatform>::RatatuiContextGenerics;
+        type StartupConfigRetrieveKeyType = ();
+        type StartupConfigStore = RecordingStore;
+        type StorageError = StartupConfigFormatError;
+
+        fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() {}
+
+        fn pre_init<CONFIG: NeoNexConfig>() {
+            STEPS.lock().unwrap().push("pre_init");
+        }
+
+        fn setup_bevy<CONFIG: NeoNexConfig>(
+            _app: &mut App,
+            _startup_config_set: StartupConfigSetOf<CONFIG>,
+        ) -> Result<(), BevyError> {
+            STEPS.lock().unwrap().push("setup_bevy");
+            Ok(())
+        }
+    }
+
+    struct PreparedApp;
+
+    impl NeoNexConfig for PreparedApp {
+        type Platform = RecordingPlatform;
+        type StartupConfig = TestConfig;
+        type StartupConfigStore = PlatformStartupConfigStore<Self>;
+        const APP_ID: &'static str = "org.neonex.test.pre-init";
+    }
+
+    #[test]
+    fn the_platform_is_prepared_before_the_startup_config_is_retrieved() {
+        NeoNexInstance::<PreparedApp>::new_with_config();
+        assert_eq!(*STEPS.lock().unwrap(), ["pre_init", "load", "setup_bevy"]);
+    }
+}
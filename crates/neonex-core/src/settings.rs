@@ -0,0 +1,653 @@
+//! Settings screen editing the startup config, generated from its schema: the fields of
+//! [`NEONEX_STARTUP_CONFIG_SCHEMA`] followed by the ones of `UserStartupConfig::SCHEMA`.
+//!
+//! Input differs between platforms, so the app maps its key presses to [`SettingsKey`]s,
+//! then draws the screen with its terminal:
+//! ```ignore
+//! app.add_plugins(SettingsScreenPlugin::<LauncherConfig>::default());
+//!
+//! fn open_settings(mut commands: Commands) {
+//!     commands.queue(OpenSettingsScreen::<LauncherConfig>::default());
+//! }
+//!
+//! fn forward_keys(mut keys: EventReader<KeyEvent>, mut settings: EventWriter<SettingsKeyPressed>) {
+//!     for key in keys.read() {
+//!         let key = match key.code {
+//!             KeyCode::Up => SettingsKey::Up,
+//!             KeyCode::Char(c) => SettingsKey::Char(c),
+//!             // ...
+//!         };
+//!         settings.write(SettingsKeyPressed(key));
+//!     }
+//! }
+//!
+//! fn draw(mut context: NonSendMut<DefaultRatatuiContext>, settings: Option<Res<SettingsScreen<LauncherConfig>>>) {
+//!     context.draw(|frame| {
+//!         if let Some(settings) = &settings {
+//!             frame.render_widget(&**settings, frame.area());
+//!         }
+//!     });
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+use bevy::{
+    app::{App, Plugin, Update},
+    ecs::{
+        event::{Event, EventReader, EventWriter},
+        resource::Resource,
+        system::{Command, Commands, ResMut},
+        world::World,
+    },
+    platform::prelude::{String, ToString, Vec, format},
+};
+use neonex_platform::NeoNexConfig;
+use neonex_shared::{
+    NeoNexStartupConfig, NeoNexStartupConfigSet, UserStartupConfig,
+    schema::{NEONEX_STARTUP_CONFIG_SCHEMA, StartupConfigField, StartupConfigFieldKind},
+};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Widget},
+};
+use serde_json::{Map, Number, Value};
+
+use crate::{DefaultNeoNexConfig, SCSWrapper};
+
+/// Key presses the settings screen reacts to.
+///
+/// Up and Down move between the fields and the Apply/Cancel buttons. Left and Right change
+/// toggles, numbers and choices, or select a button. Enter flips toggles and presses the
+/// selected button, Escape cancels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsKey {
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Escape,
+    Backspace,
+    Char(char),
+}
+
+/// Sent by the app for each key press while the [`SettingsScreen`] is open.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SettingsKeyPressed(pub SettingsKey);
+
+/// Sent once the [`SettingsScreen`] has been closed, after its modifications have been
+/// written to [`SCSWrapper`] when `applied`.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SettingsScreenClosed {
+    pub applied: bool,
+}
+
+/// What the settings screen asks for after a key press.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsAction {
+    Edit,
+    Apply,
+    Cancel,
+}
+
+/// State of the settings screen, present while it's open. Drawn as a ratatui widget.
+#[derive(Resource)]
+pub struct SettingsScreen<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
+    fields: Vec<FieldState>,
+    /// Index of the focused field, the buttons coming after the last field.
+    focus: usize,
+    /// Whether Apply, rather than Cancel, is selected when the buttons are focused.
+    apply_selected: bool,
+    _config: PhantomData<CONFIG>,
+}
+
+struct FieldState {
+    field: StartupConfigField,
+    value: FieldValue,
+    /// Only the modified fields are written, so that unset entries stay unset.
+    modified: bool,
+    error: Option<String>,
+}
+
+/// The edited value of a field, matching the kind of the field.
+enum FieldValue {
+    Toggle(bool),
+    /// Integers and floats are typed as text, and checked on apply.
+    Number(String),
+    Text(String),
+    Choice(usize),
+}
+
+impl<CONFIG: NeoNexConfig> SettingsScreen<CONFIG> {
+    /// Opens the screen with the values of `startup_config_set`.
+    pub fn new(startup_config_set: &NeoNexStartupConfigSet<CONFIG::StartupConfig>) -> Self {
+        let fields = NEONEX_STARTUP_CONFIG_SCHEMA
+            .iter()
+            .chain(CONFIG::StartupConfig::SCHEMA)
+            .map(|field| FieldState {
+                field: *field,
+                value: FieldValue::read(field, startup_config_set),
+                modified: false,
+                error: None,
+            })
+            .collect();
+        Self {
+            fields,
+            focus: 0,
+            apply_selected: true,
+            _config: PhantomData,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: SettingsKey) -> SettingsAction {
+        let buttons = self.fields.len();
+        match key {
+            SettingsKey::Escape => return SettingsAction::Cancel,
+            SettingsKey::Up => self.focus = self.focus.saturating_sub(1),
+            SettingsKey::Down => self.focus = (self.focus + 1).min(buttons),
+            SettingsKey::Enter if self.focus == buttons => {
+                return match self.apply_selected {
+                    true => SettingsAction::Apply,
+                    false => SettingsAction::Cancel,
+                };
+            }
+            SettingsKey::Left | SettingsKey::Right if self.focus == buttons => {
+                self.apply_selected = key == SettingsKey::Left;
+            }
+            _ if self.focus == buttons => {}
+            _ => {
+                let field = &mut self.fields[self.focus];
+                if field.edit(key) {
+                    field.modified = true;
+                    field.error = None;
+                } else if key == SettingsKey::Enter {
+                    self.focus += 1;
+                }
+            }
+        }
+        SettingsAction::Edit
+    }
+
+    /// The entries of the modified fields, to be set in `startup_config_set`. When some
+    /// fields are invalid, or break their validation rules once set, returns `None` and
+    /// shows why next to them.
+    pub fn entries_to_apply(
+        &mut self,
+        startup_config_set: &NeoNexStartupConfigSet<CONFIG::StartupConfig>,
+    ) -> Option<Vec<NeoNexStartupConfig<CONFIG::StartupConfig>>> {
+        let mut entries = Vec::new();
+        for field in &mut self.fields {
+            field.error = None;
+            if field.modified {
+                match field.entry() {
+                    Ok(entry) => entries.push(entry),
+                    Err(error) => field.error = Some(error),
+                }
+            }
+        }
+
+        let mut applied = startup_config_set.clone();
+        for entry in &entries {
+            applied.set(entry.clone());
+        }
+        // The violations that were already there don't come from the modifications.
+        let existing = startup_config_set.validate();
+        for violation in applied.validate() {
+            if existing.contains(&violation) {
+                continue;
+            }
+            if let Some(field) = self
+                .fields
+                .iter_mut()
+                .find(|field| field.field.key == violation.key)
+            {
+                field.error.get_or_insert(violation.message);
+            }
+        }
+
+        self.fields
+            .iter()
+            .all(|field| field.error.is_none())
+            .then_some(entries)
+    }
+}
+
+impl FieldValue {
+    fn read<U: UserStartupConfig>(
+        field: &StartupConfigField,
+        startup_config_set: &NeoNexStartupConfigSet<U>,
+    ) -> Self {
+        // Entries are serialized as `{"Key": value}`.
+        let value = startup_config_set
+            .get_by_key(field.key)
+            .and_then(|entry| serde_json::to_value(entry).ok())
+            .and_then(|entry| match entry {
+                Value::Object(mut entry) => entry.remove(field.key),
+                _ => None,
+            });
+
+        match field.kind {
+            StartupConfigFieldKind::Toggle => {
+                FieldValue::Toggle(value.and_then(|value| value.as_bool()).unwrap_or(false))
+            }
+            StartupConfigFieldKind::Integer { min, max } => FieldValue::Number(
+                value
+                    .and_then(|value| value.as_i64())
+                    .unwrap_or(0_i64.clamp(min, max))
+                    .to_string(),
+            ),
+            StartupConfigFieldKind::Float { min, max, step } => FieldValue::Number(format_float(
+                value
+                    .and_then(|value| value.as_f64())
+                    .unwrap_or(0_f64.clamp(min, max)),
+                step,
+            )),
+            StartupConfigFieldKind::Text => FieldValue::Text(
+                value
+                    .as_ref()
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            StartupConfigFieldKind::Choice(options) => FieldValue::Choice(
+                value
+                    .as_ref()
+                    .and_then(|value| value.as_str())
+                    .and_then(|value| options.iter().position(|option| *option == value))
+                    .unwrap_or(0),
+            ),
+        }
+    }
+}
+
+impl FieldState {
+    /// Applies `key` to the value, returning whether it changed.
+    fn edit(&mut self, key: SettingsKey) -> bool {
+        match (&mut self.value, self.field.kind, key) {
+            (
+                FieldValue::Toggle(value),
+                _,
+                SettingsKey::Left
+                | SettingsKey::Right
+                | SettingsKey::Enter
+                | SettingsKey::Char(' '),
+            ) => *value = !*value,
+            (
+                FieldValue::Number(text),
+                StartupConfigFieldKind::Integer { min, max },
+                SettingsKey::Left | SettingsKey::Right,
+            ) => {
+                let value = text.trim().parse().unwrap_or(0_i64.clamp(min, max));
+                let value = match key {
+                    SettingsKey::Left => value.saturating_sub(1),
+                    _ => value.saturating_add(1),
+                };
+                *text = value.clamp(min, max).to_string();
+            }
+            (
+                FieldValue::Number(text),
+                StartupConfigFieldKind::Float { min, max, step },
+                SettingsKey::Left | SettingsKey::Right,
+            ) => {
+                let value = text.trim().parse().unwrap_or(0_f64.clamp(min, max));
+                let value = match key {
+                    SettingsKey::Left => value - step,
+                    _ => value + step,
+                };
+                *text = format_float(value.clamp(min, max), step);
+            }
+            (FieldValue::Number(text), _, SettingsKey::Char(c))
+                if c.is_ascii_digit() || c == '-' || c == '.' =>
+            {
+                text.push(c)
+            }
+            (FieldValue::Number(text) | FieldValue::Text(text), _, SettingsKey::Backspace) => {
+                return text.pop().is_some();
+            }
+            (FieldValue::Text(text), _, SettingsKey::Char(c)) => text.push(c),
+            (
+                FieldValue::Choice(index),
+                StartupConfigFieldKind::Choice(options),
+                SettingsKey::Left | SettingsKey::Right,
+            ) if !options.is_empty() => {
+                *index = match key {
+                    SettingsKey::Left => (*index + options.len() - 1) % options.len(),
+                    _ => (*index + 1) % options.len(),
+                };
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// The entry holding the value, or why the value is invalid.
+    fn entry<U: UserStartupConfig>(&self) -> Result<NeoNexStartupConfig<U>, String> {
+        let value = match (&self.value, self.field.kind) {
+            (FieldValue::Toggle(value), _) => Value::Bool(*value),
+            (FieldValue::Number(text), StartupConfigFieldKind::Integer { .. }) => {
+                let value: i64 = text
+                    .trim()
+                    .parse()
+                    .map_err(|_| "must be a whole number".to_string())?;
+                Value::from(value)
+            }
+            (FieldValue::Number(text), StartupConfigFieldKind::Float { .. }) => {
+                let value: f64 = text
+                    .trim()
+                    .parse()
+                    .map_err(|_| "must be a number".to_string())?;
+                Number::from_f64(value).map_or(Value::Null, Value::Number)
+            }
+            (FieldValue::Text(text), _) => Value::String(text.clone()),
+            (FieldValue::Choice(index), StartupConfigFieldKind::Choice(options)) => {
+                let option = options.get(*index).ok_or("has no option".to_string())?;
+                Value::String(option.to_string())
+            }
+            _ => unreachable!("the value always matches the kind of its field"),
+        };
+
+        let entry = Value::Object(Map::from_iter([(self.field.key.to_string(), value)]));
+        serde_json::from_value(entry).map_err(|_| "invalid value".to_string())
+    }
+
+    fn display(&self, focused: bool) -> String {
+        match (&self.value, self.field.kind) {
+            (FieldValue::Toggle(true), _) => "[x]".to_string(),
+            (FieldValue::Toggle(false), _) => "[ ]".to_string(),
+            (FieldValue::Number(text), StartupConfigFieldKind::Integer { min, max }) => {
+                format!("< {text} >  ({min} to {max})")
+            }
+            (FieldValue::Number(text), StartupConfigFieldKind::Float { min, max, .. }) => {
+                format!("< {text} >  ({min} to {max})")
+            }
+            (FieldValue::Text(text), _) if focused => format!("{text}_"),
+            (FieldValue::Text(text), _) => text.clone(),
+            (FieldValue::Choice(index), StartupConfigFieldKind::Choice(options)) => {
+                format!("< {} >", options.get(*index).copied().unwrap_or_default())
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/// `value` with as many decimals as `step`.
+fn format_float(value: f64, step: f64) -> String {
+    let step = step.to_string();
+    let decimals = step
+        .split_once('.')
+        .map_or(0, |(_, decimals)| decimals.len());
+    format!("{value:.decimals$}")
+}
+
+impl<CONFIG: NeoNexConfig> Widget for &SettingsScreen<CONFIG> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let focused = Style::default().add_modifier(Modifier::REVERSED);
+        let label_width = self
+            .fields
+            .iter()
+            .map(|field| field.field.label.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = Vec::new();
+        for (index, field) in self.fields.iter().enumerate() {
+            let is_focused = index == self.focus;
+            let value = field.display(is_focused);
+            lines.push(Line::from(Vec::from([
+                Span::raw(format!("{:label_width$}  ", field.field.label)),
+                match is_focused {
+                    true => Span::styled(value, focused),
+                    false => Span::raw(value),
+                },
+            ])));
+            if let Some(error) = &field.error {
+                lines.push(Line::styled(
+                    format!("{:label_width$}  {error}", ""),
+                    Style::default().fg(Color::Red),
+                ));
+            }
+        }
+
+        let button = |label: &'static str, selected: bool| match selected {
+            true if self.focus == self.fields.len() => Span::styled(label, focused),
+            true => Span::styled(label, Style::default().add_modifier(Modifier::BOLD)),
+            false => Span::raw(label),
+        };
+        lines.push(Line::default());
+        lines.push(Line::from(Vec::from([
+            button("[ Apply ]", self.apply_selected),
+            Span::raw("  "),
+            button("[ Cancel ]", !self.apply_selected),
+        ])));
+
+        Paragraph::new(lines)
+            .style(
+                Style::default()
+                    .fg(CONFIG::DEFAULT_FOREGROUND_COLOR)
+                    .bg(CONFIG::DEFAULT_BACKGROUND_COLOR),
+            )
+            .block(Block::bordered().title(" Settings "))
+            .render(area, buf);
+    }
+}
+
+/// Opens the [`SettingsScreen`] with the current values of [`SCSWrapper`]:
+/// ```ignore
+/// commands.queue(OpenSettingsScreen::<LauncherConfig>::default());
+/// ```
+pub struct OpenSettingsScreen<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for OpenSettingsScreen<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for OpenSettingsScreen<CONFIG> {
+    fn apply(self, world: &mut World) {
+        let screen = SettingsScreen::<CONFIG>::new(&world.resource::<SCSWrapper<CONFIG>>().0);
+        world.insert_resource(screen);
+    }
+}
+
+/// Feeds the [`SettingsKeyPressed`] events to the open [`SettingsScreen`], writing its
+/// modifications to [`SCSWrapper`] on Apply.
+pub struct SettingsScreenPlugin<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for SettingsScreenPlugin<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Plugin for SettingsScreenPlugin<CONFIG> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SettingsKeyPressed>()
+            .add_event::<SettingsScreenClosed>()
+            .add_systems(Update, update_settings_screen::<CONFIG>);
+    }
+}
+
+fn update_settings_screen<CONFIG: NeoNexConfig>(
+    mut keys: EventReader<SettingsKeyPressed>,
+    screen: Option<ResMut<SettingsScreen<CONFIG>>>,
+    mut startup_config_set: ResMut<SCSWrapper<CONFIG>>,
+    mut closed: EventWriter<SettingsScreenClosed>,
+    mut commands: Commands,
+) {
+    let Some(mut screen) = screen else {
+        keys.clear();
+        return;
+    };
+
+    for SettingsKeyPressed(key) in keys.read() {
+        let applied = match screen.handle_key(*key) {
+            SettingsAction::Edit => continue,
+            SettingsAction::Apply => {
+                let Some(entries) = screen.entries_to_apply(&startup_config_set.0) else {
+                    continue;
+                };
+                for entry in entries {
+                    startup_config_set.set(entry);
+                }
+                true
+            }
+            SettingsAction::Cancel => false,
+        };
+        commands.remove_resource::<SettingsScreen<CONFIG>>();
+        closed.write(SettingsScreenClosed { applied });
+        break;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use neonex_mockplatform::MockPlatform;
+    use neonex_platform::MemoryStartupConfigStore;
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+    enum TestConfig {
+        #[startup_config(min = 0, max = 10)]
+        Volume(u8),
+        Username(String),
+    }
+
+    struct TestApp;
+
+    impl NeoNexConfig for TestApp {
+        type Platform = MockPlatform;
+        type StartupConfig = TestConfig;
+        type StartupConfigStore = MemoryStartupConfigStore;
+    }
+
+    /// Index of the buttons, after `NativeTerminal`, `Volume` and `Username`.
+    const BUTTONS: usize = 3;
+
+    fn screen() -> SettingsScreen<TestApp> {
+        SettingsScreen::new(&NeoNexStartupConfigSet::default())
+    }
+
+    fn press(screen: &mut SettingsScreen<TestApp>, keys: &[SettingsKey]) -> SettingsAction {
+        keys.iter()
+            .map(|key| screen.handle_key(*key))
+            .last()
+            .unwrap_or(SettingsAction::Edit)
+    }
+
+    #[test]
+    fn focus_stays_between_the_first_field_and_the_buttons() {
+        let mut screen = screen();
+        press(&mut screen, &[SettingsKey::Up]);
+        assert_eq!(screen.focus, 0);
+        press(&mut screen, &[SettingsKey::Down; BUTTONS + 2]);
+        assert_eq!(screen.focus, BUTTONS);
+    }
+
+    #[test]
+    fn buttons_apply_or_cancel() {
+        let mut screen = screen();
+        press(&mut screen, &[SettingsKey::Down; BUTTONS]);
+        assert_eq!(
+            press(&mut screen, &[SettingsKey::Enter]),
+            SettingsAction::Apply
+        );
+        assert_eq!(
+            press(&mut screen, &[SettingsKey::Right, SettingsKey::Enter]),
+            SettingsAction::Cancel
+        );
+        assert_eq!(
+            press(&mut screen, &[SettingsKey::Left, SettingsKey::Enter]),
+            SettingsAction::Apply
+        );
+        assert_eq!(
+            press(&mut screen, &[SettingsKey::Escape]),
+            SettingsAction::Cancel
+        );
+    }
+
+    #[test]
+    fn enter_moves_to_the_next_field_unless_it_edits() {
+        let mut screen = screen();
+        // Flips the toggle.
+        press(&mut screen, &[SettingsKey::Enter]);
+        assert_eq!(screen.focus, 0);
+        // Numbers aren't edited by Enter.
+        press(&mut screen, &[SettingsKey::Down, SettingsKey::Enter]);
+        assert_eq!(screen.focus, 2);
+    }
+
+    #[test]
+    fn only_modified_fields_are_applied() {
+        let mut screen = screen();
+        press(
+            &mut screen,
+            &[
+                SettingsKey::Down,
+                SettingsKey::Right,
+                SettingsKey::Right,
+                SettingsKey::Down,
+                SettingsKey::Char('a'),
+            ],
+        );
+
+        let entries = screen
+            .entries_to_apply(&NeoNexStartupConfigSet::default())
+            .unwrap();
+        assert_eq!(
+            entries,
+            [
+                NeoNexStartupConfig::User(TestConfig::Volume(2)),
+                NeoNexStartupConfig::User(TestConfig::Username("a".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_are_clamped_by_the_arrows() {
+        let mut screen = screen();
+        press(&mut screen, &[SettingsKey::Down, SettingsKey::Left]);
+        let entries = screen
+            .entries_to_apply(&NeoNexStartupConfigSet::default())
+            .unwrap();
+        assert_eq!(entries, [NeoNexStartupConfig::User(TestConfig::Volume(0))]);
+    }
+
+    #[test]
+    fn invalid_fields_are_not_applied() {
+        let mut screen = screen();
+        press(
+            &mut screen,
+            &[
+                SettingsKey::Down,
+                SettingsKey::Backspace,
+                SettingsKey::Char('1'),
+                SettingsKey::Char('2'),
+            ],
+        );
+        assert_eq!(
+            screen.entries_to_apply(&NeoNexStartupConfigSet::default()),
+            None
+        );
+        assert!(screen.fields[1].error.is_some());
+
+        press(
+            &mut screen,
+            &[SettingsKey::Backspace, SettingsKey::Char('-')],
+        );
+        assert_eq!(
+            screen.entries_to_apply(&NeoNexStartupConfigSet::default()),
+            None
+        );
+        assert_eq!(
+            screen.fields[1].error.as_deref(),
+            Some("must be a whole number")
+        );
+    }
+}
@@ -0,0 +1,803 @@
+use core::{marker::PhantomData, time::Duration};
+
+use bevy::{
+    app::{App, AppExit, Last, PostUpdate, PreUpdate},
+    ecs::{
+        change_detection::DetectChanges,
+        event::{Event, EventReader, EventWriter},
+        resource::Resource,
+        system::{Command, Commands, Local, Res, ResMut},
+        world::World,
+    },
+    platform::prelude::{String, ToString, Vec},
+    prelude::Deref,
+    time::{Real, Time},
+};
+use neonex_platform::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, NeoNexPlatform, StartupConfigConflictPolicy,
+    StartupConfigSetOf, StartupConfigWatcher,
+};
+use neonex_shared::{
+    NeoNexStartupConfigSet, NoStartupConfig, StartupConfigChange, UserStartupConfig,
+    layers::{
+        LayeredStartupConfigSet, StartupConfigLayer, parse_arg_overrides, parse_env_overrides,
+        parse_namespace_override,
+    },
+    namespaces::NamespacedStartupConfigSet,
+    validation::StartupConfigViolation,
+};
+
+use crate::{
+    DefaultNeoNexConfig, SCSWrapper,
+    bundle::{StartupConfigBundleFailed, StartupConfigExported, StartupConfigReset},
+};
+
+/// Sent once the startup config has been reloaded after being modified outside of the app,
+/// or replaced by an import or a reset, with the entries of [`SCSWrapper`] that changed.
+///
+/// `U` is the `NeoNexConfig::StartupConfig` of the app.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigChanged<U: UserStartupConfig = NoStartupConfig> {
+    pub changes: Vec<StartupConfigChange<U>>,
+}
+
+/// Sent when a layer of the startup config couldn't be retrieved, with the reason given by the
+/// platform. The layer starts empty instead, or keeps its current entries on reload.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigLoadFailed {
+    pub error: String,
+}
+
+/// Sent when entries of the startup config break their validation rules, see
+/// `neonex_shared::validation`.
+///
+/// Invalid entries retrieved from the platform are left out of [`SCSWrapper`], falling back
+/// to the lower layers and eventually to their defaults, but stay persisted. Invalid entries
+/// of [`SCSWrapper`] aren't saved, the previously saved entries being kept instead.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigInvalid {
+    pub violations: Vec<StartupConfigViolation>,
+}
+
+/// Sent when saving the startup config failed, with the reason given by the platform.
+#[derive(Event, Debug, Clone)]
+pub struct StartupConfigSaveFailed {
+    pub error: String,
+}
+
+/// Saves the startup config set of [`SCSWrapper`] right away:
+/// ```ignore
+/// commands.queue(SaveStartupConfig::<LauncherConfig>::default());
+/// ```
+///
+/// When it fails, a [`StartupConfigSaveFailed`] event is sent.
+pub struct SaveStartupConfig<CONFIG: NeoNexConfig = DefaultNeoNexConfig>(PhantomData<CONFIG>);
+
+impl<CONFIG: NeoNexConfig> Default for SaveStartupConfig<CONFIG> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<CONFIG: NeoNexConfig> Command for SaveStartupConfig<CONFIG> {
+    fn apply(self, world: &mut World) {
+        save_startup_config::<CONFIG>(world);
+    }
+}
+
+/// Returns whether the startup config has been saved.
+pub(crate) fn save_startup_config<CONFIG: NeoNexConfig>(world: &mut World) -> bool {
+    let layers = world.resource::<StartupConfigLayers<CONFIG>>();
+    if layers.read_only {
+        world.send_event(StartupConfigSaveFailed {
+            error: "the startup config can't be read by this version of the app".to_string(),
+        });
+        return false;
+    }
+    let mut startup_config_set = world.resource::<SCSWrapper<CONFIG>>().0.clone();
+    // The invalid entries keep the value they were loaded with.
+    let violations = startup_config_set.validate();
+    let persisted = layers.effective();
+    for violation in &violations {
+        match persisted.get_by_key(violation.key) {
+            Some(entry) => startup_config_set.set(entry.clone()),
+            None => startup_config_set.remove_by_key(violation.key),
+        };
+    }
+    let user_layer = layers.user_layer_for(&startup_config_set);
+    let changes = layers.layer(StartupConfigLayer::User).diff(&user_layer);
+    // The namespaces as modified explicitly, then with the modifications of the set.
+    let mut store = world.resource::<SCSWrapper<CONFIG>>().2.clone();
+    store.apply(layers.namespace(), &changes);
+    if !violations.is_empty() {
+        world.send_event(StartupConfigInvalid { violations });
+    }
+    match CONFIG::Platform::update_startup_config::<CONFIG>(store.clone()) {
+        Ok(()) => {
+            let mut layers = world.resource_mut::<StartupConfigLayers<CONFIG>>();
+            let user_layer = store.merged(layers.namespace());
+            layers
+                .layers
+                .set_layer(StartupConfigLayer::User, user_layer);
+            layers.persisted = store.clone();
+            let effective = layers.effective();
+
+            // The explicit modifications of the global namespace, or of the one of the app,
+            // show up in the set.
+            let mut startup_config_set = world.resource_mut::<SCSWrapper<CONFIG>>();
+            let merged = merge(
+                &persisted,
+                &startup_config_set.0,
+                &effective,
+                StartupConfigConflictPolicy::PreferApp,
+            );
+            if merged != startup_config_set.0 {
+                startup_config_set.0 = merged;
+            }
+            if store != startup_config_set.2 {
+                startup_config_set.2 = store;
+            }
+            true
+        }
+        Err(error) => {
+            world.send_event(StartupConfigSaveFailed {
+                error: error.to_string(),
+            });
+            false
+        }
+    }
+}
+
+/// Writes `store` in place of every persisted namespace, e.g. when importing a bundle, and
+/// makes [`SCSWrapper`] its effective set, dropping its unsaved modifications. Returns
+/// whether it has been written.
+pub(crate) fn replace_startup_config<CONFIG: NeoNexConfig>(
+    world: &mut World,
+    store: NamespacedStartupConfigSetOf<CONFIG>,
+) -> bool {
+    if let Err(error) = CONFIG::Platform::update_startup_config::<CONFIG>(store.clone()) {
+        world.send_event(StartupConfigSaveFailed {
+            error: error.to_string(),
+        });
+        return false;
+    }
+
+    let mut layers = world.resource_mut::<StartupConfigLayers<CONFIG>>();
+    let user_layer = store.merged(layers.namespace());
+    layers
+        .layers
+        .set_layer(StartupConfigLayer::User, user_layer);
+    layers.persisted = store.clone();
+    layers.read_only = false;
+    let violations = layers.layers.skip_invalid();
+    let effective = layers.effective();
+
+    let mut startup_config_set = world.resource_mut::<SCSWrapper<CONFIG>>();
+    startup_config_set.2 = store;
+    let changes = startup_config_set.0.diff(&effective);
+    if !changes.is_empty() {
+        startup_config_set.0 = effective;
+        world.send_event(StartupConfigChanged::<CONFIG::StartupConfig> { changes });
+    }
+    if !violations.is_empty() {
+        world.send_event(StartupConfigInvalid { violations });
+    }
+    true
+}
+
+/// The layers the startup config set of [`SCSWrapper`] has been merged from, as they were
+/// last read from, or written to, the platform. Tells which layer each entry comes from:
+/// ```ignore
+/// fn show_volume_source(layers: Res<StartupConfigLayers<LauncherConfig>>) {
+///     if layers.source(Volume::KEY) == Some(StartupConfigLayer::CommandLine) {
+///         info!("the volume is set from the command line");
+///     }
+/// }
+/// ```
+///
+/// Only the user layer is written back: it receives the modifications of [`SCSWrapper`].
+/// It's the persisted namespace of the app merged over the global one.
+#[derive(Resource, Deref)]
+pub struct StartupConfigLayers<CONFIG: NeoNexConfig = DefaultNeoNexConfig> {
+    #[deref]
+    layers: LayeredStartupConfigSet<CONFIG::StartupConfig>,
+    namespace: Option<String>,
+    /// Every namespace, as last read from, or written to, the platform.
+    pub(crate) persisted: NamespacedStartupConfigSetOf<CONFIG>,
+    /// Whether the persisted startup config can't be read by this version of the app, e.g.
+    /// written by a newer one, in which case it isn't saved over.
+    pub(crate) read_only: bool,
+    _config: PhantomData<CONFIG>,
+}
+
+impl<CONFIG: NeoNexConfig> StartupConfigLayers<CONFIG> {
+    /// Namespace of the app, from `--neonex-namespace`, `NEONEX_NAMESPACE` or
+    /// `NeoNexConfig::STARTUP_CONFIG_NAMESPACE`. `None` is the global namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+}
+
+/// Retrieves the layers of the startup config and inserts their merged set into the app,
+/// then reloads the user layer whenever the platform notices that it has been modified
+/// outside of the app.
+///
+/// It's saved once it stays unmodified for `NeoNexConfig::STARTUP_CONFIG_AUTOSAVE_DELAY`,
+/// and when the app exits.
+pub(crate) fn insert_startup_config<CONFIG: NeoNexConfig>(
+    app: &mut App,
+) -> StartupConfigSetOf<CONFIG> {
+    app.add_event::<StartupConfigChanged<CONFIG::StartupConfig>>()
+        .add_event::<StartupConfigLoadFailed>()
+        .add_event::<StartupConfigInvalid>()
+        .add_event::<StartupConfigSaveFailed>()
+        .add_event::<StartupConfigExported>()
+        .add_event::<StartupConfigBundleFailed>()
+        .add_event::<StartupConfigReset>()
+        .add_systems(Last, save_startup_config_on_exit::<CONFIG>);
+
+    let vars = CONFIG::Platform::startup_config_env_vars();
+    let args = CONFIG::Platform::startup_config_args::<CONFIG>();
+    let namespace = parse_namespace_override(&vars, &args)
+        .unwrap_or_else(|| CONFIG::STARTUP_CONFIG_NAMESPACE.map(String::from));
+
+    let mut layers = LayeredStartupConfigSet::default();
+    let mut persisted = NamespacedStartupConfigSet::default();
+    let mut read_only = false;
+    let mut errors = Vec::new();
+    layers.set_layer(
+        StartupConfigLayer::Default,
+        CONFIG::default_startup_config(),
+    );
+    match CONFIG::Platform::retrieve_system_startup_config::<CONFIG>() {
+        Ok(system) => layers.set_layer(
+            StartupConfigLayer::System,
+            system.merged(namespace.as_deref()),
+        ),
+        Err(error) => errors.push(error.to_string()),
+    }
+    match CONFIG::Platform::retrieve_startup_config::<CONFIG>() {
+        Ok(user) => {
+            layers.set_layer(StartupConfigLayer::User, user.merged(namespace.as_deref()));
+            persisted = user;
+        }
+        Err(error) => {
+            read_only = error.is_left_untouched();
+            errors.push(error.to_string());
+        }
+    }
+    match CONFIG::Platform::retrieve_enforced_startup_config::<CONFIG>() {
+        Ok(enforced) => layers.set_layer(
+            StartupConfigLayer::Enforced,
+            enforced.merged(namespace.as_deref()),
+        ),
+        Err(error) => errors.push(error.to_string()),
+    }
+    let (env, env_errors) = parse_env_overrides(vars);
+    layers.set_layer(StartupConfigLayer::Environment, env);
+    let (args, arg_errors) = parse_arg_overrides(args);
+    layers.set_layer(StartupConfigLayer::CommandLine, args);
+    errors.extend(
+        env_errors
+            .iter()
+            .chain(&arg_errors)
+            .map(ToString::to_string),
+    );
+
+    for error in errors {
+        app.world_mut()
+            .send_event(StartupConfigLoadFailed { error });
+    }
+    let violations = layers.skip_invalid();
+    if !violations.is_empty() {
+        app.world_mut()
+            .send_event(StartupConfigInvalid { violations });
+    }
+
+    let startup_config_set = layers.effective();
+    app.insert_resource(SCSWrapper::<CONFIG>(
+        startup_config_set.clone(),
+        PhantomData,
+        persisted.clone(),
+    ))
+    .insert_resource(StartupConfigLayers::<CONFIG> {
+        layers,
+        namespace,
+        persisted,
+        read_only,
+        _config: PhantomData,
+    });
+
+    if CONFIG::STARTUP_CONFIG_AUTOSAVE_DELAY.is_some() {
+        app.add_systems(PostUpdate, autosave_startup_config::<CONFIG>);
+    }
+
+    if let Some(watcher) = CONFIG::Platform::watch_startup_config::<CONFIG>() {
+        app.insert_resource(watcher)
+            .add_systems(PreUpdate, reload_startup_config::<CONFIG>);
+    }
+
+    startup_config_set
+}
+
+/// Saves the startup config once it hasn't been modified for the autosave delay.
+fn autosave_startup_config<CONFIG: NeoNexConfig>(
+    startup_config_set: Res<SCSWrapper<CONFIG>>,
+    layers: Res<StartupConfigLayers<CONFIG>>,
+    time: Res<Time<Real>>,
+    mut dirty_since: Local<Option<Duration>>,
+    mut commands: Commands,
+) {
+    let Some(delay) = CONFIG::STARTUP_CONFIG_AUTOSAVE_DELAY else {
+        return;
+    };
+
+    if startup_config_set.is_changed() {
+        let user_layer = layers.user_layer_for(&startup_config_set.0);
+        let dirty = user_layer != *layers.layer(StartupConfigLayer::User)
+            || startup_config_set.2 != layers.persisted;
+        *dirty_since = dirty.then(|| time.elapsed());
+    }
+
+    if let Some(since) = *dirty_since
+        && time.elapsed().saturating_sub(since) >= delay
+    {
+        *dirty_since = None;
+        commands.queue(SaveStartupConfig::<CONFIG>::default());
+    }
+}
+
+/// Saves the startup config when the app exits. When it fails, the app exits with an error.
+/// A startup config this version of the app can't read is left as is.
+fn save_startup_config_on_exit<CONFIG: NeoNexConfig>(
+    mut exit: EventReader<AppExit>,
+    layers: Res<StartupConfigLayers<CONFIG>>,
+    mut commands: Commands,
+) {
+    if exit.read().last().is_none() || layers.read_only {
+        return;
+    }
+
+    commands.queue(|world: &mut World| {
+        if !save_startup_config::<CONFIG>(world) {
+            world.send_event(AppExit::error());
+        }
+    });
+}
+
+fn reload_startup_config<CONFIG: NeoNexConfig>(
+    watcher: Res<StartupConfigWatcher>,
+    mut startup_config_set: ResMut<SCSWrapper<CONFIG>>,
+    mut layers: ResMut<StartupConfigLayers<CONFIG>>,
+    mut changed: EventWriter<StartupConfigChanged<CONFIG::StartupConfig>>,
+    mut load_failed: EventWriter<StartupConfigLoadFailed>,
+    mut invalid: EventWriter<StartupConfigInvalid>,
+) {
+    if !watcher.take_changed() {
+        return;
+    }
+
+    let external = match CONFIG::Platform::reload_startup_config::<CONFIG>() {
+        Ok(external) => external,
+        Err(error) => {
+            if error.is_left_untouched() {
+                layers.read_only = true;
+            }
+            load_failed.write(StartupConfigLoadFailed {
+                error: error.to_string(),
+            });
+            return;
+        }
+    };
+    if layers.read_only {
+        layers.read_only = false;
+    }
+    // e.g. when the notification comes from a write of the app itself
+    if external == layers.persisted {
+        return;
+    }
+
+    let namespaces = merge_namespaces(
+        &layers.persisted,
+        &startup_config_set.2,
+        &external,
+        CONFIG::STARTUP_CONFIG_CONFLICT_POLICY,
+    );
+    if namespaces != startup_config_set.2 {
+        startup_config_set.2 = namespaces;
+    }
+
+    let persisted = layers.effective();
+    let user_layer = external.merged(layers.namespace());
+    layers
+        .layers
+        .set_layer(StartupConfigLayer::User, user_layer);
+    layers.persisted = external;
+    let violations = layers.layers.skip_invalid();
+    if !violations.is_empty() {
+        invalid.write(StartupConfigInvalid { violations });
+    }
+    let merged = merge(
+        &persisted,
+        &startup_config_set.0,
+        &layers.effective(),
+        CONFIG::STARTUP_CONFIG_CONFLICT_POLICY,
+    );
+    let changes = startup_config_set.0.diff(&merged);
+
+    if !changes.is_empty() {
+        startup_config_set.0 = merged;
+        changed.write(StartupConfigChanged { changes });
+    }
+}
+
+/// Applies [`merge`] to every namespace.
+fn merge_namespaces<U: UserStartupConfig>(
+    persisted: &NamespacedStartupConfigSet<U>,
+    app: &NamespacedStartupConfigSet<U>,
+    external: &NamespacedStartupConfigSet<U>,
+    policy: StartupConfigConflictPolicy,
+) -> NamespacedStartupConfigSet<U> {
+    let empty = NeoNexStartupConfigSet::default();
+    let names: Vec<_> = [persisted, app, external]
+        .into_iter()
+        .flat_map(|store| store.namespaces().map(|(name, _)| Some(name)))
+        .chain([None])
+        .collect();
+
+    let mut merged = NamespacedStartupConfigSet::default();
+    for namespace in names {
+        let set = merge(
+            persisted.get(namespace).unwrap_or(&empty),
+            app.get(namespace).unwrap_or(&empty),
+            external.get(namespace).unwrap_or(&empty),
+            policy,
+        );
+        if namespace.is_none() || !set.is_empty() {
+            *merged.get_mut(namespace) = set;
+        }
+    }
+    merged
+}
+
+/// Applies the in-app modifications made since `persisted` on top of `external`, following
+/// `policy` for the entries modified on both sides.
+fn merge<U: UserStartupConfig>(
+    persisted: &NeoNexStartupConfigSet<U>,
+    app: &NeoNexStartupConfigSet<U>,
+    external: &NeoNexStartupConfigSet<U>,
+    policy: StartupConfigConflictPolicy,
+) -> NeoNexStartupConfigSet<U> {
+    let mut merged = external.clone();
+    if policy == StartupConfigConflictPolicy::DiscardApp {
+        return merged;
+    }
+
+    for change in persisted.diff(app) {
+        let key = change.key();
+        let modified_externally = external.get_by_key(key) != persisted.get_by_key(key);
+        if modified_externally && policy == StartupConfigConflictPolicy::PreferExternal {
+            continue;
+        }
+        match app.get_by_key(key) {
+            Some(entry) => merged.set(entry.clone()),
+            None => merged.remove_by_key(key),
+        };
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::collections::BTreeMap;
+
+    use bevy::{MinimalPlugins, ecs::event::Events, time::TimeUpdateStrategy};
+    use neonex_mockplatform::MockPlatform;
+    use neonex_platform::{
+        MemoryStartupConfigStore, StartupConfigChangeNotifier, decode_startup_config,
+        encode_startup_config,
+    };
+    use neonex_shared::{UserStartupConfig, startup_config_key};
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+    enum TestConfig {
+        Volume(u8),
+        Name(String),
+        Speed(u8),
+        /// Tuples can't be the keys of a persisted map, so a set holding it can't be saved.
+        #[startup_config(skip)]
+        Shortcuts(BTreeMap<(u8, u8), String>),
+    }
+
+    startup_config_key!(Volume => TestConfig::Volume(u8));
+    startup_config_key!(Name => TestConfig::Name(String));
+    startup_config_key!(Speed => TestConfig::Speed(u8));
+
+    /// A config reloading with `$policy`, in its own startup config as tests run in parallel.
+    macro_rules! reloading_app {
+        ($name:ident, $app_id:literal, $policy:ident) => {
+            struct $name;
+
+            impl NeoNexConfig for $name {
+                type Platform = MockPlatform;
+                type StartupConfig = TestConfig;
+                type StartupConfigStore = MemoryStartupConfigStore;
+                const APP_ID: &'static str = $app_id;
+                const STARTUP_CONFIG_AUTOSAVE_DELAY: Option<Duration> = None;
+                const STARTUP_CONFIG_CONFLICT_POLICY: StartupConfigConflictPolicy =
+                    StartupConfigConflictPolicy::$policy;
+            }
+        };
+    }
+
+    reloading_app!(PreferAppApp, "org.neonex.test.reload-app", PreferApp);
+    reloading_app!(
+        PreferExternalApp,
+        "org.neonex.test.reload-external",
+        PreferExternal
+    );
+    reloading_app!(DiscardAppApp, "org.neonex.test.reload-discard", DiscardApp);
+    reloading_app!(CorruptApp, "org.neonex.test.reload-corrupt", PreferApp);
+
+    fn store(global: &[TestConfig], game: &[TestConfig]) -> NamespacedStartupConfigSet<TestConfig> {
+        let mut store = NamespacedStartupConfigSet::default();
+        for entry in global {
+            store.global_mut().set(entry.clone());
+        }
+        for entry in game {
+            store.get_mut(Some("game")).set(entry.clone());
+        }
+        store
+    }
+
+    fn persist<CONFIG: NeoNexConfig<StartupConfig = TestConfig>>(
+        store: &NamespacedStartupConfigSet<TestConfig>,
+    ) {
+        MemoryStartupConfigStore::set_data::<CONFIG>(
+            encode_startup_config::<CONFIG>(store).unwrap(),
+        );
+    }
+
+    fn events<E: Event + Clone>(app: &App) -> Vec<E> {
+        app.world()
+            .resource::<Events<E>>()
+            .iter_current_update_events()
+            .cloned()
+            .collect()
+    }
+
+    /// An app in the global namespace watching its startup config, which modified
+    /// `Volume` and `Speed` in the global namespace and `Volume` in `game` without saving
+    /// them.
+    fn modified_app<CONFIG: NeoNexConfig<StartupConfig = TestConfig>>()
+    -> (App, StartupConfigChangeNotifier) {
+        persist::<CONFIG>(&store(
+            &[TestConfig::Volume(1), TestConfig::Name("ada".into())],
+            &[TestConfig::Volume(10)],
+        ));
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        insert_startup_config::<CONFIG>(&mut app);
+        let (watcher, notifier) = StartupConfigWatcher::new();
+        app.insert_resource(watcher)
+            .add_systems(PreUpdate, reload_startup_config::<CONFIG>);
+        app.update();
+
+        let mut startup_config_set = app.world_mut().resource_mut::<SCSWrapper<CONFIG>>();
+        startup_config_set.set(TestConfig::Volume(2));
+        startup_config_set.set(TestConfig::Speed(9));
+        startup_config_set
+            .namespace_mut(Some("game"))
+            .set(TestConfig::Volume(20));
+        (app, notifier)
+    }
+
+    /// Modifies `Volume` and `Name` in the global namespace, and `Volume` and `Speed` in
+    /// `game`, outside of the app, then lets it reload.
+    fn modify_externally<CONFIG: NeoNexConfig<StartupConfig = TestConfig>>(
+        app: &mut App,
+        notifier: &StartupConfigChangeNotifier,
+    ) {
+        persist::<CONFIG>(&store(
+            &[TestConfig::Volume(3), TestConfig::Name("bob".into())],
+            &[TestConfig::Volume(30), TestConfig::Speed(5)],
+        ));
+        notifier.notify();
+        app.update();
+    }
+
+    #[test]
+    fn prefer_app_keeps_the_entries_modified_on_both_sides() {
+        let (mut app, notifier) = modified_app::<PreferAppApp>();
+        modify_externally::<PreferAppApp>(&mut app, &notifier);
+
+        let startup_config_set = app.world().resource::<SCSWrapper<PreferAppApp>>();
+        assert_eq!(startup_config_set.get::<Volume>(), Some(&2));
+        assert_eq!(
+            startup_config_set.get::<Name>().map(String::as_str),
+            Some("bob")
+        );
+        assert_eq!(startup_config_set.get::<Speed>(), Some(&9));
+        let game = startup_config_set.namespace(Some("game")).unwrap();
+        assert_eq!(game.get::<Volume>(), Some(&20));
+        assert_eq!(game.get::<Speed>(), Some(&5));
+
+        let changes = events::<StartupConfigChanged<TestConfig>>(&app);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0]
+                .changes
+                .iter()
+                .map(|change| change.key())
+                .collect::<Vec<_>>(),
+            ["Name"]
+        );
+    }
+
+    #[test]
+    fn prefer_external_takes_the_entries_modified_on_both_sides() {
+        let (mut app, notifier) = modified_app::<PreferExternalApp>();
+        modify_externally::<PreferExternalApp>(&mut app, &notifier);
+
+        let startup_config_set = app.world().resource::<SCSWrapper<PreferExternalApp>>();
+        assert_eq!(startup_config_set.get::<Volume>(), Some(&3));
+        assert_eq!(
+            startup_config_set.get::<Name>().map(String::as_str),
+            Some("bob")
+        );
+        assert_eq!(startup_config_set.get::<Speed>(), Some(&9));
+        let game = startup_config_set.namespace(Some("game")).unwrap();
+        assert_eq!(game.get::<Volume>(), Some(&30));
+        assert_eq!(game.get::<Speed>(), Some(&5));
+    }
+
+    #[test]
+    fn discard_app_drops_every_unsaved_modification() {
+        let (mut app, notifier) = modified_app::<DiscardAppApp>();
+        modify_externally::<DiscardAppApp>(&mut app, &notifier);
+
+        let startup_config_set = app.world().resource::<SCSWrapper<DiscardAppApp>>();
+        assert_eq!(startup_config_set.get::<Volume>(), Some(&3));
+        assert_eq!(
+            startup_config_set.get::<Name>().map(String::as_str),
+            Some("bob")
+        );
+        assert_eq!(startup_config_set.get::<Speed>(), None);
+        let game = startup_config_set.namespace(Some("game")).unwrap();
+        assert_eq!(game.get::<Volume>(), Some(&30));
+        assert_eq!(game.get::<Speed>(), Some(&5));
+    }
+
+    #[test]
+    fn a_corrupt_file_leaves_the_set_alone_on_reload() {
+        let (mut app, notifier) = modified_app::<CorruptApp>();
+        MemoryStartupConfigStore::set_data::<CorruptApp>(b"{ \"values\": ".to_vec());
+        notifier.notify();
+        app.update();
+
+        let startup_config_set = app.world().resource::<SCSWrapper<CorruptApp>>();
+        assert_eq!(startup_config_set.get::<Volume>(), Some(&2));
+        assert_eq!(
+            startup_config_set.get::<Name>().map(String::as_str),
+            Some("ada")
+        );
+        assert_eq!(startup_config_set.get::<Speed>(), Some(&9));
+        let game = startup_config_set.namespace(Some("game")).unwrap();
+        assert_eq!(game.get::<Volume>(), Some(&20));
+        assert_eq!(events::<StartupConfigLoadFailed>(&app).len(), 1);
+        assert!(events::<StartupConfigChanged<TestConfig>>(&app).is_empty());
+        // Left for the editor to finish writing it.
+        assert_eq!(
+            MemoryStartupConfigStore::data::<CorruptApp>().as_deref(),
+            Some(&b"{ \"values\": "[..])
+        );
+    }
+
+    /// A config saved a second after its last modification.
+    macro_rules! autosaved_app {
+        ($name:ident, $app_id:literal) => {
+            struct $name;
+
+            impl NeoNexConfig for $name {
+                type Platform = MockPlatform;
+                type StartupConfig = TestConfig;
+                type StartupConfigStore = MemoryStartupConfigStore;
+                const APP_ID: &'static str = $app_id;
+            }
+        };
+    }
+
+    autosaved_app!(AutosavedApp, "org.neonex.test.autosave");
+    autosaved_app!(ExitingApp, "org.neonex.test.save-on-exit");
+    autosaved_app!(UnsavableApp, "org.neonex.test.save-failed");
+
+    /// Every update of the app is this long.
+    const FRAME: Duration = Duration::from_millis(400);
+
+    /// An app whose clock moves by [`FRAME`] on every update, with nothing persisted yet.
+    fn autosaved_app<CONFIG: NeoNexConfig<StartupConfig = TestConfig>>() -> App {
+        MemoryStartupConfigStore::clear::<CONFIG>();
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME));
+        insert_startup_config::<CONFIG>(&mut app);
+        app.update();
+        app
+    }
+
+    fn saved_volume<CONFIG: NeoNexConfig<StartupConfig = TestConfig>>() -> Option<u8> {
+        let data = MemoryStartupConfigStore::data::<CONFIG>()?;
+        let store = decode_startup_config::<CONFIG>(&data).unwrap();
+        store.global().get::<Volume>().copied()
+    }
+
+    #[test]
+    fn the_set_is_saved_once_after_the_autosave_delay() {
+        let mut app = autosaved_app::<AutosavedApp>();
+        app.world_mut()
+            .resource_mut::<SCSWrapper<AutosavedApp>>()
+            .set(TestConfig::Volume(3));
+        // Modified 0, 400 and 800 ms ago, under the one second delay.
+        for _ in 0..3 {
+            app.update();
+            assert_eq!(MemoryStartupConfigStore::data::<AutosavedApp>(), None);
+        }
+        app.update();
+        assert_eq!(saved_volume::<AutosavedApp>(), Some(3));
+
+        // Nothing is modified anymore, so it isn't written again.
+        MemoryStartupConfigStore::clear::<AutosavedApp>();
+        for _ in 0..5 {
+            app.update();
+        }
+        assert_eq!(MemoryStartupConfigStore::data::<AutosavedApp>(), None);
+    }
+
+    #[test]
+    fn a_pending_modification_is_saved_on_exit() {
+        let mut app = autosaved_app::<ExitingApp>();
+        app.world_mut()
+            .resource_mut::<SCSWrapper<ExitingApp>>()
+            .set(TestConfig::Volume(4));
+        app.update();
+        assert_eq!(MemoryStartupConfigStore::data::<ExitingApp>(), None);
+
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(saved_volume::<ExitingApp>(), Some(4));
+        assert!(!events::<AppExit>(&app).contains(&AppExit::error()));
+    }
+
+    #[test]
+    fn a_set_that_can_not_be_encoded_is_reported() {
+        let mut app = autosaved_app::<UnsavableApp>();
+        let shortcuts = BTreeMap::from([((1, 2), "quit".into())]);
+        app.world_mut()
+            .resource_mut::<SCSWrapper<UnsavableApp>>()
+            .set(TestConfig::Shortcuts(shortcuts));
+        let mut failures = Vec::new();
+        for _ in 0..6 {
+            app.update();
+            failures.extend(events::<StartupConfigSaveFailed>(&app));
+        }
+        assert_eq!(failures.len(), 1);
+        assert!(
+            failures[0].error.contains("key must be a string"),
+            "{}",
+            failures[0].error
+        );
+        assert_eq!(MemoryStartupConfigStore::data::<UnsavableApp>(), None);
+
+        // Exiting tries again, and exits with an error.
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(events::<StartupConfigSaveFailed>(&app).len(), 1);
+        assert!(events::<AppExit>(&app).contains(&AppExit::error()));
+    }
+}
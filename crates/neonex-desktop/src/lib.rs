@@ -1,7 +1,5 @@
 use std::{
-    env::temp_dir,
-    fs::OpenOptions,
-    io::{Read, Stdout, Write, stdout},
+    io::{Stdout, stdout},
     path::{Path, PathBuf},
 };
 
@@ -19,8 +17,7 @@ use bevy::{
     ecs::error::BevyError,
     prelude::{Deref, DerefMut, PluginGroup},
 };
-use neonex_platform::{NeoNexConfig, NeoNexPlatform};
-use neonex_shared::NeoNexStartupConfigSet;
+use neonex_platform::{NeoNexConfig, NeoNexPlatform, StartupConfigSetOf, fs, process};
 use neonex_terminal::{TerminalContext};
 use ratatui::Terminal;
 #[cfg(not(any(feature = "softatui", feature = "crossterm")))]
@@ -56,48 +53,25 @@ impl NeoNexPlatform for DesktopPlatform {
 
     type StartupConfigRetrieveKeyType = PathBuf;
 
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType {
-        let mut path = temp_dir();
-        path.push(Self::STARTUP_CONFIG_RANDOM_KEY.to_string());
-        path
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType {
+        fs::startup_config_path::<CONFIG>()
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        let path = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true).read(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .expect("Unable to read to string from temp file");
-        if let Ok(output) = serde_json::from_str(&buf) {
-            return output;
-        } else {
-            if buf.len() > 0 {
-                file.set_len(0)
-                    .expect("Unable to clear the file once the data has been corrupted");
-            }
-            return NeoNexStartupConfigSet::default();
-        }
+    type StartupConfigStore = fs::FileStartupConfigStore;
+
+    type StorageError = std::io::Error;
+
+    fn startup_config_env_vars() -> Vec<(String, String)> {
+        process::env_vars()
     }
 
-    type UpdateResult = serde_json::Result<()>;
-
-    fn update_startup_config(
-        startup_config_set: neonex_shared::NeoNexStartupConfigSet,
-    ) -> Self::UpdateResult {
-        let path = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        file.write_all(serde_json::to_string(&startup_config_set)?.as_bytes())
-            .expect("Unable to write to the temp file");
-        Ok(())
+    fn startup_config_args<CONFIG: NeoNexConfig>() -> Vec<String> {
+        process::args()
     }
 
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> Result<(), BevyError> {
         cfg_if::cfg_if! {
             if #[cfg(feature = "hybrid-contexts")] {
@@ -158,46 +132,23 @@ impl NeoNexPlatform for SoftatuiDesktop {
         const PLATFORM: &'static str = "Desktop - Unknown OS";
     }}
 
-    fn retrieve_startup_config_key() -> PathBuf {
-        let mut path = temp_dir();
-        path.push(Self::STARTUP_CONFIG_RANDOM_KEY.to_string());
-        path
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> PathBuf {
+        fs::startup_config_path::<CONFIG>()
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        let path: PathBuf = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true).read(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .expect("Unable to read to string from temp file");
-        if let Ok(output) = serde_json::from_str(&buf) {
-            return output;
-        } else {
-            if buf.len() > 0 {
-                file.set_len(0)
-                    .expect("Unable to clear the file once the data has been corrupted");
-            }
-            return NeoNexStartupConfigSet::default();
-        }
+    type StartupConfigStore = fs::FileStartupConfigStore;
+
+    fn startup_config_env_vars() -> Vec<(String, String)> {
+        process::env_vars()
     }
 
-    fn update_startup_config(
-        startup_config_set: neonex_shared::NeoNexStartupConfigSet,
-    ) -> serde_json::Result<()> {
-        let path: PathBuf = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        file.write_all(serde_json::to_string(&startup_config_set)?.as_bytes())
-            .expect("Unable to write to the temp file");
-        Ok(())
+    fn startup_config_args<CONFIG: NeoNexConfig>() -> Vec<String> {
+        process::args()
     }
 
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> Result<(), BevyError> {
         cfg_if::cfg_if! {
             if #[cfg(feature = "hybrid-contexts")] {
@@ -246,7 +197,7 @@ impl NeoNexPlatform for SoftatuiDesktop {
 
     type StartupConfigRetrieveKeyType = PathBuf;
 
-    type UpdateResult = serde_json::Result<()>;
+    type StorageError = std::io::Error;
 }
 
 #[cfg(feature = "hybrid-contexts")]
@@ -264,46 +215,23 @@ impl NeoNexPlatform for CrosstermDesktop {
         const PLATFORM: &'static str = "Desktop - Unknown OS";
     }}
 
-    fn retrieve_startup_config_key() -> PathBuf {
-        let mut path = temp_dir();
-        path.push(Self::STARTUP_CONFIG_RANDOM_KEY.to_string());
-        path
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> PathBuf {
+        fs::startup_config_path::<CONFIG>()
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        let path: PathBuf = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true).read(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .expect("Unable to read to string from temp file");
-        if let Ok(output) = serde_json::from_str(&buf) {
-            return output;
-        } else {
-            if buf.len() > 0 {
-                file.set_len(0)
-                    .expect("Unable to clear the file once the data has been corrupted");
-            }
-            return NeoNexStartupConfigSet::default();
-        }
+    type StartupConfigStore = fs::FileStartupConfigStore;
+
+    fn startup_config_env_vars() -> Vec<(String, String)> {
+        process::env_vars()
     }
 
-    fn update_startup_config(
-        startup_config_set: neonex_shared::NeoNexStartupConfigSet,
-    ) -> serde_json::Result<()> {
-        let path: PathBuf = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        file.write_all(serde_json::to_string(&startup_config_set)?.as_bytes())
-            .expect("Unable to write to the temp file");
-        Ok(())
+    fn startup_config_args<CONFIG: NeoNexConfig>() -> Vec<String> {
+        process::args()
     }
 
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> Result<(), BevyError> {
         cfg_if::cfg_if! {
             if #[cfg(feature = "hybrid-contexts")] {
@@ -352,7 +280,7 @@ impl NeoNexPlatform for CrosstermDesktop {
 
     type StartupConfigRetrieveKeyType = PathBuf;
 
-    type UpdateResult = serde_json::Result<()>;
+    type StorageError = std::io::Error;
 }
 
 #[cfg(any(feature = "softatui", feature = "hybrid-contexts"))]
@@ -1,6 +1,9 @@
 use bevy::prelude::{Deref, DerefMut};
-use neonex_platform::NeoNexPlatform;
-use neonex_shared::NeoNexStartupConfigSet;
+use neonex_platform::{
+    MemoryStartupConfigStore, NamespacedStartupConfigSetOf, NeoNexConfig, NeoNexPlatform,
+    StartupConfigFormatError, StartupConfigLoadError, StartupConfigSetOf,
+    StartupConfigStoreErrorOf,
+};
 use neonex_terminal::TerminalContext;
 use ratatui::{backend::TestBackend, prelude::Backend, Terminal};
 
@@ -17,23 +20,26 @@ impl NeoNexPlatform for EmbeddedPlatform {
 
     type StartupConfigRetrieveKeyType = ();
 
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType {
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType {
         panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
+    fn retrieve_startup_config<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<StartupConfigStoreErrorOf<CONFIG>>> {
         panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
     }
 
-    type UpdateResult = ();
+    type StartupConfigStore = MemoryStartupConfigStore;
 
-    fn update_startup_config(sc: NeoNexStartupConfigSet) -> Self::UpdateResult {
+    type StorageError = StartupConfigFormatError;
+
+    fn update_startup_config<CONFIG: NeoNexConfig>(sc: NamespacedStartupConfigSetOf<CONFIG>) -> Result<(), StartupConfigStoreErrorOf<CONFIG>> {
         panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
     }
 
-    fn setup_bevy<CONFIG: neonex_platform::NeoNexConfig>(
+    fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut bevy::app::App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> core::result::Result<(), bevy::ecs::error::BevyError> {
         panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
     }
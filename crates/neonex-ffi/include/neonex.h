@@ -0,0 +1,77 @@
+/*
+ * Reads the persisted startup config of a NeoNex app, for the apps it launches that aren't
+ * written in Rust. Link against the `neonex_ffi` library built from `crates/neonex-ffi`,
+ * with its `integrity` feature when the app signs its startup config.
+ *
+ * Entries are read by key, e.g. "Volume", as persisted by the app. Their JSON Schema is
+ * generated by `neonex_shared::schema::startup_config_json_schema`.
+ *
+ *     NeoNexStartupConfig *config = neonex_startup_config_open("com.example.launcher", "game");
+ *     if (config == NULL) {
+ *         fprintf(stderr, "%s\n", neonex_last_error());
+ *     }
+ *     int64_t volume = 5;
+ *     neonex_startup_config_get_i64(config, "Volume", &volume);
+ *     neonex_startup_config_free(config);
+ */
+
+#ifndef NEONEX_H
+#define NEONEX_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Entries seen by an app of a namespace. */
+typedef struct NeoNexStartupConfig NeoNexStartupConfig;
+
+/* Why the last call of the thread returning NULL failed, or NULL. Valid until the next
+ * failing call of the thread. */
+const char *neonex_last_error(void);
+
+/* Opens the startup config of the app whose `NeoNexConfig::APP_ID` is `app_id`, in its
+ * default location, with the entries seen by the apps of `namespace`, or only the global
+ * ones when it's NULL. A missing startup config has no entries.
+ * Returns NULL when it can't be read, see `neonex_last_error`. */
+NeoNexStartupConfig *neonex_startup_config_open(const char *app_id, const char *namespace_);
+
+/* Like `neonex_startup_config_open`, with the startup config stored at `path`. */
+NeoNexStartupConfig *neonex_startup_config_open_path(const char *path, const char *namespace_);
+
+/* Frees an opened startup config. Does nothing on NULL. */
+void neonex_startup_config_free(NeoNexStartupConfig *config);
+
+/* Whether the startup config has an entry with `key`. */
+bool neonex_startup_config_contains(const NeoNexStartupConfig *config, const char *key);
+
+/* Write the entry with `key` to `value` when it has the right type, integers being numbers
+ * too. Return whether they did. */
+bool neonex_startup_config_get_bool(const NeoNexStartupConfig *config, const char *key,
+                                    bool *value);
+bool neonex_startup_config_get_i64(const NeoNexStartupConfig *config, const char *key,
+                                   int64_t *value);
+bool neonex_startup_config_get_f64(const NeoNexStartupConfig *config, const char *key,
+                                   double *value);
+
+/* Copies the entry with `key` into `buffer` when it's a string, with a NUL terminator.
+ * `length` holds the size of `buffer`, and is set to the size needed, or to 0 when there's
+ * no such string entry. Returns whether it was copied, so that a call with a NULL buffer
+ * gives the size to allocate. Strings holding a NUL character aren't copied, and set the
+ * last error: they can be read with `neonex_startup_config_get_json`. */
+bool neonex_startup_config_get_string(const NeoNexStartupConfig *config, const char *key,
+                                      char *buffer, size_t *length);
+
+/* Like `neonex_startup_config_get_string`, with any entry serialized as JSON, e.g. lists,
+ * or variants holding several values. */
+bool neonex_startup_config_get_json(const NeoNexStartupConfig *config, const char *key,
+                                    char *buffer, size_t *length);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NEONEX_H */
@@ -0,0 +1,304 @@
+//! C ABI reading the persisted startup config of a NeoNex app, for the apps it launches that
+//! aren't written in Rust, e.g. C++ or C# ones. The functions are declared in
+//! `include/neonex.h`.
+//!
+//! Entries are read as persisted, see `neonex_shared::dynamic`, without knowing the schema
+//! of the app: values are returned by key, like `Volume` for `{ "Volume": 5 }`. Their JSON
+//! Schema is generated by `neonex_shared::schema::startup_config_json_schema`.
+//!
+//! Reading never writes anything. Signed files, see the `integrity` feature of
+//! `neonex-platform`, need the `integrity` feature of this crate, which is off by default.
+
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString, c_char},
+    path::Path,
+    ptr,
+};
+
+use neonex_platform::{StartupConfigFormat, StartupConfigLoadError, fs};
+use neonex_shared::DynamicStartupConfigSet;
+use serde_json::Value;
+
+/// Entries seen by an app of a namespace, opened with [`neonex_startup_config_open`].
+pub struct NeoNexStartupConfig(DynamicStartupConfigSet);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(error: impl ToString) {
+    let error = CString::new(error.to_string().replace('\0', " ")).ok();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = error);
+}
+
+/// Why the last call of the thread returning `NULL` failed, or `NULL`. Valid until the next
+/// failing call of the thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn neonex_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |error| error.as_ptr())
+    })
+}
+
+/// # Safety
+/// `string` is `NULL` or a NUL-terminated string.
+unsafe fn str_arg<'a>(string: *const c_char, name: &str) -> Result<Option<&'a str>, String> {
+    if string.is_null() {
+        return Ok(None);
+    }
+    unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .map(Some)
+        .map_err(|_| format!("{name} isn't valid UTF-8"))
+}
+
+fn open(path: Result<&Path, String>, namespace: Option<&str>) -> *mut NeoNexStartupConfig {
+    let persisted = path.and_then(|path| {
+        fs::read_persisted_startup_config(path).map_err(|error| match error {
+            // The file is left untouched.
+            StartupConfigLoadError::Corrupt { error, .. } => error.to_string(),
+            error => error.to_string(),
+        })
+    });
+    match persisted {
+        Ok(persisted) => Box::into_raw(Box::new(NeoNexStartupConfig(
+            DynamicStartupConfigSet::from_persisted(&persisted, namespace),
+        ))),
+        Err(error) => {
+            set_last_error(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Opens the startup config of the app whose `NeoNexConfig::APP_ID` is `app_id`, in its
+/// default location, with the entries seen by the apps of `namespace`, or only the global
+/// ones when it's `NULL`. A missing startup config has no entries.
+///
+/// Returns `NULL` when it can't be read, see [`neonex_last_error`].
+///
+/// # Safety
+/// `app_id` and `namespace` are `NULL` or NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_open(
+    app_id: *const c_char,
+    namespace: *const c_char,
+) -> *mut NeoNexStartupConfig {
+    let args = unsafe { str_arg(app_id, "app_id") }
+        .and_then(|app_id| app_id.ok_or_else(|| "app_id is NULL".to_string()))
+        .and_then(|app_id| Ok((app_id, unsafe { str_arg(namespace, "namespace") }?)));
+    match args {
+        Ok((app_id, namespace)) => {
+            // The file written in another format is found from the JSON one.
+            let path = fs::startup_config_path_for(app_id, StartupConfigFormat::Json);
+            open(Ok(&path), namespace)
+        }
+        Err(error) => open(Err(error), None),
+    }
+}
+
+/// Like [`neonex_startup_config_open`], with the startup config stored at `path`.
+///
+/// # Safety
+/// `path` and `namespace` are `NULL` or NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_open_path(
+    path: *const c_char,
+    namespace: *const c_char,
+) -> *mut NeoNexStartupConfig {
+    let args = unsafe { str_arg(path, "path") }
+        .and_then(|path| path.ok_or_else(|| "path is NULL".to_string()))
+        .and_then(|path| Ok((path, unsafe { str_arg(namespace, "namespace") }?)));
+    match args {
+        Ok((path, namespace)) => open(Ok(Path::new(path)), namespace),
+        Err(error) => open(Err(error), None),
+    }
+}
+
+/// Frees a startup config opened by [`neonex_startup_config_open`]. Does nothing on `NULL`.
+///
+/// # Safety
+/// `config` is `NULL` or was returned by an open function, and isn't used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_free(config: *mut NeoNexStartupConfig) {
+    if !config.is_null() {
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
+
+/// # Safety
+/// `config` is `NULL` or an opened startup config, `key` is `NULL` or a NUL-terminated string.
+unsafe fn get<'a>(config: *const NeoNexStartupConfig, key: *const c_char) -> Option<&'a Value> {
+    let config = unsafe { config.as_ref() }?;
+    let key = unsafe { str_arg(key, "key") }.ok()??;
+    config.0.get(key)
+}
+
+/// Whether the startup config has an entry with `key`.
+///
+/// # Safety
+/// `config` is `NULL` or an opened startup config, `key` is `NULL` or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_contains(
+    config: *const NeoNexStartupConfig,
+    key: *const c_char,
+) -> bool {
+    unsafe { get(config, key) }.is_some()
+}
+
+/// # Safety
+/// `value` is `NULL` or valid for writes.
+unsafe fn write<T>(value: *mut T, read: Option<T>) -> bool {
+    match (unsafe { value.as_mut() }, read) {
+        (Some(value), Some(read)) => {
+            *value = read;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Writes the entry with `key` to `value` when it's a boolean. Returns whether it did.
+///
+/// # Safety
+/// `config` is `NULL` or an opened startup config, `key` is `NULL` or a NUL-terminated string,
+/// `value` is `NULL` or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_get_bool(
+    config: *const NeoNexStartupConfig,
+    key: *const c_char,
+    value: *mut bool,
+) -> bool {
+    let read = unsafe { get(config, key) }.and_then(Value::as_bool);
+    unsafe { write(value, read) }
+}
+
+/// Writes the entry with `key` to `value` when it's an integer fitting in 64 bits. Returns
+/// whether it did.
+///
+/// # Safety
+/// Same as [`neonex_startup_config_get_bool`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_get_i64(
+    config: *const NeoNexStartupConfig,
+    key: *const c_char,
+    value: *mut i64,
+) -> bool {
+    let read = unsafe { get(config, key) }.and_then(Value::as_i64);
+    unsafe { write(value, read) }
+}
+
+/// Writes the entry with `key` to `value` when it's a number, integers included. Returns
+/// whether it did.
+///
+/// # Safety
+/// Same as [`neonex_startup_config_get_bool`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_get_f64(
+    config: *const NeoNexStartupConfig,
+    key: *const c_char,
+    value: *mut f64,
+) -> bool {
+    let read = unsafe { get(config, key) }.and_then(Value::as_f64);
+    unsafe { write(value, read) }
+}
+
+/// Copies `text` and a NUL terminator into `buffer`, whose size is read from `length`, then
+/// sets `length` to the size needed. Returns whether it fitted. Text holding a NUL character
+/// would be cut by C strings, so it isn't copied, as when there's no text.
+///
+/// # Safety
+/// `length` is `NULL` or valid for reads and writes, `buffer` is `NULL` or valid for `length`
+/// bytes of writes.
+unsafe fn copy_text(text: Option<&str>, buffer: *mut c_char, length: *mut usize) -> bool {
+    let Some(length) = (unsafe { length.as_mut() }) else {
+        return false;
+    };
+    let Some(text) = text else {
+        *length = 0;
+        return false;
+    };
+    if text.contains('\0') {
+        set_last_error("the string holds a NUL character, read it as JSON instead");
+        *length = 0;
+        return false;
+    }
+    let capacity = *length;
+    *length = text.len() + 1;
+    if buffer.is_null() || capacity < *length {
+        return false;
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(text.as_ptr().cast(), buffer, text.len());
+        *buffer.add(text.len()) = 0;
+    }
+    true
+}
+
+/// Copies the entry with `key` into `buffer` when it's a string, with a NUL terminator.
+/// `length` holds the size of `buffer`, and is set to the size needed, or to 0 when there's
+/// no such string entry. Returns whether it was copied, so that a call with a `NULL` buffer
+/// gives the size to allocate. Strings holding a NUL character aren't copied, and set the
+/// last error, see [`neonex_last_error`]: they can be read with
+/// [`neonex_startup_config_get_json`].
+///
+/// # Safety
+/// `config` is `NULL` or an opened startup config, `key` is `NULL` or a NUL-terminated string,
+/// `length` is `NULL` or valid for reads and writes, `buffer` is `NULL` or valid for `*length`
+/// bytes of writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_get_string(
+    config: *const NeoNexStartupConfig,
+    key: *const c_char,
+    buffer: *mut c_char,
+    length: *mut usize,
+) -> bool {
+    let text = unsafe { get(config, key) }.and_then(Value::as_str);
+    unsafe { copy_text(text, buffer, length) }
+}
+
+/// Like [`neonex_startup_config_get_string`], with any entry serialized as JSON, e.g. lists,
+/// or variants holding several values.
+///
+/// # Safety
+/// Same as [`neonex_startup_config_get_string`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn neonex_startup_config_get_json(
+    config: *const NeoNexStartupConfig,
+    key: *const c_char,
+    buffer: *mut c_char,
+    length: *mut usize,
+) -> bool {
+    let json = unsafe { get(config, key) }.map(Value::to_string);
+    unsafe { copy_text(json.as_deref(), buffer, length) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn copy(text: &str, capacity: usize) -> (bool, usize, Vec<u8>) {
+        let mut buffer = vec![0xff_u8; capacity];
+        let mut length = capacity;
+        let copied = unsafe { copy_text(Some(text), buffer.as_mut_ptr().cast(), &mut length) };
+        (copied, length, buffer)
+    }
+
+    #[test]
+    fn text_is_copied_with_a_nul_terminator() {
+        assert_eq!(copy("abc", 4), (true, 4, b"abc\0".to_vec()));
+        // Too small, only the size needed is given.
+        assert_eq!(copy("abc", 3), (false, 4, vec![0xff; 3]));
+    }
+
+    #[test]
+    fn text_holding_a_nul_is_not_cut() {
+        assert_eq!(copy("a\0c", 4), (false, 0, vec![0xff; 4]));
+        let error = unsafe { CStr::from_ptr(neonex_last_error()) };
+        assert!(error.to_str().unwrap().contains("NUL"));
+    }
+}
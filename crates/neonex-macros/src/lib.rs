@@ -22,3 +22,234 @@ pub fn generate_32char_seed(_item: TokenStream) -> TokenStream {
 
     TokenStream::from_str(&output).expect("Unable to produce TokenStream from random seed")
 }
+
+/// Implements `UserStartupConfig` for an enum whose variants are the entries: the key of each
+/// entry is the name of its variant, and `SCHEMA` holds a field per variant wrapping a single
+/// value, its kind following the type of the value:
+/// ```ignore
+/// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+/// #[startup_config(cross_rules = &[StartupConfigCrossRule::NotGreater { lower: "MinVolume", upper: "MaxVolume" }])]
+/// enum LauncherConfig {
+///     #[startup_config(min = 0, max = 100)]
+///     MinVolume(u8),
+///     #[startup_config(min = 0, max = 100)]
+///     MaxVolume(u8),
+///     #[startup_config(label = "User name", rules = &[StartupConfigRule::Pattern("[a-z_][a-z0-9_]*")])]
+///     Username(String),
+///     #[startup_config(options = &["Dark", "Light"])]
+///     Theme(Theme),
+///     #[startup_config(skip)]
+///     LastSession(Session),
+/// }
+/// ```
+///
+/// `bool` values are toggles, integers are bounded by their type unless `min` and `max` are
+/// given, floats need `min` and `max` and are changed by `step`, a hundredth of their range
+/// by default, and `String` values are text. Values of any other type need their `options`,
+/// or to be skipped. Labels default to the name of the variant, split into words.
+#[proc_macro_derive(UserStartupConfig, attributes(startup_config))]
+pub fn derive_user_startup_config(item: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(item as syn::DeriveInput);
+    user_startup_config(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn user_startup_config(input: syn::DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let syn::Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "`UserStartupConfig` can only be derived for enums, with a variant per entry",
+        ));
+    };
+
+    let mut cross_rules = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("startup_config"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("cross_rules") {
+                cross_rules = Some(meta.value()?.parse::<syn::Expr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `cross_rules`"))
+            }
+        })?;
+    }
+
+    let mut keys = Vec::new();
+    let mut fields = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let key = ident.to_string();
+        keys.push(quote::quote! { Self::#ident { .. } => #key });
+        if let Some(field) = schema_field(variant, &key)? {
+            fields.push(field);
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let cross_rules = cross_rules.map(|cross_rules| {
+        quote::quote! {
+            const CROSS_RULES: &'static [::neonex_shared::validation::StartupConfigCrossRule] =
+                #cross_rules;
+        }
+    });
+    Ok(quote::quote! {
+        impl #impl_generics ::neonex_shared::UserStartupConfig for #name #type_generics #where_clause {
+            fn key(&self) -> &'static str {
+                match *self {
+                    #(#keys,)*
+                }
+            }
+
+            const SCHEMA: &'static [::neonex_shared::schema::StartupConfigField] = &[#(#fields),*];
+
+            #cross_rules
+        }
+    })
+}
+
+/// Options of a variant, given with `#[startup_config(...)]`.
+#[derive(Default)]
+struct FieldOptions {
+    label: Option<syn::LitStr>,
+    min: Option<syn::Expr>,
+    max: Option<syn::Expr>,
+    step: Option<syn::Expr>,
+    options: Option<syn::Expr>,
+    rules: Option<syn::Expr>,
+    skip: bool,
+}
+
+/// The `StartupConfigField` of `variant`, if it wraps a single value and isn't skipped.
+fn schema_field(
+    variant: &syn::Variant,
+    key: &str,
+) -> syn::Result<Option<proc_macro2::TokenStream>> {
+    let mut options = FieldOptions::default();
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("startup_config"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let path = &meta.path;
+            if path.is_ident("skip") {
+                options.skip = true;
+            } else if path.is_ident("label") {
+                options.label = Some(meta.value()?.parse()?);
+            } else if path.is_ident("min") {
+                options.min = Some(meta.value()?.parse()?);
+            } else if path.is_ident("max") {
+                options.max = Some(meta.value()?.parse()?);
+            } else if path.is_ident("step") {
+                options.step = Some(meta.value()?.parse()?);
+            } else if path.is_ident("options") {
+                options.options = Some(meta.value()?.parse()?);
+            } else if path.is_ident("rules") {
+                options.rules = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error(
+                    "expected `skip`, `label`, `min`, `max`, `step`, `options` or `rules`",
+                ));
+            }
+            Ok(())
+        })?;
+    }
+
+    let syn::Fields::Unnamed(unnamed) = &variant.fields else {
+        return Ok(None);
+    };
+    if options.skip || unnamed.unnamed.len() != 1 {
+        return Ok(None);
+    }
+    let ty = &unnamed.unnamed[0].ty;
+    let type_name = match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    };
+
+    let kind = quote::quote! { ::neonex_shared::schema::StartupConfigFieldKind };
+    let kind = match (&options.options, type_name.as_deref()) {
+        (Some(choices), _) => quote::quote! { #kind::Choice(#choices) },
+        (None, Some("bool")) => quote::quote! { #kind::Toggle },
+        (None, Some("String")) => quote::quote! { #kind::Text },
+        (
+            None,
+            Some("u8" | "u16" | "u32" | "u64" | "usize" | "i8" | "i16" | "i32" | "i64" | "isize"),
+        ) => {
+            let min = match &options.min {
+                Some(min) => quote::quote! { (#min) as i64 },
+                None => quote::quote! { <#ty>::MIN as i64 },
+            };
+            // Wider than `i64` for `u64`.
+            let max = match &options.max {
+                Some(max) => quote::quote! { (#max) as i64 },
+                None => quote::quote! {
+                    if <#ty>::MAX as u128 > i64::MAX as u128 { i64::MAX } else { <#ty>::MAX as i64 }
+                },
+            };
+            quote::quote! { #kind::Integer { min: #min, max: #max } }
+        }
+        (None, Some("f32" | "f64")) => {
+            let (Some(min), Some(max)) = (&options.min, &options.max) else {
+                return Err(syn::Error::new_spanned(
+                    ty,
+                    "float entries need their `min` and `max`",
+                ));
+            };
+            let step = match &options.step {
+                Some(step) => quote::quote! { (#step) as f64 },
+                None => quote::quote! { ((#max) as f64 - (#min) as f64) / 100.0 },
+            };
+            quote::quote! {
+                #kind::Float { min: (#min) as f64, max: (#max) as f64, step: #step }
+            }
+        }
+        (None, _) => {
+            return Err(syn::Error::new_spanned(
+                ty,
+                "the kind of this entry can't be derived from its type, \
+                 give its `options` or `skip` it",
+            ));
+        }
+    };
+
+    let label = match &options.label {
+        Some(label) => label.value(),
+        None => words(key),
+    };
+    let rules = options
+        .rules
+        .as_ref()
+        .map(|rules| quote::quote! { .with_rules(#rules) });
+    Ok(Some(quote::quote! {
+        ::neonex_shared::schema::StartupConfigField::new(#key, #label, #kind)#rules
+    }))
+}
+
+/// `name` split into words, e.g. `Native terminal` for `NativeTerminal`.
+fn words(name: &str) -> String {
+    let mut words = String::new();
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        match previous {
+            None => words.push(c),
+            Some(previous) if c.is_uppercase() && !previous.is_uppercase() => {
+                words.push(' ');
+                words.extend(c.to_lowercase());
+            }
+            Some(_) => words.push(c),
+        }
+        previous = Some(c);
+    }
+    words
+}
@@ -1,7 +1,4 @@
 use std::{
-    env::temp_dir,
-    fs::OpenOptions,
-    io::{Read, Write},
     path::PathBuf,
 };
 
@@ -12,8 +9,7 @@ use bevy::{
     ecs::error::BevyError,
     prelude::{Deref, DerefMut, PluginGroup},
 };
-use neonex_platform::{NeoNexConfig, NeoNexPlatform,};
-use neonex_shared::NeoNexStartupConfigSet;
+use neonex_platform::{NeoNexConfig, NeoNexPlatform, StartupConfigSetOf, fs};
 use neonex_terminal::{RatatuiContext, TerminalContext};
 use ratatui::Terminal;
 use soft_ratatui::SoftBackend;
@@ -37,7 +33,7 @@ impl NeoNexPlatform for MobilePlatform {
 
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> Result<(), BevyError> {
         app.add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
@@ -61,44 +57,13 @@ impl NeoNexPlatform for MobilePlatform {
         Ok(())
     }
 
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType {
-        let mut path = temp_dir();
-        path.push(Self::STARTUP_CONFIG_RANDOM_KEY.to_string());
-        path
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType {
+        fs::startup_config_path::<CONFIG>()
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        let path = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true).read(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        let mut buf = String::new();
-        file.read_to_string(&mut buf)
-            .expect("Unable to read to string from temp file");
-        if let Ok(output) = serde_json::from_str(&buf) {
-            return output;
-        } else {
-            if buf.len() > 0 {
-                file.set_len(0)
-                    .expect("Unable to clear the file once the data has been corrupted");
-            }
-            return NeoNexStartupConfigSet::default();
-        }
-    }
+    type StartupConfigStore = fs::FileStartupConfigStore;
 
-    type UpdateResult = serde_json::Result<()>;
-
-    fn update_startup_config(
-        startup_config_set: NeoNexStartupConfigSet,
-    ) -> serde_json::Result<()> {
-        let path = Self::retrieve_startup_config_key();
-        let mut options = OpenOptions::new();
-        options.create(true).write(true);
-        let mut file = options.open(path).expect("Unable to open temp file");
-        file.write_all(serde_json::to_string(&startup_config_set)?.as_bytes())
-            .expect("Unable to write to the temp file");
-        Ok(())
-    }
+    type StorageError = std::io::Error;
 
     type RatatuiContextBackend = SoftBackend;
 
@@ -1,6 +1,8 @@
 use bevy::prelude::{Deref, DerefMut};
-use neonex_platform::NeoNexPlatform;
-use neonex_shared::NeoNexStartupConfigSet;
+use neonex_platform::{
+    MemoryStartupConfigStore, NeoNexConfig, NeoNexPlatform, StartupConfigFormatError,
+    StartupConfigSetOf,
+};
 use neonex_terminal::TerminalContext;
 use ratatui::{backend::TestBackend, prelude::Backend, Terminal};
 
@@ -15,23 +17,19 @@ impl NeoNexPlatform for MockPlatform {
 
     type StartupConfigRetrieveKeyType = ();
 
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType {
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType {
         panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
-    }
-
-    type UpdateResult = ();
+    // The startup config goes through the store of the config, so that tests can run the
+    // startup config systems with `MemoryStartupConfigStore`.
+    type StartupConfigStore = MemoryStartupConfigStore;
 
-    fn update_startup_config(sc: NeoNexStartupConfigSet) -> Self::UpdateResult {
-        panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
-    }
+    type StorageError = StartupConfigFormatError;
 
-    fn setup_bevy<CONFIG: neonex_platform::NeoNexConfig>(
+    fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut bevy::app::App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> core::result::Result<(), bevy::ecs::error::BevyError> {
         panic!("MockPlatform should never be chosen at runtime! Its only purpose is to provide a full Rust-Analyzer support within NeoNex");
     }
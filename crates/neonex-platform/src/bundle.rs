@@ -0,0 +1,182 @@
+//! Portable bundles of the persisted startup config, e.g. for support engineers to get the
+//! settings of a user, or to move them to another machine.
+//!
+//! A bundle is a JSON document holding every namespace of the store, the versions of the
+//! schemas it was written with, and the app and platform it comes from. Importing a bundle
+//! runs it through the migrations and the validation rules, like a stored startup config.
+
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use neonex_shared::{
+    NeoNexStartupConfigSet, StartupConfigChange, UserStartupConfig,
+    migration::PersistedStartupConfig, namespaces::NamespacedStartupConfigSet,
+    validation::StartupConfigViolation,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, NeoNexPlatform, StartupConfigDecodeError,
+    StartupConfigFormat, StartupConfigFormatError, persist_startup_config,
+};
+
+/// The persisted startup config of an app, along with where it comes from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StartupConfigBundle {
+    /// `NeoNexConfig::APP_ID` of the app that exported it.
+    pub app_id: String,
+    /// `NeoNexPlatform::PLATFORM` the app ran on.
+    pub platform: String,
+    /// Every namespace, with the versions of their schemas.
+    pub startup_config: PersistedStartupConfig,
+}
+
+/// A bundle ready to be imported, to be shown to the user before replacing the store.
+#[derive(Debug, Clone)]
+pub struct StartupConfigImport<U> {
+    /// `NeoNexConfig::APP_ID` of the app that exported it.
+    pub app_id: String,
+    /// `NeoNexPlatform::PLATFORM` the app ran on.
+    pub platform: String,
+    /// The imported store, upgraded to the current schemas, without its invalid entries.
+    pub store: NamespacedStartupConfigSet<U>,
+    /// Modifications the import makes to each namespace, `None` being the global namespace.
+    /// Namespaces left untouched aren't listed.
+    pub changes: Vec<(Option<String>, Vec<StartupConfigChange<U>>)>,
+    /// Entries of the bundle dropped for breaking their validation rules, by namespace.
+    pub violations: Vec<(Option<String>, StartupConfigViolation)>,
+}
+
+/// Serializes a startup config store into a bundle, as pretty-printed JSON.
+///
+/// With the `integrity` feature, the entries of `NeoNexConfig::STARTUP_CONFIG_ENCRYPTED_KEYS`
+/// are left out: they're only meant to be readable on the installation that stored them.
+pub fn export_startup_config_bundle<CONFIG: NeoNexConfig>(
+    store: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> Result<Vec<u8>, StartupConfigFormatError> {
+    #[cfg(feature = "integrity")]
+    let store = &without_encrypted_entries::<CONFIG>(store);
+    let bundle = StartupConfigBundle {
+        app_id: String::from(CONFIG::APP_ID),
+        platform: String::from(<CONFIG::Platform as NeoNexPlatform>::PLATFORM),
+        startup_config: persist_startup_config::<CONFIG>(store)?,
+    };
+    serde_json::to_vec_pretty(&bundle).map_err(|error| StartupConfigFormatError {
+        format: Some(StartupConfigFormat::Json),
+        message: error.to_string(),
+        unsupported: false,
+    })
+}
+
+/// Parses a bundle exported by the same app, possibly on another platform or with an older
+/// version of the app, and compares it to `current`, the store it's about to replace.
+///
+/// With the `integrity` feature, the entries of `NeoNexConfig::STARTUP_CONFIG_ENCRYPTED_KEYS`
+/// aren't exported, so the ones of `current` are kept.
+pub fn preview_startup_config_bundle<CONFIG: NeoNexConfig>(
+    data: &[u8],
+    current: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> Result<StartupConfigImport<CONFIG::StartupConfig>, StartupConfigDecodeError> {
+    let format_error = |message| {
+        StartupConfigDecodeError::Format(StartupConfigFormatError {
+            format: Some(StartupConfigFormat::Json),
+            message,
+            unsupported: false,
+        })
+    };
+    let bundle: StartupConfigBundle =
+        serde_json::from_slice(data).map_err(|error| format_error(error.to_string()))?;
+    if bundle.app_id != CONFIG::APP_ID {
+        return Err(format_error(format!(
+            "the bundle was exported by {}, not by {}",
+            bundle.app_id,
+            CONFIG::APP_ID
+        )));
+    }
+
+    let mut store = bundle
+        .startup_config
+        .into_set(CONFIG::STARTUP_CONFIG_MIGRATIONS)
+        .map_err(StartupConfigDecodeError::Migration)?;
+    let mut violations = Vec::new();
+    for namespace in namespace_names(&[&store]) {
+        let set = store.get_mut(namespace.as_deref());
+        let found = set.retain_valid();
+        if let Some(name) = &namespace
+            && set.is_empty()
+            && set.dynamic().is_empty()
+        {
+            store.remove_namespace(name);
+        }
+        violations.extend(
+            found
+                .into_iter()
+                .map(|violation| (namespace.clone(), violation)),
+        );
+    }
+
+    #[cfg(feature = "integrity")]
+    keep_encrypted_entries::<CONFIG>(&mut store, current);
+
+    let empty = NeoNexStartupConfigSet::default();
+    let changes = namespace_names(&[current, &store])
+        .into_iter()
+        .filter_map(|namespace| {
+            let before = current.get(namespace.as_deref()).unwrap_or(&empty);
+            let after = store.get(namespace.as_deref()).unwrap_or(&empty);
+            let changes = before.diff(after);
+            (!changes.is_empty()).then_some((namespace, changes))
+        })
+        .collect();
+
+    Ok(StartupConfigImport {
+        app_id: bundle.app_id,
+        platform: bundle.platform,
+        store,
+        changes,
+        violations,
+    })
+}
+
+/// The global namespace, then every other namespace of `stores`.
+fn namespace_names<U: UserStartupConfig>(
+    stores: &[&NamespacedStartupConfigSet<U>],
+) -> Vec<Option<String>> {
+    let mut names = Vec::from([None]);
+    for (name, _) in stores.iter().flat_map(|store| store.namespaces()) {
+        if !names.iter().any(|known| known.as_deref() == Some(name)) {
+            names.push(Some(name.to_string()));
+        }
+    }
+    names
+}
+
+#[cfg(feature = "integrity")]
+fn without_encrypted_entries<CONFIG: NeoNexConfig>(
+    store: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> NamespacedStartupConfigSetOf<CONFIG> {
+    let mut store = store.clone();
+    for namespace in namespace_names(&[&store]) {
+        let set = store.get_mut(namespace.as_deref());
+        for key in CONFIG::STARTUP_CONFIG_ENCRYPTED_KEYS {
+            set.remove_by_key(key);
+        }
+    }
+    store
+}
+
+/// Sets the entries of `NeoNexConfig::STARTUP_CONFIG_ENCRYPTED_KEYS` of `current` in `store`.
+#[cfg(feature = "integrity")]
+fn keep_encrypted_entries<CONFIG: NeoNexConfig>(
+    store: &mut NamespacedStartupConfigSetOf<CONFIG>,
+    current: &NamespacedStartupConfigSetOf<CONFIG>,
+) {
+    for namespace in namespace_names(&[current]) {
+        let Some(set) = current.get(namespace.as_deref()) else {
+            continue;
+        };
+        for key in CONFIG::STARTUP_CONFIG_ENCRYPTED_KEYS {
+            if let Some(entry) = set.get_by_key(key) {
+                store.get_mut(namespace.as_deref()).set(entry.clone());
+            }
+        }
+    }
+}
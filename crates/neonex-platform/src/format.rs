@@ -0,0 +1,314 @@
+use bevy::platform::prelude::{String, ToString, Vec};
+use neonex_shared::migration::{MigrationError, PersistedStartupConfig};
+#[cfg(feature = "postcard")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "postcard")]
+use serde_json::{Map, Number, Value};
+
+/// How the startup config is serialized, chosen with `NeoNexConfig::STARTUP_CONFIG_FORMAT`.
+///
+/// The format is detected when reading, so switching formats keeps the stored entries: they
+/// are rewritten in the new format on the next save. Detecting a format requires its
+/// feature to be enabled though.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupConfigFormat {
+    Json,
+    /// Human-editable, for power users. Requires the `toml` feature.
+    #[cfg(feature = "toml")]
+    Toml,
+    /// Human-editable, for power users. Requires the `ron` feature.
+    #[cfg(feature = "ron")]
+    Ron,
+    /// Compact binary, for UEFI variables and embedded flash where size matters. Requires
+    /// the `postcard` feature.
+    #[cfg(feature = "postcard")]
+    Postcard,
+}
+
+/// Starts every startup config serialized with postcard, which isn't self-describing.
+const POSTCARD_MAGIC: &[u8] = b"NNXP";
+
+/// The data isn't valid in the format it has been detected as, or can't be serialized in
+/// the chosen format.
+#[derive(Debug)]
+pub struct StartupConfigFormatError {
+    /// `None` when the format couldn't be detected, or isn't enabled.
+    pub format: Option<StartupConfigFormat>,
+    pub message: String,
+    /// Whether the data is in a format whose feature isn't enabled, in which case it may
+    /// well be valid.
+    pub unsupported: bool,
+}
+
+impl core::fmt::Display for StartupConfigFormatError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.format {
+            Some(format) => write!(f, "invalid {format:?} startup config: {}", self.message),
+            None => write!(f, "unknown startup config format: {}", self.message),
+        }
+    }
+}
+
+impl core::error::Error for StartupConfigFormatError {}
+
+/// Why stored data couldn't be turned back into a startup config set.
+#[derive(Debug)]
+pub enum StartupConfigDecodeError {
+    Format(StartupConfigFormatError),
+    Migration(MigrationError),
+    #[cfg(feature = "integrity")]
+    Integrity(crate::StartupConfigIntegrityError),
+}
+
+impl core::fmt::Display for StartupConfigDecodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StartupConfigDecodeError::Format(error) => error.fmt(f),
+            StartupConfigDecodeError::Migration(error) => error.fmt(f),
+            #[cfg(feature = "integrity")]
+            StartupConfigDecodeError::Integrity(error) => error.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for StartupConfigDecodeError {}
+
+impl StartupConfigFormat {
+    /// Every format whose feature is enabled.
+    pub const ENABLED: &'static [StartupConfigFormat] = &[
+        StartupConfigFormat::Json,
+        #[cfg(feature = "toml")]
+        StartupConfigFormat::Toml,
+        #[cfg(feature = "ron")]
+        StartupConfigFormat::Ron,
+        #[cfg(feature = "postcard")]
+        StartupConfigFormat::Postcard,
+    ];
+
+    /// Extension of the files holding a startup config in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            StartupConfigFormat::Json => "json",
+            #[cfg(feature = "toml")]
+            StartupConfigFormat::Toml => "toml",
+            #[cfg(feature = "ron")]
+            StartupConfigFormat::Ron => "ron",
+            #[cfg(feature = "postcard")]
+            StartupConfigFormat::Postcard => "bin",
+        }
+    }
+
+    /// Detects the format of stored data: postcard starts with a magic number, JSON with
+    /// `{`, RON with `(`, and TOML with anything else.
+    pub fn detect(data: &[u8]) -> Result<Self, StartupConfigFormatError> {
+        let unknown = |message: &str, unsupported| StartupConfigFormatError {
+            format: None,
+            message: message.to_string(),
+            unsupported,
+        };
+
+        if data.starts_with(POSTCARD_MAGIC) {
+            #[cfg(feature = "postcard")]
+            return Ok(StartupConfigFormat::Postcard);
+            #[cfg(not(feature = "postcard"))]
+            return Err(unknown("postcard data, without the `postcard` feature", true));
+        }
+
+        let text = core::str::from_utf8(data).map_err(|_| unknown("binary data", false))?;
+        match text.trim_start().chars().next() {
+            Some('{') => Ok(StartupConfigFormat::Json),
+            #[cfg(feature = "ron")]
+            Some('(') => Ok(StartupConfigFormat::Ron),
+            #[cfg(not(feature = "ron"))]
+            Some('(') => Err(unknown("RON data, without the `ron` feature", true)),
+            #[cfg(feature = "toml")]
+            _ => Ok(StartupConfigFormat::Toml),
+            #[cfg(not(feature = "toml"))]
+            _ => Err(unknown("TOML data, without the `toml` feature", true)),
+        }
+    }
+
+    pub(crate) fn serialize(
+        self,
+        persisted: &PersistedStartupConfig,
+    ) -> Result<Vec<u8>, StartupConfigFormatError> {
+        let error = |message: String| StartupConfigFormatError {
+            format: Some(self),
+            message,
+            unsupported: false,
+        };
+
+        match self {
+            StartupConfigFormat::Json => {
+                serde_json::to_vec(persisted).map_err(|e| error(e.to_string()))
+            }
+            #[cfg(feature = "toml")]
+            StartupConfigFormat::Toml => toml::to_string_pretty(persisted)
+                .map(String::into_bytes)
+                .map_err(|e| error(e.to_string())),
+            #[cfg(feature = "ron")]
+            StartupConfigFormat::Ron => {
+                ron::ser::to_string_pretty(persisted, ron::ser::PrettyConfig::default())
+                    .map(String::into_bytes)
+                    .map_err(|e| error(e.to_string()))
+            }
+            #[cfg(feature = "postcard")]
+            StartupConfigFormat::Postcard => {
+                let compact = CompactPersisted::from(persisted);
+                let namespaces: CompactNamespaces = persisted
+                    .namespaces
+                    .iter()
+                    .map(|(name, values)| {
+                        (
+                            name.clone(),
+                            values.iter().map(CompactValue::from).collect(),
+                        )
+                    })
+                    .collect();
+                let mut data = Vec::from(POSTCARD_MAGIC);
+                data.extend(postcard::to_allocvec(&compact).map_err(|e| error(e.to_string()))?);
+                if !namespaces.is_empty() {
+                    data.extend(
+                        postcard::to_allocvec(&namespaces).map_err(|e| error(e.to_string()))?,
+                    );
+                }
+                Ok(data)
+            }
+        }
+    }
+
+    pub(crate) fn deserialize(
+        self,
+        data: &[u8],
+    ) -> Result<PersistedStartupConfig, StartupConfigFormatError> {
+        let error = |message: String| StartupConfigFormatError {
+            format: Some(self),
+            message,
+            unsupported: false,
+        };
+        #[cfg(any(feature = "toml", feature = "ron"))]
+        let text = || core::str::from_utf8(data).map_err(|e| error(e.to_string()));
+
+        match self {
+            StartupConfigFormat::Json => {
+                serde_json::from_slice(data).map_err(|e| error(e.to_string()))
+            }
+            #[cfg(feature = "toml")]
+            StartupConfigFormat::Toml => toml::from_str(text()?).map_err(|e| error(e.to_string())),
+            #[cfg(feature = "ron")]
+            StartupConfigFormat::Ron => ron::from_str(text()?).map_err(|e| error(e.to_string())),
+            #[cfg(feature = "postcard")]
+            StartupConfigFormat::Postcard => {
+                let (compact, rest) =
+                    postcard::take_from_bytes::<CompactPersisted>(&data[POSTCARD_MAGIC.len()..])
+                        .map_err(|e| error(e.to_string()))?;
+                let mut persisted = PersistedStartupConfig::from(compact);
+                if !rest.is_empty() {
+                    let namespaces = postcard::from_bytes::<CompactNamespaces>(rest)
+                        .map_err(|e| error(e.to_string()))?;
+                    persisted.namespaces = namespaces
+                        .into_iter()
+                        .map(|(name, values)| (name, values.into_iter().map(Value::from).collect()))
+                        .collect();
+                }
+                Ok(persisted)
+            }
+        }
+    }
+}
+
+/// [`PersistedStartupConfig`] for postcard, whose entries have to describe their own layout
+/// to be read back without knowing their type, so that they can still be migrated.
+#[cfg(feature = "postcard")]
+#[derive(Serialize, Deserialize)]
+struct CompactPersisted {
+    neonex_version: u32,
+    version: u32,
+    values: Vec<CompactValue>,
+}
+
+/// Entries of the namespaces other than the global one. Written after the
+/// [`CompactPersisted`], and only when there are some, so that data written before namespaces
+/// existed can still be read.
+#[cfg(feature = "postcard")]
+type CompactNamespaces = Vec<(String, Vec<CompactValue>)>;
+
+#[cfg(feature = "postcard")]
+#[derive(Serialize, Deserialize)]
+enum CompactValue {
+    Null,
+    Bool(bool),
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    String(String),
+    Array(Vec<CompactValue>),
+    Object(Vec<(String, CompactValue)>),
+}
+
+#[cfg(feature = "postcard")]
+impl From<&PersistedStartupConfig> for CompactPersisted {
+    fn from(persisted: &PersistedStartupConfig) -> Self {
+        Self {
+            neonex_version: persisted.neonex_version,
+            version: persisted.version,
+            values: persisted.values.iter().map(CompactValue::from).collect(),
+        }
+    }
+}
+
+#[cfg(feature = "postcard")]
+impl From<CompactPersisted> for PersistedStartupConfig {
+    fn from(compact: CompactPersisted) -> Self {
+        Self {
+            neonex_version: compact.neonex_version,
+            version: compact.version,
+            values: compact.values.into_iter().map(Value::from).collect(),
+            namespaces: Default::default(),
+        }
+    }
+}
+
+#[cfg(feature = "postcard")]
+impl From<&Value> for CompactValue {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::Null => CompactValue::Null,
+            Value::Bool(bool) => CompactValue::Bool(*bool),
+            Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+                (Some(unsigned), _) => CompactValue::Unsigned(unsigned),
+                (None, Some(signed)) => CompactValue::Signed(signed),
+                _ => CompactValue::Float(number.as_f64().unwrap_or_default()),
+            },
+            Value::String(string) => CompactValue::String(string.clone()),
+            Value::Array(array) => CompactValue::Array(array.iter().map(Self::from).collect()),
+            Value::Object(object) => CompactValue::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Self::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "postcard")]
+impl From<CompactValue> for Value {
+    fn from(compact: CompactValue) -> Self {
+        match compact {
+            CompactValue::Null => Value::Null,
+            CompactValue::Bool(bool) => Value::Bool(bool),
+            CompactValue::Unsigned(unsigned) => Value::Number(unsigned.into()),
+            CompactValue::Signed(signed) => Value::Number(signed.into()),
+            CompactValue::Float(float) => Number::from_f64(float).map_or(Value::Null, Value::Number),
+            CompactValue::String(string) => Value::String(string),
+            CompactValue::Array(array) => Value::Array(array.into_iter().map(Self::from).collect()),
+            CompactValue::Object(object) => Value::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, Self::from(value)))
+                    .collect::<Map<_, _>>(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,772 @@
+//! Startup config files, for the platforms storing the startup config on a filesystem.
+//!
+//! The file may be shared by several processes, like a launcher and the target it starts,
+//! so every modification happens under an advisory lock, held on a `.lock` file next to it.
+//! The lock doesn't depend on the format of the file, so that a file written in another
+//! format is never read, backed up and replaced at once.
+//! Writes go to a `.tmp` file which is synced then renamed over the previous file, so a
+//! crash leaves either the previous or the new config, never a mix of both.
+//!
+//! On Linux, the file lives in `$XDG_CONFIG_HOME` (`~/.config` by default), and its backups
+//! in `$XDG_STATE_HOME` (`~/.local/state` by default). Elsewhere, they live in the temp dir.
+//! Read-only system-wide and enforced files may also be shipped next to the executable.
+//!
+//! With the `integrity` feature, the file is sealed with an installation key, kept in an
+//! `installation.key` file of its state dir, readable by its owner only. An unsigned file,
+//! written before the feature was enabled or by another tool, is sealed once first read.
+
+use std::{
+    env,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    iter,
+    path::{Path, PathBuf},
+    sync::Once,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use neonex_shared::migration::PersistedStartupConfig;
+use notify::{EventKind, RecursiveMode, Watcher};
+
+#[cfg(feature = "integrity")]
+use crate::InstallationKey;
+use crate::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, StartupConfigDecodeError, StartupConfigFormat,
+    StartupConfigLoadError, StartupConfigStore, StartupConfigWatcher, decode_startup_config,
+    is_legacy_startup_config_key, startup_config_key,
+    startup_config_key_for,
+};
+
+/// Where the startup config of `CONFIG` is stored:
+/// `<config dir>/<app id>/startup-config.<format extension>`.
+pub fn startup_config_path<CONFIG: NeoNexConfig>() -> PathBuf {
+    startup_config_path_for(CONFIG::APP_ID, CONFIG::STARTUP_CONFIG_FORMAT)
+}
+
+/// [`startup_config_path`] of the app identified by `app_id`, for when it's only known at
+/// runtime. The file written in another format is found by [`read_persisted_startup_config`].
+pub fn startup_config_path_for(app_id: &str, format: StartupConfigFormat) -> PathBuf {
+    let mut path = config_dir();
+    path.push(startup_config_key_for(app_id));
+    path.push("startup-config");
+    path.set_extension(format.extension());
+    path
+}
+
+/// Where the credential vault of `CONFIG` is stored, next to its startup config:
+/// `<config dir>/<app id>/credentials.vault`. See [`CredentialVault`](crate::CredentialVault).
+#[cfg(feature = "vault")]
+pub fn credential_vault_path<CONFIG: NeoNexConfig>() -> PathBuf {
+    startup_config_path::<CONFIG>().with_file_name("credentials.vault")
+}
+
+/// Where the system-wide startup config of `CONFIG` is shipped:
+/// `<executable dir>/<app id>.startup-config.<format extension>`.
+pub fn system_startup_config_path<CONFIG: NeoNexConfig>() -> io::Result<PathBuf> {
+    let mut path = env::current_exe()?;
+    path.set_file_name(format!("{}.startup-config", startup_config_key::<CONFIG>()));
+    path.set_extension(CONFIG::STARTUP_CONFIG_FORMAT.extension());
+    Ok(path)
+}
+
+/// Where the enforced startup config of `CONFIG` is shipped, pinning entries over every
+/// other layer: `<executable dir>/<app id>.enforced-startup-config.<format extension>`.
+pub fn enforced_startup_config_path<CONFIG: NeoNexConfig>() -> io::Result<PathBuf> {
+    let mut path = env::current_exe()?;
+    path.set_file_name(format!(
+        "{}.enforced-startup-config",
+        startup_config_key::<CONFIG>()
+    ));
+    path.set_extension(CONFIG::STARTUP_CONFIG_FORMAT.extension());
+    Ok(path)
+}
+
+/// `path` with the extensions of the other enabled formats, i.e. where the startup config
+/// was stored before switching formats. Files in the formats that aren't enabled are never
+/// read, nor removed.
+fn other_format_paths(path: &Path) -> impl Iterator<Item = PathBuf> {
+    StartupConfigFormat::ENABLED
+        .iter()
+        .map(|format| format.extension())
+        .filter(|extension| path.extension().is_none_or(|current| current != *extension))
+        .map(|extension| path.with_extension(extension))
+}
+
+fn config_dir() -> PathBuf {
+    #[cfg(target_os = "linux")]
+    {
+        // Relative paths are invalid in XDG variables and must be ignored.
+        let xdg_config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute());
+        let home_config = env::var_os("HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .map(|home| home.join(".config"));
+        if let Some(dir) = xdg_config_home.or(home_config) {
+            return dir;
+        }
+    }
+    env::temp_dir()
+}
+
+fn state_dir() -> PathBuf {
+    #[cfg(target_os = "linux")]
+    {
+        let xdg_state_home = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute());
+        let home_state = env::var_os("HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .map(|home| home.join(".local").join("state"));
+        if let Some(dir) = xdg_state_home.or(home_state) {
+            return dir;
+        }
+    }
+    env::temp_dir().join("neonex-state")
+}
+
+/// Where the files kept for the startup config file at `path` are stored, e.g. its backups.
+/// The files of the config dir keep them at the same place in the state dir, the others in a
+/// `.neonex-state` dir next to them.
+fn state_dir_of(path: &Path) -> PathBuf {
+    let parent = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    match parent.strip_prefix(config_dir()) {
+        Ok(relative) => state_dir().join(relative),
+        Err(_) => parent.join(".neonex-state"),
+    }
+}
+
+/// Creates the state dir `dir`, readable by its owner only.
+fn create_state_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Removes the startup config files left in the temp dir under random names, see
+/// [`is_legacy_startup_config_key`].
+pub fn remove_legacy_startup_configs() -> io::Result<()> {
+    remove_legacy_startup_configs_in(&env::temp_dir())
+}
+
+/// Runs [`remove_legacy_startup_configs`] once per installation, the first time a startup
+/// config is loaded, rather than scanning the temp dir on every load. Best effort, it's
+/// retried on the next run when it fails.
+fn migrate_legacy_startup_configs() {
+    static MIGRATION: Once = Once::new();
+    MIGRATION.call_once(|| {
+        let marker = state_dir().join("legacy-startup-configs-removed");
+        if marker.exists() || remove_legacy_startup_configs().is_err() {
+            return;
+        }
+        let _ = create_state_dir(&state_dir()).and_then(|()| File::create(marker));
+    });
+}
+
+fn remove_legacy_startup_configs_in(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let is_legacy = entry
+            .file_name()
+            .to_str()
+            .is_some_and(is_legacy_startup_config_key);
+        if is_legacy && entry.file_type()?.is_file() {
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Keeps the startup config of `CONFIG` in the file at [`startup_config_path`], for the
+/// platforms with a filesystem. Legacy files are removed before it's first read. The system
+/// and enforced ones are shipped next to the executable.
+pub struct FileStartupConfigStore;
+
+impl StartupConfigStore for FileStartupConfigStore {
+    type Error = io::Error;
+
+    fn load<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+        migrate_legacy_startup_configs();
+        read_startup_config::<CONFIG>(&startup_config_path::<CONFIG>())
+    }
+
+    fn reload<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+        reread_startup_config::<CONFIG>(&startup_config_path::<CONFIG>())
+    }
+
+    fn load_system<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+        read_system_startup_config::<CONFIG>()
+    }
+
+    fn load_enforced<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+        read_enforced_startup_config::<CONFIG>()
+    }
+
+    fn save<CONFIG: NeoNexConfig>(
+        startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+    ) -> io::Result<()> {
+        write_startup_config::<CONFIG>(&startup_config_path::<CONFIG>(), startup_config_set)
+    }
+
+    fn back_up<CONFIG: NeoNexConfig>() -> io::Result<Option<String>> {
+        back_up_startup_config(&startup_config_path::<CONFIG>())
+    }
+
+    fn watch<CONFIG: NeoNexConfig>() -> Option<StartupConfigWatcher> {
+        watch_startup_config(&startup_config_path::<CONFIG>()).ok()
+    }
+}
+
+/// Reads the startup config store stored at `path`, with all of its namespaces.
+///
+/// When it's missing, the file written in another format is read instead. When both are
+/// missing, the store is empty. A file that can't be parsed, even once migrated, is moved to
+/// a timestamped `.bak` file in the state dir, so it can be recovered by hand. A file written
+/// by a newer version of the app, or in a format that isn't enabled, is left untouched.
+///
+/// With the `integrity` feature, an unsigned file written by this version of the app or an
+/// older one is read then sealed, rather than being taken for a corrupt one.
+pub fn read_startup_config<CONFIG: NeoNexConfig>(
+    path: &Path,
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let path = existing_format_path(path);
+    // Writes replace the file atomically, so it can be read without holding the lock. This
+    // also keeps read-only directories readable.
+    let data = read(&path).map_err(StartupConfigLoadError::Storage)?;
+    match decode::<CONFIG>(&path, &data).map_err(StartupConfigLoadError::Storage)? {
+        Ok(startup_config_set) => Ok(startup_config_set),
+        // It may have been replaced since it was read, so it's only moved once read again
+        // under the lock.
+        Err(_) => read_or_back_up::<CONFIG>(&path),
+    }
+}
+
+/// Reads the startup config file at `path` under its lock, moving it to its [`backup_path`]
+/// when it's corrupt.
+fn read_or_back_up<CONFIG: NeoNexConfig>(
+    path: &Path,
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let _lock = lock(path).map_err(StartupConfigLoadError::Storage)?;
+    let data = read(path).map_err(StartupConfigLoadError::Storage)?;
+    #[cfg(feature = "integrity")]
+    if !crate::is_signed(&data) {
+        return seal_unsigned::<CONFIG>(path, &data);
+    }
+    decode::<CONFIG>(path, &data)
+        .map_err(StartupConfigLoadError::Storage)?
+        .map_err(|error| StartupConfigLoadError::from_decode_error(error, || move_aside(path)))
+}
+
+/// Parses the unsigned data of the startup config file at `path`, then seals it in place.
+/// Data written by a newer version of the app is left untouched, and corrupt data is moved
+/// aside, as when it's signed.
+#[cfg(feature = "integrity")]
+fn seal_unsigned<CONFIG: NeoNexConfig>(
+    path: &Path,
+    data: &[u8],
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let startup_config_set = decode_startup_config::<CONFIG>(data)
+        .map_err(|error| StartupConfigLoadError::from_decode_error(error, || move_aside(path)))?;
+    if !data.trim_ascii().is_empty() {
+        let sealed =
+            encode::<CONFIG>(path, &startup_config_set).map_err(StartupConfigLoadError::Storage)?;
+        write_atomically(path, &sealed).map_err(StartupConfigLoadError::Storage)?;
+    }
+    Ok(startup_config_set)
+}
+
+/// Moves the corrupt startup config file at `path` to its [`backup_path`], returning where.
+fn move_aside(path: &Path) -> Option<String> {
+    let backup = backup_path(path).ok()?;
+    if fs::rename(path, &backup).is_err() {
+        let _ = fs::remove_file(&backup);
+        return None;
+    }
+    Some(backup.display().to_string())
+}
+
+/// Reads the startup config store stored at `path` again, once it has been modified outside
+/// of the app. Unlike [`read_startup_config`], a corrupt file is left untouched, as it may be
+/// in the middle of being edited.
+pub fn reread_startup_config<CONFIG: NeoNexConfig>(
+    path: &Path,
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let path = existing_format_path(path);
+    let data = read(&path).map_err(StartupConfigLoadError::Storage)?;
+    decode::<CONFIG>(&path, &data)
+        .map_err(StartupConfigLoadError::Storage)?
+        .map_err(|error| StartupConfigLoadError::from_decode_error(error, || None))
+}
+
+/// Reads the startup config file stored at `path`, or the one written in another format,
+/// without parsing nor upgrading its entries, e.g. for apps that don't know their schema.
+/// Unlike [`read_startup_config`], a corrupt file is left untouched, and nothing is written,
+/// not even a missing installation key.
+pub fn read_persisted_startup_config(
+    path: &Path,
+) -> Result<PersistedStartupConfig, StartupConfigLoadError<io::Error>> {
+    let path = existing_format_path(path);
+    let data = read(&path).map_err(StartupConfigLoadError::Storage)?;
+    decode_persisted_read_only(&path, &data)
+        .map_err(StartupConfigLoadError::Storage)?
+        .map_err(|error| StartupConfigLoadError::from_decode_error(error, || None))
+}
+
+/// Reads the system-wide startup config store, see [`system_startup_config_path`]. It's
+/// empty when there's no such file. Unlike the user file, a corrupt file is left untouched.
+pub fn read_system_startup_config<CONFIG: NeoNexConfig>()
+-> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let path = system_startup_config_path::<CONFIG>().map_err(StartupConfigLoadError::Storage)?;
+    read_shipped_startup_config::<CONFIG>(&path)
+}
+
+/// Reads the enforced startup config store, see [`enforced_startup_config_path`]. Like the
+/// system-wide one, it's empty when there's no such file, and left untouched when corrupt.
+pub fn read_enforced_startup_config<CONFIG: NeoNexConfig>()
+-> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let path =
+        enforced_startup_config_path::<CONFIG>().map_err(StartupConfigLoadError::Storage)?;
+    read_shipped_startup_config::<CONFIG>(&path)
+}
+
+/// Reads a read-only startup config store shipped along with the app.
+fn read_shipped_startup_config<CONFIG: NeoNexConfig>(
+    path: &Path,
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<io::Error>> {
+    let data = read(&existing_format_path(path)).map_err(StartupConfigLoadError::Storage)?;
+    decode_startup_config::<CONFIG>(&data)
+        .map_err(|error| StartupConfigLoadError::from_decode_error(error, || None))
+}
+
+/// `path`, or the file written in another format when it's missing.
+fn existing_format_path(path: &Path) -> PathBuf {
+    iter::once(path.to_path_buf())
+        .chain(other_format_paths(path))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Copies the startup config file stored at `path`, in whichever format, to a timestamped
+/// `.bak` file in the state dir, returning the path of the copy. Returns `None` when there's
+/// no such file.
+pub fn back_up_startup_config(path: &Path) -> io::Result<Option<String>> {
+    let path = existing_format_path(path);
+    let _lock = lock(&path)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let backup = backup_path(&path)?;
+    fs::copy(&path, &backup)?;
+    Ok(Some(backup.display().to_string()))
+}
+
+/// A new, empty `<state dir>/<file name>.<unix timestamp>.bak` file, followed by a counter
+/// when there's already a backup from the same second. Creates the state dir when needed.
+fn backup_path(path: &Path) -> io::Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let dir = state_dir_of(path);
+    create_state_dir(&dir)?;
+    let file = dir.join(path.file_name().unwrap_or(path.as_os_str()));
+    let mut backup = sibling(&file, &format!(".{timestamp}.bak"));
+    for counter in 1.. {
+        match OpenOptions::new().write(true).create_new(true).open(&backup) {
+            Ok(_) => break,
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+                backup = sibling(&file, &format!(".{timestamp}-{counter}.bak"));
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(backup)
+}
+
+/// Replaces the startup config store stored at `path`, removing the files written in other
+/// formats.
+pub fn write_startup_config<CONFIG: NeoNexConfig>(
+    path: &Path,
+    startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> io::Result<()> {
+    let data = encode::<CONFIG>(path, startup_config_set)?;
+    let _lock = lock(path)?;
+    write_atomically(path, &data)?;
+    for other in other_format_paths(path) {
+        match fs::remove_file(other) {
+            Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Reads, modifies then writes back the startup config store stored at `path`, without
+/// letting another process write it in between.
+pub fn modify_startup_config<CONFIG: NeoNexConfig>(
+    path: &Path,
+    modify: impl FnOnce(&mut NamespacedStartupConfigSetOf<CONFIG>),
+) -> io::Result<()> {
+    let _lock = lock(path)?;
+    let mut startup_config_set = match decode::<CONFIG>(path, &read(path)?)? {
+        Ok(startup_config_set) => startup_config_set,
+        Err(error) => return Err(io::Error::new(ErrorKind::InvalidData, error)),
+    };
+    modify(&mut startup_config_set);
+    let data = encode::<CONFIG>(path, &startup_config_set)?;
+    write_atomically(path, &data)
+}
+
+/// Parses the data of the startup config file at `path`.
+fn decode<CONFIG: NeoNexConfig>(
+    path: &Path,
+    data: &[u8],
+) -> io::Result<Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigDecodeError>> {
+    Ok(decode_persisted(path, data)?.and_then(|persisted| {
+        persisted
+            .into_set(CONFIG::STARTUP_CONFIG_MIGRATIONS)
+            .map_err(StartupConfigDecodeError::Migration)
+    }))
+}
+
+/// Parses the data of the startup config file at `path`, without parsing its entries.
+///
+/// With the `integrity` feature, the data must be sealed with the installation key, and
+/// unsigned data is rejected.
+#[cfg_attr(not(feature = "integrity"), allow(unused_variables))]
+fn decode_persisted(
+    path: &Path,
+    data: &[u8],
+) -> io::Result<Result<PersistedStartupConfig, StartupConfigDecodeError>> {
+    #[cfg(feature = "integrity")]
+    let persisted = crate::open_persisted_startup_config(data, &installation_key(path)?);
+    #[cfg(not(feature = "integrity"))]
+    let persisted = crate::deserialize_startup_config(data);
+    Ok(persisted)
+}
+
+/// Like [`decode_persisted`], without creating the installation key when it's missing. Data
+/// can't have been sealed without it, so only empty data is read then.
+#[cfg_attr(not(feature = "integrity"), allow(unused_variables))]
+fn decode_persisted_read_only(
+    path: &Path,
+    data: &[u8],
+) -> io::Result<Result<PersistedStartupConfig, StartupConfigDecodeError>> {
+    #[cfg(feature = "integrity")]
+    let persisted = match existing_installation_key(path)? {
+        Some(key) => crate::open_persisted_startup_config(data, &key),
+        None if data.trim_ascii().is_empty() => Ok(PersistedStartupConfig::default()),
+        None => Err(StartupConfigDecodeError::Integrity(
+            crate::StartupConfigIntegrityError::MissingKey,
+        )),
+    };
+    #[cfg(not(feature = "integrity"))]
+    let persisted = crate::deserialize_startup_config(data);
+    Ok(persisted)
+}
+
+#[cfg_attr(not(feature = "integrity"), allow(unused_variables))]
+fn encode<CONFIG: NeoNexConfig>(
+    path: &Path,
+    startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> io::Result<Vec<u8>> {
+    #[cfg(feature = "integrity")]
+    let data = crate::seal_startup_config::<CONFIG>(startup_config_set, &installation_key(path)?);
+    #[cfg(not(feature = "integrity"))]
+    let data = crate::encode_startup_config::<CONFIG>(startup_config_set);
+    data.map_err(io::Error::other)
+}
+
+/// Reads the installation key kept in the state dir of the startup config file at `path`,
+/// creating it when it's missing.
+#[cfg(feature = "integrity")]
+pub(crate) fn installation_key(path: &Path) -> io::Result<InstallationKey> {
+    if let Some(key) = existing_installation_key(path)? {
+        return Ok(key);
+    }
+
+    let key_path = installation_key_path(path);
+    let key = InstallationKey::generate().map_err(io::Error::other)?;
+    create_state_dir(&state_dir_of(path))?;
+    let temp_path = sibling(&key_path, &format!(".{}.tmp", std::process::id()));
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(&temp_path)?;
+    file.write_all(key.as_bytes())?;
+    file.sync_all()?;
+
+    // Linking fails when another process created the key in the meantime, in which case
+    // its key is used.
+    let linked = fs::hard_link(&temp_path, &key_path);
+    fs::remove_file(&temp_path)?;
+    match linked {
+        Ok(()) => Ok(key),
+        Err(error) if error.kind() == ErrorKind::AlreadyExists => installation_key(path),
+        Err(error) => Err(error),
+    }
+}
+
+/// Reads the installation key kept in the state dir of the startup config file at `path`, if
+/// there's one.
+#[cfg(feature = "integrity")]
+fn existing_installation_key(path: &Path) -> io::Result<Option<InstallationKey>> {
+    match fs::read(installation_key_path(path)) {
+        Ok(key) => key
+            .try_into()
+            .map(|key| Some(InstallationKey::from_bytes(key)))
+            .map_err(|_| io::Error::new(ErrorKind::InvalidData, "invalid installation key")),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(feature = "integrity")]
+fn installation_key_path(path: &Path) -> PathBuf {
+    state_dir_of(path).join("installation.key")
+}
+
+/// Watches the startup config file at `path`, including when it's replaced by a rename.
+pub fn watch_startup_config(path: &Path) -> io::Result<StartupConfigWatcher> {
+    let dir = match path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        Some(parent) => parent.to_path_buf(),
+        None => env::current_dir()?,
+    };
+    fs::create_dir_all(&dir)?;
+    let file_name = path.file_name().map(OsString::from);
+
+    let (watcher, notifier) = StartupConfigWatcher::new();
+    let mut file_watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        let relevant = !matches!(event.kind, EventKind::Access(_))
+            && event
+                .paths
+                .iter()
+                .any(|path| path.file_name().map(OsString::from) == file_name);
+        if relevant {
+            notifier.notify();
+        }
+    })
+    .map_err(io::Error::other)?;
+    // The whole directory is watched, since the file itself is replaced on each write.
+    file_watcher
+        .watch(&dir, RecursiveMode::NonRecursive)
+        .map_err(io::Error::other)?;
+
+    Ok(watcher.keep_alive(file_watcher))
+}
+
+/// `path`, with `extension` appended to its file name.
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut sibling = OsString::from(path.as_os_str());
+    sibling.push(extension);
+    sibling.into()
+}
+
+/// Blocks until the lock of `path` is acquired. It's released once the file is dropped.
+pub(crate) fn lock(path: &Path) -> io::Result<File> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path(path))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// The file locked for `path`, `path` without its extension, if any, then `.lock`: files of
+/// every format share it.
+fn lock_path(path: &Path) -> PathBuf {
+    path.with_extension("lock")
+}
+
+fn read(path: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        result => result,
+    }
+}
+
+pub(crate) fn write_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    write_atomically_with(path, data, OpenOptions::new())
+}
+
+/// Like [`write_atomically`], with a file readable by its owner only, e.g. for secrets.
+#[cfg(feature = "vault")]
+pub(crate) fn write_private_atomically(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    write_atomically_with(path, data, options)
+}
+
+fn write_atomically_with(path: &Path, data: &[u8], mut options: OpenOptions) -> io::Result<()> {
+    let temp_path = sibling(path, ".tmp");
+    // A temp file left by a crash keeps its mode when opened, so it's replaced.
+    match fs::remove_file(&temp_path) {
+        Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+    let mut file = options.write(true).create_new(true).open(&temp_path)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)?;
+
+    // Persist the rename itself. Directories can't be opened as files on every platform,
+    // in which case the rename is left to the OS.
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn files_outside_the_config_dir_keep_their_state_next_to_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("startup-config.json");
+        assert_eq!(state_dir_of(&path), dir.path().join(".neonex-state"));
+    }
+
+    #[test]
+    fn files_of_the_config_dir_keep_their_state_in_the_state_dir() {
+        let path = startup_config_path_for("org.neonex.test", StartupConfigFormat::Json);
+        assert_eq!(
+            state_dir_of(&path),
+            state_dir().join(startup_config_key_for("org.neonex.test"))
+        );
+    }
+
+    #[test]
+    fn backups_go_to_the_state_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("startup-config.json");
+        fs::write(&path, b"{}").unwrap();
+
+        let backup = back_up_startup_config(&path).unwrap().unwrap();
+        assert!(Path::new(&backup).starts_with(dir.path().join(".neonex-state")));
+        assert_eq!(fs::read(&backup).unwrap(), b"{}");
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn files_of_every_format_share_their_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("startup-config.json");
+        let ron = dir.path().join("startup-config.ron");
+        assert_eq!(lock_path(&json), dir.path().join("startup-config.lock"));
+        assert_eq!(lock_path(&json), lock_path(&ron));
+
+        let _lock = lock(&json).unwrap();
+        let other = File::options().write(true).open(lock_path(&ron)).unwrap();
+        assert!(other.try_lock().is_err());
+    }
+
+    #[cfg(feature = "integrity")]
+    #[test]
+    fn unsigned_files_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("startup-config.json");
+
+        // Even while the installation key has just been created.
+        let error = decode_persisted(&path, br#"{ "neonex_version": 1 }"#)
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            StartupConfigDecodeError::Integrity(crate::StartupConfigIntegrityError::Unsigned)
+        ));
+    }
+
+    #[cfg(feature = "integrity")]
+    #[test]
+    fn installation_keys_are_kept_in_the_state_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("startup-config.json");
+
+        let key = installation_key(&path).unwrap();
+        let key_path = dir.path().join(".neonex-state").join("installation.key");
+        assert_eq!(fs::read(&key_path).unwrap(), key.as_bytes());
+        assert!(!dir.path().join("installation.key").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&key_path), 0o600);
+            assert_eq!(mode(key_path.parent().unwrap()), 0o700);
+        }
+    }
+
+    #[cfg(feature = "integrity")]
+    #[test]
+    fn reading_the_persisted_file_creates_no_installation_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("startup-config.json");
+
+        assert!(read_persisted_startup_config(&path).unwrap().values.is_empty());
+        fs::write(&path, b"{}").unwrap();
+        let error = read_persisted_startup_config(&path).unwrap_err();
+        assert!(matches!(
+            error,
+            StartupConfigLoadError::Corrupt {
+                error: StartupConfigDecodeError::Integrity(
+                    crate::StartupConfigIntegrityError::MissingKey
+                ),
+                ..
+            }
+        ));
+        assert!(!dir.path().join(".neonex-state").exists());
+
+        // Once the app created the key, the file is checked with it.
+        installation_key(&path).unwrap();
+        let error = read_persisted_startup_config(&path).unwrap_err();
+        assert!(matches!(
+            error,
+            StartupConfigLoadError::Corrupt {
+                error: StartupConfigDecodeError::Integrity(
+                    crate::StartupConfigIntegrityError::Unsigned
+                ),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn only_legacy_files_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir
+            .path()
+            .join("0123456789abcdef0123456789abcdef-neonex-startup-config.json");
+        let other = dir.path().join("other-neonex-startup-config.json");
+        fs::write(&legacy, b"{}").unwrap();
+        fs::write(&other, b"{}").unwrap();
+
+        remove_legacy_startup_configs_in(dir.path()).unwrap();
+        assert!(!legacy.exists());
+        assert!(other.exists());
+    }
+}
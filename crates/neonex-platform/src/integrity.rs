@@ -0,0 +1,235 @@
+//! Signature and encryption of the persisted startup config, with a key per installation.
+//!
+//! Sealed data starts with a `NNXS:<base64 HMAC-SHA256>` line, followed by the startup
+//! config in its format. The signature covers the whole config, so a launched app knows the
+//! values really come from an app of the same installation, like its launcher. The entries
+//! listed in `NeoNexConfig::STARTUP_CONFIG_ENCRYPTED_KEYS` keep their key, their value
+//! being replaced by `{"$encrypted": "<base64 nonce and ChaCha20-Poly1305 ciphertext>"}`
+//! before being signed.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use chacha20poly1305::{ChaCha20Poly1305, KeyInit, Nonce, aead::Aead};
+use hmac::{Hmac, Mac};
+use serde_json::{Map, Value};
+use sha2::Sha256;
+
+use neonex_shared::{migration::PersistedStartupConfig, namespaces::NamespacedStartupConfigSet};
+
+use crate::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, StartupConfigDecodeError, StartupConfigFormatError,
+    deserialize_startup_config, persist_startup_config,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const SIGNATURE_PREFIX: &[u8] = b"NNXS:";
+const ENCRYPTED_FIELD: &str = "$encrypted";
+const NONCE_LEN: usize = 12;
+
+/// Secret shared by the apps of an installation, e.g. a launcher and the targets it starts.
+/// It must stay out of reach of whoever can write the startup config.
+#[derive(Clone)]
+pub struct InstallationKey([u8; 32]);
+
+impl InstallationKey {
+    /// A new random key.
+    pub fn generate() -> Result<Self, getrandom::Error> {
+        let mut key = [0; 32];
+        getrandom::fill(&mut key)?;
+        Ok(Self(key))
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Key dedicated to `purpose`, so that the same key is never used by two algorithms.
+    pub(crate) fn derive(&self, purpose: &str) -> [u8; 32] {
+        let mut mac =
+            <HmacSha256 as Mac>::new_from_slice(&self.0).expect("HMAC accepts any key length");
+        mac.update(purpose.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+
+    fn signer(&self) -> HmacSha256 {
+        <HmacSha256 as Mac>::new_from_slice(&self.derive("neonex startup config signature"))
+            .expect("HMAC accepts any key length")
+    }
+
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(&self.derive("neonex startup config encryption").into())
+    }
+}
+
+impl core::fmt::Debug for InstallationKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("InstallationKey(..)")
+    }
+}
+
+/// Why sealed data was rejected.
+#[derive(Debug)]
+pub enum StartupConfigIntegrityError {
+    /// The data has no signature, e.g. it has been written by hand.
+    Unsigned,
+    /// The signature doesn't match the data, or was made with another installation key.
+    Tampered,
+    /// An encrypted entry can't be decrypted, or isn't an entry once decrypted.
+    Undecryptable,
+    /// The installation has no key yet, so its data can't have been signed.
+    MissingKey,
+}
+
+impl core::fmt::Display for StartupConfigIntegrityError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StartupConfigIntegrityError::Unsigned => f.write_str("the startup config isn't signed"),
+            StartupConfigIntegrityError::Tampered => f.write_str(
+                "the startup config has been tampered with, its signature doesn't match",
+            ),
+            StartupConfigIntegrityError::Undecryptable => {
+                f.write_str("an encrypted entry of the startup config can't be decrypted")
+            }
+            StartupConfigIntegrityError::MissingKey => {
+                f.write_str("the installation key of the startup config is missing")
+            }
+        }
+    }
+}
+
+impl core::error::Error for StartupConfigIntegrityError {}
+
+/// Whether `data` starts with a signature. It may still not match.
+pub fn is_signed(data: &[u8]) -> bool {
+    data.starts_with(SIGNATURE_PREFIX)
+}
+
+/// Serializes a startup config store like
+/// [`encode_startup_config`](crate::encode_startup_config), encrypting the selected entries
+/// of every namespace then signing the result with `key`.
+pub fn seal_startup_config<CONFIG: NeoNexConfig>(
+    startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+    key: &InstallationKey,
+) -> Result<Vec<u8>, StartupConfigFormatError> {
+    let error = |message: String| StartupConfigFormatError {
+        format: Some(CONFIG::STARTUP_CONFIG_FORMAT),
+        message,
+        unsupported: false,
+    };
+
+    let mut persisted = persist_startup_config::<CONFIG>(startup_config_set)?;
+    for entry in persisted.entries_mut() {
+        let Value::Object(object) = entry else {
+            continue;
+        };
+        let encrypted_key = object
+            .keys()
+            .find(|key| CONFIG::STARTUP_CONFIG_ENCRYPTED_KEYS.contains(&key.as_str()))
+            .cloned();
+        if let Some(encrypted_key) = encrypted_key {
+            let encrypted = encrypt(entry, key).map_err(error)?;
+            *entry = Value::Object(Map::from_iter([(encrypted_key, encrypted)]));
+        }
+    }
+    let data = CONFIG::STARTUP_CONFIG_FORMAT.serialize(&persisted)?;
+
+    let mut signer = key.signer();
+    signer.update(&data);
+    let signature = BASE64_STANDARD.encode(signer.finalize().into_bytes());
+    let mut sealed = Vec::from(SIGNATURE_PREFIX);
+    sealed.extend(signature.as_bytes());
+    sealed.push(b'\n');
+    sealed.extend(data);
+    Ok(sealed)
+}
+
+/// Parses data sealed by [`seal_startup_config`] like
+/// [`decode_startup_config`](crate::decode_startup_config), once its signature has been
+/// checked and its encrypted entries decrypted. Empty data is an empty store.
+pub fn open_startup_config<CONFIG: NeoNexConfig>(
+    sealed: &[u8],
+    key: &InstallationKey,
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigDecodeError> {
+    if sealed.trim_ascii().is_empty() {
+        return Ok(NamespacedStartupConfigSet::default());
+    }
+    open_persisted_startup_config(sealed, key)?
+        .into_set(CONFIG::STARTUP_CONFIG_MIGRATIONS)
+        .map_err(StartupConfigDecodeError::Migration)
+}
+
+/// Checks the signature of data sealed by [`seal_startup_config`] and decrypts its encrypted
+/// entries, without parsing nor upgrading them, e.g. for apps that don't know their schema.
+/// Empty data has no entries.
+pub fn open_persisted_startup_config(
+    sealed: &[u8],
+    key: &InstallationKey,
+) -> Result<PersistedStartupConfig, StartupConfigDecodeError> {
+    if sealed.trim_ascii().is_empty() {
+        return Ok(PersistedStartupConfig::default());
+    }
+    let integrity = StartupConfigDecodeError::Integrity;
+
+    let signed = sealed
+        .strip_prefix(SIGNATURE_PREFIX)
+        .ok_or(integrity(StartupConfigIntegrityError::Unsigned))?;
+    let line_end = signed
+        .iter()
+        .position(|byte| *byte == b'\n')
+        .ok_or(integrity(StartupConfigIntegrityError::Tampered))?;
+    let (signature, data) = (&signed[..line_end], &signed[line_end + 1..]);
+    let signature = BASE64_STANDARD
+        .decode(signature)
+        .map_err(|_| integrity(StartupConfigIntegrityError::Tampered))?;
+    let mut verifier = key.signer();
+    verifier.update(data);
+    verifier
+        .verify_slice(&signature)
+        .map_err(|_| integrity(StartupConfigIntegrityError::Tampered))?;
+
+    let mut persisted = deserialize_startup_config(data)?;
+    for entry in persisted.entries_mut() {
+        // Entries sealed before the keyed layout were replaced as a whole.
+        let encrypted = entry.get(ENCRYPTED_FIELD).or_else(|| {
+            let object = entry.as_object().filter(|object| object.len() == 1)?;
+            object.values().next()?.get(ENCRYPTED_FIELD)
+        });
+        if let Some(Value::String(encrypted)) = encrypted {
+            *entry = decrypt(encrypted, key)
+                .ok_or(integrity(StartupConfigIntegrityError::Undecryptable))?;
+        }
+    }
+    Ok(persisted)
+}
+
+fn encrypt(entry: &Value, key: &InstallationKey) -> Result<Value, String> {
+    let mut nonce = [0; NONCE_LEN];
+    getrandom::fill(&mut nonce).map_err(|error| error.to_string())?;
+    let plaintext = serde_json::to_vec(entry).map_err(|error| error.to_string())?;
+    let ciphertext = key
+        .cipher()
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|error| format!("unable to encrypt an entry: {error}"))?;
+
+    let mut encrypted = Vec::from(nonce);
+    encrypted.extend(ciphertext);
+    Ok(Value::Object(Map::from_iter([(
+        ENCRYPTED_FIELD.to_string(),
+        Value::String(BASE64_STANDARD.encode(encrypted)),
+    )])))
+}
+
+fn decrypt(encrypted: &str, key: &InstallationKey) -> Option<Value> {
+    let encrypted = BASE64_STANDARD.decode(encrypted).ok()?;
+    let (nonce, ciphertext) = encrypted.split_at_checked(NONCE_LEN)?;
+    let plaintext = key
+        .cipher()
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .ok()?;
+    serde_json::from_slice(&plaintext).ok()
+}
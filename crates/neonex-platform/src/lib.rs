@@ -1,18 +1,54 @@
 #![no_std]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 use bevy::app::{App, AppExit};
 use bevy::ecs::error::BevyError;
 use bevy::ecs::resource::Resource;
 use bevy::platform::collections::HashSet;
-use bevy::platform::prelude::String;
+use bevy::platform::prelude::{String, ToString};
 use bevy::platform::prelude::vec::Vec;
-use neonex_macros::generate_32char_seed;
-use neonex_shared::NeoNexStartupConfigSet;
+use neonex_shared::migration::{MigrationError, PersistedStartupConfig, StartupConfigMigration};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+use neonex_shared::{NeoNexStartupConfigSet, UserStartupConfig};
 use neonex_terminal::TerminalContext;
 use ratatui::prelude::Backend;
 use ratatui::style::Color;
 use serde::{Deserialize, Serialize};
 use core::hash::{Hash, Hasher};
+use core::time::Duration;
+
+pub use crate::bundle::{
+    StartupConfigBundle, StartupConfigImport, export_startup_config_bundle,
+    preview_startup_config_bundle,
+};
+pub use crate::format::{StartupConfigDecodeError, StartupConfigFormat, StartupConfigFormatError};
+#[cfg(feature = "integrity")]
+pub use crate::integrity::{
+    InstallationKey, StartupConfigIntegrityError, is_signed, open_persisted_startup_config,
+    open_startup_config, seal_startup_config,
+};
+#[cfg(feature = "vault")]
+pub use crate::vault::{
+    CredentialVault, CredentialVaultError, CredentialVaultSecret, handed_off_secret,
+};
+pub use crate::store::{MemoryStartupConfigStore, StartupConfigStore};
+pub use crate::watch::{StartupConfigChangeNotifier, StartupConfigWatcher};
+
+mod bundle;
+mod format;
+#[cfg(feature = "std")]
+pub mod fs;
+#[cfg(feature = "integrity")]
+mod integrity;
+#[cfg(feature = "std")]
+pub mod process;
+mod store;
+#[cfg(feature = "vault")]
+mod vault;
+mod watch;
 
 /// Platform-specific data, that make cross-platform
 pub trait NeoNexPlatform {
@@ -25,19 +61,197 @@ pub trait NeoNexPlatform {
     /// Can be a type to define key to access the Startup Config on the web, and can be a type to define a path to access it on desktop/mobile
     type StartupConfigRetrieveKeyType;
 
-    const STARTUP_CONFIG_RANDOM_KEY: &'static str =
-        concat!(generate_32char_seed!(), "-neonex-startup-config.json");
-
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType;
-    /// Retrieve a startup config (if exists). If doesn't exist, it outputs None.
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet;
-    type UpdateResult;
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType;
+    /// Where the startup config is persisted, e.g. a file or the localStorage. Used by the
+    /// configs through `NeoNexConfig::StartupConfigStore`, see [`PlatformStartupConfigStore`].
+    type StartupConfigStore: StartupConfigStore<Error = Self::StorageError>;
+    /// Prepares the platform before anything else runs, even before the startup config is
+    /// retrieved, e.g. the firmware services it's stored with.
+    fn pre_init<CONFIG: NeoNexConfig>() {}
+    /// Retrieve a startup config (if exists), with all of its namespaces. If doesn't exist,
+    /// it outputs an empty store.
+    fn retrieve_startup_config<CONFIG: NeoNexConfig>() -> Result<
+        NamespacedStartupConfigSetOf<CONFIG>,
+        StartupConfigLoadError<StartupConfigStoreErrorOf<CONFIG>>,
+    > {
+        CONFIG::StartupConfigStore::load::<CONFIG>()
+    }
+    /// Retrieve the startup config again, once it has been modified outside of the app. Unlike
+    /// [`Self::retrieve_startup_config`], a corrupt one is left untouched, as it may be in the
+    /// middle of being edited.
+    fn reload_startup_config<CONFIG: NeoNexConfig>() -> Result<
+        NamespacedStartupConfigSetOf<CONFIG>,
+        StartupConfigLoadError<StartupConfigStoreErrorOf<CONFIG>>,
+    > {
+        CONFIG::StartupConfigStore::reload::<CONFIG>()
+    }
+    /// Why the startup config storage couldn't be read or written, shown to the app.
+    type StorageError: core::fmt::Display;
     /// Update the startup config at a specified location.
-    fn update_startup_config(sc: NeoNexStartupConfigSet) -> Self::UpdateResult;
+    fn update_startup_config<CONFIG: NeoNexConfig>(
+        sc: NamespacedStartupConfigSetOf<CONFIG>,
+    ) -> Result<(), StartupConfigStoreErrorOf<CONFIG>> {
+        CONFIG::StartupConfigStore::save::<CONFIG>(&sc)
+    }
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> core::result::Result<(), BevyError>;
+    /// Keeps a copy of the stored startup config before it's reset, returning where it's kept.
+    /// Returns `None` when nothing is stored, or when the platform can't keep a copy.
+    fn back_up_startup_config<CONFIG: NeoNexConfig>()
+    -> Result<Option<String>, StartupConfigStoreErrorOf<CONFIG>> {
+        CONFIG::StartupConfigStore::back_up::<CONFIG>()
+    }
+    /// Starts watching the startup config for modifications made outside of the app, so that
+    /// it gets reloaded. Returns `None` when the platform can't watch it.
+    fn watch_startup_config<CONFIG: NeoNexConfig>() -> Option<StartupConfigWatcher> {
+        CONFIG::StartupConfigStore::watch::<CONFIG>()
+    }
+    /// Retrieve the system-wide startup config, shipped along with the app. It's never written
+    /// back. Platforms without one output an empty store.
+    fn retrieve_system_startup_config<CONFIG: NeoNexConfig>() -> Result<
+        NamespacedStartupConfigSetOf<CONFIG>,
+        StartupConfigLoadError<StartupConfigStoreErrorOf<CONFIG>>,
+    > {
+        CONFIG::StartupConfigStore::load_system::<CONFIG>()
+    }
+    /// Retrieve the enforced startup config, shipped along with the app to pin entries over
+    /// every other layer. It's never written back. Platforms without one output an empty
+    /// store.
+    fn retrieve_enforced_startup_config<CONFIG: NeoNexConfig>() -> Result<
+        NamespacedStartupConfigSetOf<CONFIG>,
+        StartupConfigLoadError<StartupConfigStoreErrorOf<CONFIG>>,
+    > {
+        CONFIG::StartupConfigStore::load_enforced::<CONFIG>()
+    }
+    /// Environment variables the app has been started with, parsed with
+    /// [`parse_env_overrides`](neonex_shared::layers::parse_env_overrides), e.g.
+    /// `process::env_vars` on the platforms starting apps as processes.
+    fn startup_config_env_vars() -> Vec<(String, String)> {
+        Vec::new()
+    }
+    /// Command-line arguments the app has been started with, without the program name, parsed
+    /// with [`parse_arg_overrides`](neonex_shared::layers::parse_arg_overrides), e.g.
+    /// `process::args` on the platforms starting apps as processes.
+    fn startup_config_args<CONFIG: NeoNexConfig>() -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The startup config set of a [`NeoNexConfig`], holding its user-defined entries.
+pub type StartupConfigSetOf<CONFIG> =
+    NeoNexStartupConfigSet<<CONFIG as NeoNexConfig>::StartupConfig>;
+
+/// The persisted startup config of a [`NeoNexConfig`], with all of its namespaces.
+pub type NamespacedStartupConfigSetOf<CONFIG> =
+    NamespacedStartupConfigSet<<CONFIG as NeoNexConfig>::StartupConfig>;
+
+/// The store of the platform of a [`NeoNexConfig`], to use as its
+/// `NeoNexConfig::StartupConfigStore`:
+/// ```ignore
+/// type StartupConfigStore = PlatformStartupConfigStore<Self>;
+/// ```
+pub type PlatformStartupConfigStore<CONFIG> =
+    <<CONFIG as NeoNexConfig>::Platform as NeoNexPlatform>::StartupConfigStore;
+
+/// Why the startup config store of a [`NeoNexConfig`] couldn't be read or written.
+pub type StartupConfigStoreErrorOf<CONFIG> =
+    <<CONFIG as NeoNexConfig>::StartupConfigStore as StartupConfigStore>::Error;
+
+/// Key of the startup config of `CONFIG`, derived from [`NeoNexConfig::APP_ID`]: every
+/// build of the app, and every app sharing its identifier, agree on it.
+///
+/// Characters other than ASCII letters, digits, `.`, `-` and `_` are replaced by `_`, so
+/// that the key can be used as a file name.
+pub fn startup_config_key<CONFIG: NeoNexConfig>() -> String {
+    startup_config_key_for(CONFIG::APP_ID)
+}
+
+/// [`startup_config_key`] of the app identified by `app_id`, for when it's only known at
+/// runtime, e.g. by an app written in another language.
+pub fn startup_config_key_for(app_id: &str) -> String {
+    if app_id.is_empty() {
+        return String::from("neonex");
+    }
+    app_id
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Whether `key` is one of the random keys the startup config was stored under before
+/// being keyed by [`NeoNexConfig::APP_ID`], i.e. 32 random characters followed by
+/// `-neonex-startup-config.json`. Those can't be read back anymore, and are only cleaned up.
+pub fn is_legacy_startup_config_key(key: &str) -> bool {
+    key.strip_suffix("-neonex-startup-config.json")
+        .is_some_and(|seed| seed.chars().count() == 32)
+}
+
+/// Parses a persisted startup config store, in whichever format it was stored, upgrading it
+/// first when it was written with an older version of the NeoNex entries or of the `CONFIG`
+/// ones. Empty data is an empty store.
+pub fn decode_startup_config<CONFIG: NeoNexConfig>(
+    data: &[u8],
+) -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigDecodeError> {
+    if data.trim_ascii().is_empty() {
+        return Ok(NamespacedStartupConfigSet::default());
+    }
+    deserialize_startup_config(data)?
+        .into_set(CONFIG::STARTUP_CONFIG_MIGRATIONS)
+        .map_err(StartupConfigDecodeError::Migration)
+}
+
+/// Parses persisted data, in whichever format it was stored, without upgrading it nor parsing
+/// its entries, e.g. for apps that don't know their schema. Empty data has no entries.
+pub fn deserialize_startup_config(
+    data: &[u8],
+) -> Result<PersistedStartupConfig, StartupConfigDecodeError> {
+    if data.trim_ascii().is_empty() {
+        return Ok(PersistedStartupConfig::default());
+    }
+    StartupConfigFormat::detect(data)
+        .and_then(|format| format.deserialize(data))
+        .map_err(StartupConfigDecodeError::Format)
+}
+
+/// Serializes a startup config store to be persisted in
+/// `NeoNexConfig::STARTUP_CONFIG_FORMAT`, along with the versions of its schemas.
+pub fn encode_startup_config<CONFIG: NeoNexConfig>(
+    startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> Result<Vec<u8>, StartupConfigFormatError> {
+    CONFIG::STARTUP_CONFIG_FORMAT.serialize(&persist_startup_config::<CONFIG>(startup_config_set)?)
+}
+
+/// Prepares a startup config store to be serialized in `NeoNexConfig::STARTUP_CONFIG_FORMAT`.
+pub(crate) fn persist_startup_config<CONFIG: NeoNexConfig>(
+    startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+) -> Result<PersistedStartupConfig, StartupConfigFormatError> {
+    PersistedStartupConfig::from_set(startup_config_set, CONFIG::STARTUP_CONFIG_MIGRATIONS).map_err(
+        |error| StartupConfigFormatError {
+            format: Some(CONFIG::STARTUP_CONFIG_FORMAT),
+            message: error.to_string(),
+            unsupported: false,
+        },
+    )
+}
+
+/// Loads a startup config file written by an older version of the app, to check in tests
+/// that the migrations of `CONFIG` still upgrade it:
+/// ```ignore
+/// let store = load_startup_config_fixture::<LauncherConfig>(include_bytes!("fixtures/v1.json"));
+/// assert_eq!(store.global().get::<Volume>(), Some(&5));
+/// ```
+///
+/// Panics with the reason when the fixture can't be upgraded.
+pub fn load_startup_config_fixture<CONFIG: NeoNexConfig>(
+    fixture: &[u8],
+) -> NamespacedStartupConfigSetOf<CONFIG> {
+    decode_startup_config::<CONFIG>(fixture)
+        .unwrap_or_else(|error| panic!("Unable to load the startup config fixture: {error}"))
 }
 
 /// `NeoNexConfig` is a trait, containing all of the NeoNex static customizations.
@@ -63,22 +277,34 @@ pub trait NeoNexPlatform {
 ///         ```
 ///
 ///     - Implement the trait NeoNexConfig for the struct we have defined, by customizing
-///         the items we want to (in our example, we'll change the name of the assistant):
+///         the items we want to (in our example, we'll change the name of the assistant).
+///         The platform, the startup config entries of the app and their store are always
+///         required:
 ///         ```rust
+///         # use neonex_mockplatform::MockPlatform as ActivePlatform;
+///         # use neonex_platform::{NeoNexConfig, PlatformStartupConfigStore};
+///         # use neonex_shared::NoStartupConfig;
+///         # struct ExampleCustomizations;
+///         // `ActivePlatform` is exported by neonex-core for the enabled platform feature.
 ///         impl NeoNexConfig for ExampleCustomizations {
+///             type Platform = ActivePlatform;
+///             // The app doesn't persist any entry of its own.
+///             type StartupConfig = NoStartupConfig;
+///             type StartupConfigStore = PlatformStartupConfigStore<Self>;
 ///             const NAME: &'static str = "Bob";
 ///         }
 ///         ```
 ///
-///     - Inject the struct that stores our NAME customization into NeoNex:
-///         ```rust
-///         let mut instance = NeoNexInstance::new_from(ExampleCustomizations);
+///     - Inject the struct that stores our NAME customization into NeoNex, with
+///         neonex-core:
+///         ```rust,ignore
+///         let mut instance = NeoNexInstance::<ExampleCustomizations>::new_with_config();
 ///         // Match the exit state to indicate if NeoNex terminated because of an error or not
 ///         // This match is purely optional, you can write `instance.run();` instead, by ignoring
 ///         // the AppExit value that the function returns.
 ///         match instance.run() {
-///             Success => println!("NeoNex has been terminated without errors"),
-///             Error(e) => println!("NeoNex has been terminated with an error: {:?}", e)
+///             AppExit::Success => println!("NeoNex has been terminated without errors"),
+///             AppExit::Error(e) => println!("NeoNex has been terminated with an error: {:?}", e)
 ///         }
 ///         ```
 ///
@@ -91,10 +317,169 @@ pub trait NeoNexPlatform {
 pub trait NeoNexConfig: Sized + Send + Sync + 'static {
     /// Allow to implement a custom platform within NeoNex
     type Platform: NeoNexPlatform;
+    /// User-defined entries of the startup config, persisted next to the NeoNex ones.
+    /// Use [`NoStartupConfig`](neonex_shared::NoStartupConfig) when the app doesn't need any.
+    type StartupConfig: UserStartupConfig;
+    /// Where the startup config is persisted: [`PlatformStartupConfigStore`] for the store of
+    /// the platform, or [`MemoryStartupConfigStore`] to keep it in memory, e.g. in tests that
+    /// must not touch the real storage.
+    type StartupConfigStore: StartupConfigStore;
+    /// Identifies the app, e.g. `"com.example.launcher"`. The startup config is stored under
+    /// a key derived from it, so a launcher and the targets it starts must share the same
+    /// identifier to share their startup config.
+    const APP_ID: &'static str = "neonex";
+    /// Namespace of the startup config the app reads and writes, on top of the global one,
+    /// e.g. the name of a launch target. A launcher can start an app in another namespace
+    /// with `--neonex-namespace=<name>`. When `None`, the app uses the global namespace.
+    const STARTUP_CONFIG_NAMESPACE: Option<&'static str> = None;
+    /// Migrations of the [`StartupConfig`](NeoNexConfig::StartupConfig) entries, the one at
+    /// index N upgrading files from version N to version N + 1. Whenever a variant is renamed
+    /// or its value changes, push a migration rather than editing the previous ones, so that
+    /// files written by any older version are upgraded instead of being wiped.
+    const STARTUP_CONFIG_MIGRATIONS: &'static [StartupConfigMigration] = &[];
+    /// Serialization of the startup config. Stored data is read whatever its format, as long
+    /// as the feature of that format is enabled.
+    const STARTUP_CONFIG_FORMAT: StartupConfigFormat = StartupConfigFormat::Json;
+    /// How long the startup config has to stay unmodified in the app before being saved.
+    /// When `None`, it's only saved on exit and with `SaveStartupConfig`.
+    const STARTUP_CONFIG_AUTOSAVE_DELAY: Option<Duration> = Some(Duration::from_secs(1));
+    /// How the startup config is reloaded when it's modified outside of the app while
+    /// some of its entries have been modified in the app and not saved yet.
+    const STARTUP_CONFIG_CONFLICT_POLICY: StartupConfigConflictPolicy =
+        StartupConfigConflictPolicy::PreferApp;
+    /// Keys of the entries a link can override on the web, with the `neonex.*` parameters of
+    /// its URL, see [`url_override_args`](neonex_shared::layers::url_override_args). `Namespace`
+    /// lets it choose the namespace. None by default, as anyone can craft a link.
+    const STARTUP_CONFIG_URL_OVERRIDES: &'static [&'static str] = &[];
+    /// Keys of the entries encrypted at rest with the installation key, e.g. tokens or licence
+    /// keys. Other entries are only signed.
+    #[cfg(feature = "integrity")]
+    const STARTUP_CONFIG_ENCRYPTED_KEYS: &'static [&'static str] = &[];
+    /// The lowest layer of the startup config, overridden by the system-wide, persisted,
+    /// environment and command-line ones.
+    fn default_startup_config() -> StartupConfigSetOf<Self> {
+        NeoNexStartupConfigSet::default()
+    }
     #[cfg(feature = "desktop-hybrid-contexts")]
     const DESKTOP_HYBRID_SOFTATUI: bool = true;
+    /// Text mode (columns, rows) the UEFI console switches to. When `None`, or when the
+    /// firmware doesn't offer that mode, the largest available mode is picked.
+    #[cfg(feature = "uefi")]
+    const UEFI_TEXT_MODE: Option<(usize, usize)> = None;
+    /// Draw into the Graphics Output Protocol framebuffer rather than the text console,
+    /// for truecolor and box-drawing characters. Only has an effect with the `gop` feature
+    /// of `neonex-uefi`, and falls back to the text console when the firmware has no GOP.
+    #[cfg(feature = "uefi")]
+    const UEFI_GRAPHICS_OUTPUT: bool = true;
+    /// Updates per second of the UEFI runner, which sleeps on a firmware timer in between.
+    #[cfg(feature = "uefi")]
+    const UEFI_FRAME_RATE: u32 = 60;
+    /// What the machine does once NeoNex exits, or after its panic screen.
+    #[cfg(feature = "uefi")]
+    const UEFI_EXIT_ACTION: UefiExitAction = UefiExitAction::ReturnToFirmware;
     const WINDOW_NAME: &'static str = "NeoNex";
     const NAME: &'static str = "NeoNex";
     const DEFAULT_BACKGROUND_COLOR: Color = Color::Black;
     const DEFAULT_FOREGROUND_COLOR: Color = Color::White;
 }
+
+/// Why the startup config couldn't be retrieved. The app starts with an empty set instead.
+#[derive(Debug)]
+pub enum StartupConfigLoadError<E> {
+    /// The storage couldn't be read, e.g. a missing or read-only directory.
+    Storage(E),
+    /// The stored startup config is corrupt. It has been moved to `backup`, when the platform
+    /// could keep it.
+    Corrupt {
+        error: StartupConfigDecodeError,
+        backup: Option<String>,
+    },
+    /// The stored startup config was written by a newer version of the app. It's left
+    /// untouched, and this version doesn't save over it.
+    TooNew(MigrationError),
+    /// The stored startup config is in a format whose feature isn't enabled, e.g. TOML
+    /// written by a launcher. It's left untouched, and this version doesn't save over it.
+    Unsupported(StartupConfigFormatError),
+}
+
+impl<E> StartupConfigLoadError<E> {
+    /// The error of stored data that can't be decoded: [`Self::TooNew`] when it was written
+    /// by a newer version of the app, [`Self::Unsupported`] when it's in a format that isn't
+    /// enabled, otherwise [`Self::Corrupt`], moved aside by `back_up`.
+    pub fn from_decode_error(
+        error: StartupConfigDecodeError,
+        back_up: impl FnOnce() -> Option<String>,
+    ) -> Self {
+        match error {
+            StartupConfigDecodeError::Migration(error @ MigrationError::TooNew { .. }) => {
+                StartupConfigLoadError::TooNew(error)
+            }
+            StartupConfigDecodeError::Format(error) if error.unsupported => {
+                StartupConfigLoadError::Unsupported(error)
+            }
+            error => StartupConfigLoadError::Corrupt {
+                error,
+                backup: back_up(),
+            },
+        }
+    }
+
+    /// Whether the stored startup config is valid for another version or build of the app,
+    /// so that it mustn't be saved over.
+    pub fn is_left_untouched(&self) -> bool {
+        matches!(
+            self,
+            StartupConfigLoadError::TooNew(_) | StartupConfigLoadError::Unsupported(_)
+        )
+    }
+}
+
+impl<E: core::fmt::Display> core::fmt::Display for StartupConfigLoadError<E> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StartupConfigLoadError::Storage(error) => {
+                write!(f, "unable to read the startup config: {error}")
+            }
+            StartupConfigLoadError::Corrupt {
+                error,
+                backup: Some(backup),
+            } => write!(f, "{error}, the startup config has been moved to {backup}"),
+            StartupConfigLoadError::Corrupt {
+                error,
+                backup: None,
+            } => write!(f, "{error}, the startup config has been reset"),
+            StartupConfigLoadError::TooNew(error) => {
+                write!(f, "{error}, the startup config is left untouched")
+            }
+            StartupConfigLoadError::Unsupported(error) => {
+                write!(f, "{error}, the startup config is left untouched")
+            }
+        }
+    }
+}
+
+impl<E: core::fmt::Debug + core::fmt::Display> core::error::Error for StartupConfigLoadError<E> {}
+
+/// Resolution of the conflicts between the unsaved modifications of the startup config made
+/// in the app, and the ones made outside of it.
+///
+/// Entries only modified on one side always take that modification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartupConfigConflictPolicy {
+    /// Entries modified on both sides keep their in-app value.
+    PreferApp,
+    /// Entries modified on both sides take their external value.
+    PreferExternal,
+    /// Unsaved in-app modifications are dropped, the set becomes the external one.
+    DiscardApp,
+}
+
+/// What a UEFI application does once NeoNex is done running.
+#[cfg(feature = "uefi")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UefiExitAction {
+    /// Return from the application, so the firmware boots the next entry or shows its menu.
+    ReturnToFirmware,
+    Reboot,
+    Shutdown,
+}
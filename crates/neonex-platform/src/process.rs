@@ -0,0 +1,23 @@
+//! Startup config overrides the process has been started with, for the platforms starting
+//! apps as processes, like desktops. See `NeoNexPlatform::startup_config_env_vars` and
+//! `NeoNexPlatform::startup_config_args`.
+
+use std::env;
+
+use bevy::platform::prelude::{String, Vec};
+
+/// Environment variables of the process. The ones that aren't valid Unicode are skipped.
+pub fn env_vars() -> Vec<(String, String)> {
+    env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)))
+        .collect()
+}
+
+/// Command-line arguments of the process, without the program name. The ones that aren't
+/// valid Unicode are skipped.
+pub fn args() -> Vec<String> {
+    env::args_os()
+        .skip(1)
+        .filter_map(|arg| arg.into_string().ok())
+        .collect()
+}
@@ -0,0 +1,129 @@
+//! Where the persisted startup config is kept, see [`StartupConfigStore`].
+
+use alloc::collections::BTreeMap;
+use bevy::platform::{
+    prelude::{String, Vec},
+    sync::{Mutex, PoisonError},
+};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+
+use crate::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, StartupConfigFormatError, StartupConfigLoadError,
+    StartupConfigWatcher, decode_startup_config, encode_startup_config, startup_config_key,
+};
+
+/// Storage of the persisted startup config, like a file or the localStorage, picked by
+/// `NeoNexConfig::StartupConfigStore`. Every namespace is read and written at once.
+pub trait StartupConfigStore {
+    /// Why the storage couldn't be read or written, shown to the app.
+    type Error: core::fmt::Display;
+
+    /// Reads the stored startup config of `CONFIG`, with all of its namespaces. When nothing
+    /// is stored, outputs an empty store.
+    fn load<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<Self::Error>>;
+
+    /// Reads the stored startup config of `CONFIG` again, once it has been modified outside of
+    /// the app. Unlike [`Self::load`], a corrupt one must be left untouched, as it may be in
+    /// the middle of being edited.
+    fn reload<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<Self::Error>> {
+        Self::load::<CONFIG>()
+    }
+
+    /// Reads the system-wide startup config of `CONFIG`, shipped along with the app and never
+    /// written back. Outputs an empty store when there's none, or when the storage can't ship
+    /// one.
+    fn load_system<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<Self::Error>> {
+        Ok(NamespacedStartupConfigSet::default())
+    }
+
+    /// Reads the enforced startup config of `CONFIG`, shipped along with the app like the
+    /// system-wide one, whose entries override every other layer. Outputs an empty store when
+    /// there's none, or when the storage can't ship one.
+    fn load_enforced<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<Self::Error>> {
+        Ok(NamespacedStartupConfigSet::default())
+    }
+
+    /// Replaces the stored startup config of `CONFIG`.
+    fn save<CONFIG: NeoNexConfig>(
+        startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+    ) -> Result<(), Self::Error>;
+
+    /// Keeps a copy of the stored startup config before it's reset, returning where it's
+    /// kept. Returns `None` when nothing is stored, or when the storage can't keep a copy.
+    fn back_up<CONFIG: NeoNexConfig>() -> Result<Option<String>, Self::Error> {
+        Ok(None)
+    }
+
+    /// Starts watching the stored startup config for modifications made outside of the app.
+    /// Returns `None` when the storage can't be watched.
+    fn watch<CONFIG: NeoNexConfig>() -> Option<StartupConfigWatcher> {
+        None
+    }
+}
+
+/// Encoded startup configs of [`MemoryStartupConfigStore`], by startup config key.
+static MEMORY: Mutex<BTreeMap<String, Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+/// Keeps the startup config in the memory of the process, encoded as it would be persisted,
+/// e.g. for tests that must not touch the real storage, or for platforms without any. Apps
+/// sharing the same `NeoNexConfig::APP_ID` share the same startup config.
+///
+/// Used instead of the store of the platform as `NeoNexConfig::StartupConfigStore`. Saving
+/// fails like with any other store when the startup config can't be encoded.
+pub struct MemoryStartupConfigStore;
+
+impl MemoryStartupConfigStore {
+    /// The startup config of `CONFIG`, encoded as it would be persisted, if any.
+    pub fn data<CONFIG: NeoNexConfig>() -> Option<Vec<u8>> {
+        let memory = MEMORY.lock().unwrap_or_else(PoisonError::into_inner);
+        memory.get(&startup_config_key::<CONFIG>()).cloned()
+    }
+
+    /// Replaces the startup config of `CONFIG` with encoded data, e.g. a fixture written by
+    /// an older version of the app.
+    pub fn set_data<CONFIG: NeoNexConfig>(data: Vec<u8>) {
+        let mut memory = MEMORY.lock().unwrap_or_else(PoisonError::into_inner);
+        memory.insert(startup_config_key::<CONFIG>(), data);
+    }
+
+    /// Forgets the startup config of `CONFIG`, so that the next load outputs an empty store.
+    pub fn clear<CONFIG: NeoNexConfig>() {
+        let mut memory = MEMORY.lock().unwrap_or_else(PoisonError::into_inner);
+        memory.remove(&startup_config_key::<CONFIG>());
+    }
+}
+
+impl StartupConfigStore for MemoryStartupConfigStore {
+    type Error = StartupConfigFormatError;
+
+    fn load<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<Self::Error>> {
+        let Some(data) = Self::data::<CONFIG>() else {
+            return Ok(NamespacedStartupConfigSet::default());
+        };
+        decode_startup_config::<CONFIG>(&data).map_err(|error| {
+            StartupConfigLoadError::from_decode_error(error, || {
+                Self::clear::<CONFIG>();
+                None
+            })
+        })
+    }
+
+    fn reload<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<Self::Error>> {
+        let data = Self::data::<CONFIG>().unwrap_or_default();
+        decode_startup_config::<CONFIG>(&data)
+            .map_err(|error| StartupConfigLoadError::from_decode_error(error, || None))
+    }
+
+    fn save<CONFIG: NeoNexConfig>(
+        startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+    ) -> Result<(), Self::Error> {
+        Self::set_data::<CONFIG>(encode_startup_config::<CONFIG>(startup_config_set)?);
+        Ok(())
+    }
+}
@@ -0,0 +1,473 @@
+//! Credential vault: secrets of the app, like the login token or licence key a launcher
+//! keeps for the product it starts, which must never be written to the startup config.
+//!
+//! The vault is a `credentials.vault` file next to the startup config, see
+//! [`credential_vault_path`](crate::fs::credential_vault_path). It's JSON, holding the way
+//! it's unlocked and the secrets, encrypted with ChaCha20-Poly1305:
+//! ```json
+//! { "version": 1, "unlock": { "kind": "passphrase", "salt": "...", "memory": 19456, "iterations": 2, "parallelism": 1 }, "data": "..." }
+//! ```
+//! The key is derived from a passphrase of the user with Argon2id, or from the installation
+//! key, see [`CredentialVaultSecret`].
+//!
+//! Secrets are handed to a launched app through its environment, with
+//! [`CredentialVault::handoff_env`], and read back by the app with [`handed_off_secret`].
+
+use std::{
+    collections::BTreeMap,
+    env, io,
+    path::{Path, PathBuf},
+};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use chacha20poly1305::{
+    ChaCha20Poly1305, KeyInit, Nonce,
+    aead::{Aead, Payload},
+};
+use neonex_shared::layers::SECRET_VAR_PREFIX;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::fs;
+
+const VERSION: u32 = 1;
+const NONCE_LEN: usize = 12;
+const SALT_LEN: usize = 16;
+/// Authenticated along with the secrets, so that data encrypted for another purpose with
+/// the same key isn't accepted.
+const ASSOCIATED_DATA: &[u8] = b"neonex credential vault 1";
+/// Bounds of the Argon2 parameters read from a vault, so that an edited file can't make the
+/// derivation take all the memory or time of the machine. 1 GiB, in KiB.
+const MAX_MEMORY: u32 = 1 << 20;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+/// What the key of a credential vault is derived from.
+#[derive(Clone)]
+pub enum CredentialVaultSecret {
+    /// A passphrase typed by the user, e.g. in the unlock dialog of `neonex-core`.
+    Passphrase(Zeroizing<String>),
+    /// The installation key, so that the vault opens without any user input. It only
+    /// protects the secrets from whoever can't read the installation key, see
+    /// `InstallationKey`.
+    Installation,
+}
+
+impl CredentialVaultSecret {
+    pub fn passphrase(passphrase: impl Into<String>) -> Self {
+        CredentialVaultSecret::Passphrase(Zeroizing::new(passphrase.into()))
+    }
+}
+
+impl core::fmt::Debug for CredentialVaultSecret {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CredentialVaultSecret::Passphrase(_) => f.write_str("Passphrase(..)"),
+            CredentialVaultSecret::Installation => f.write_str("Installation"),
+        }
+    }
+}
+
+/// Why a credential vault couldn't be opened or saved.
+#[derive(Debug)]
+pub enum CredentialVaultError {
+    Io(io::Error),
+    /// The secret doesn't open the vault, or the vault has been tampered with.
+    WrongSecret,
+    /// The vault isn't unlocked with this kind of secret, e.g. a passphrase was given for a
+    /// vault unlocked with the installation key.
+    WrongSecretKind,
+    /// The file isn't a vault, or was written by a newer version of NeoNex.
+    Corrupt(String),
+}
+
+impl core::fmt::Display for CredentialVaultError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CredentialVaultError::Io(error) => {
+                write!(f, "unable to access the credential vault: {error}")
+            }
+            CredentialVaultError::WrongSecret => {
+                f.write_str("wrong passphrase, or the credential vault has been tampered with")
+            }
+            CredentialVaultError::WrongSecretKind => {
+                f.write_str("the credential vault is unlocked with another kind of secret")
+            }
+            CredentialVaultError::Corrupt(message) => {
+                write!(f, "invalid credential vault: {message}")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CredentialVaultError {}
+
+impl From<io::Error> for CredentialVaultError {
+    fn from(error: io::Error) -> Self {
+        CredentialVaultError::Io(error)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredVault {
+    version: u32,
+    unlock: StoredUnlock,
+    /// Base64 of the nonce followed by the encrypted secrets.
+    data: String,
+}
+
+/// How the key is derived, kept in clear so that the vault can be unlocked.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum StoredUnlock {
+    Passphrase {
+        salt: String,
+        memory: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+    Installation,
+}
+
+/// An unlocked credential vault, holding its secrets by name. Modifications are written to
+/// the file with [`CredentialVault::save`].
+pub struct CredentialVault {
+    path: PathBuf,
+    unlock: StoredUnlock,
+    key: Zeroizing<[u8; 32]>,
+    secrets: BTreeMap<String, Zeroizing<String>>,
+}
+
+impl CredentialVault {
+    /// Whether a vault has been saved at `path`, i.e. whether it's unlocked rather than
+    /// created with a secret.
+    pub fn exists(path: &Path) -> bool {
+        path.exists()
+    }
+
+    /// Unlocks the vault stored at `path` with `secret`. When it's missing, it's an empty
+    /// vault, created with `secret` on the first save.
+    pub fn open(path: &Path, secret: &CredentialVaultSecret) -> Result<Self, CredentialVaultError> {
+        let data = match std::fs::read(path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                let mut vault = Self {
+                    path: path.to_path_buf(),
+                    unlock: StoredUnlock::Installation,
+                    key: Zeroizing::new([0; 32]),
+                    secrets: BTreeMap::new(),
+                };
+                vault.set_secret(secret)?;
+                return Ok(vault);
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        let corrupt =
+            |error: &dyn core::fmt::Display| CredentialVaultError::Corrupt(error.to_string());
+        let stored: StoredVault = serde_json::from_slice(&data).map_err(|error| corrupt(&error))?;
+        if stored.version > VERSION {
+            return Err(corrupt(&format!("unknown version {}", stored.version)));
+        }
+        let key = derive_key(path, &stored.unlock, secret)?;
+        let encrypted = BASE64_STANDARD
+            .decode(&stored.data)
+            .map_err(|error| corrupt(&error))?;
+        let (nonce, ciphertext) = encrypted
+            .split_at_checked(NONCE_LEN)
+            .ok_or_else(|| corrupt(&"truncated data"))?;
+        let plaintext = Zeroizing::new(
+            ChaCha20Poly1305::new(key.as_ref().into())
+                .decrypt(
+                    Nonce::from_slice(nonce),
+                    Payload {
+                        msg: ciphertext,
+                        aad: ASSOCIATED_DATA,
+                    },
+                )
+                .map_err(|_| CredentialVaultError::WrongSecret)?,
+        );
+        let secrets: BTreeMap<String, String> =
+            serde_json::from_slice(&plaintext).map_err(|error| corrupt(&error))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            unlock: stored.unlock,
+            key,
+            secrets: secrets
+                .into_iter()
+                .map(|(name, secret)| (name, Zeroizing::new(secret)))
+                .collect(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.secrets.get(name).map(|secret| secret.as_str())
+    }
+
+    /// Sets a secret, returning whether it replaced another one.
+    pub fn set(&mut self, name: impl Into<String>, secret: impl Into<String>) -> bool {
+        self.secrets
+            .insert(name.into(), Zeroizing::new(secret.into()))
+            .is_some()
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.secrets.remove(name).is_some()
+    }
+
+    /// Names of every secret.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.secrets.keys().map(String::as_str)
+    }
+
+    /// Changes what the vault is unlocked with, e.g. a new passphrase. Takes effect on the
+    /// next save.
+    pub fn set_secret(
+        &mut self,
+        secret: &CredentialVaultSecret,
+    ) -> Result<(), CredentialVaultError> {
+        let unlock = match secret {
+            CredentialVaultSecret::Passphrase(_) => {
+                let mut salt = [0; SALT_LEN];
+                getrandom::fill(&mut salt).map_err(io::Error::other)?;
+                StoredUnlock::Passphrase {
+                    salt: BASE64_STANDARD.encode(salt),
+                    memory: Params::DEFAULT_M_COST,
+                    iterations: Params::DEFAULT_T_COST,
+                    parallelism: Params::DEFAULT_P_COST,
+                }
+            }
+            CredentialVaultSecret::Installation => StoredUnlock::Installation,
+        };
+        self.key = derive_key(&self.path, &unlock, secret)?;
+        self.unlock = unlock;
+        Ok(())
+    }
+
+    /// Encrypts the secrets and replaces the vault file with them, readable by its owner only.
+    pub fn save(&self) -> Result<(), CredentialVaultError> {
+        let mut nonce = [0; NONCE_LEN];
+        getrandom::fill(&mut nonce).map_err(io::Error::other)?;
+        let secrets: BTreeMap<&str, &str> = self
+            .secrets
+            .iter()
+            .map(|(name, secret)| (name.as_str(), secret.as_str()))
+            .collect();
+        let plaintext = Zeroizing::new(serde_json::to_vec(&secrets).map_err(io::Error::other)?);
+        let ciphertext = ChaCha20Poly1305::new(self.key.as_ref().into())
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: ASSOCIATED_DATA,
+                },
+            )
+            .map_err(|error| io::Error::other(format!("unable to encrypt the secrets: {error}")))?;
+
+        let mut data = Vec::from(nonce);
+        data.extend(ciphertext);
+        let stored = StoredVault {
+            version: VERSION,
+            unlock: self.unlock.clone(),
+            data: BASE64_STANDARD.encode(data),
+        };
+        let data = serde_json::to_vec_pretty(&stored).map_err(io::Error::other)?;
+        let _lock = fs::lock(&self.path)?;
+        fs::write_private_atomically(&self.path, &data)?;
+        Ok(())
+    }
+
+    /// Environment variables handing the secrets named `names` to an app started by this
+    /// one, to be read with [`handed_off_secret`]. Missing secrets are left out:
+    /// ```ignore
+    /// Command::new(game_path)
+    ///     .args(startup_config_set.launch_args(Some("game")))
+    ///     .envs(vault.handoff_env(["LoginToken"]))
+    ///     .spawn()?;
+    /// ```
+    ///
+    /// The environment of a process is only readable by its user, unlike its command line.
+    pub fn handoff_env<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<(String, String)> {
+        names
+            .into_iter()
+            .filter_map(|name| Some((secret_var(name), self.get(name)?.to_string())))
+            .collect()
+    }
+}
+
+impl core::fmt::Debug for CredentialVault {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CredentialVault")
+            .field("path", &self.path)
+            .field("names", &self.secrets.keys().collect::<Vec<_>>())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The secret named `name` handed to this app by the one that started it, see
+/// [`CredentialVault::handoff_env`]. Its variable is removed once read, so that it isn't
+/// inherited by the processes this app starts, nor read twice.
+///
+/// Call it before other threads are started, e.g. before building the Bevy app, as they
+/// may read the environment meanwhile.
+pub fn handed_off_secret(name: &str) -> Option<Zeroizing<String>> {
+    let var = secret_var(name);
+    let secret = env::var_os(&var)?;
+    // SAFETY: documented as to be called before other threads are started.
+    unsafe { env::remove_var(&var) };
+    secret.into_string().ok().map(Zeroizing::new)
+}
+
+/// Variable handing the secret `name`, e.g. `NEONEX_SECRET_LOGIN_TOKEN` for `LoginToken`.
+fn secret_var(name: &str) -> String {
+    let mut var = String::from(SECRET_VAR_PREFIX);
+    for (index, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 && !var.ends_with('_') {
+            var.push('_');
+        }
+        match c.is_ascii_alphanumeric() {
+            true => var.push(c.to_ascii_uppercase()),
+            false if !var.ends_with('_') => var.push('_'),
+            false => {}
+        }
+    }
+    var
+}
+
+fn derive_key(
+    path: &Path,
+    unlock: &StoredUnlock,
+    secret: &CredentialVaultSecret,
+) -> Result<Zeroizing<[u8; 32]>, CredentialVaultError> {
+    let mut key = Zeroizing::new([0; 32]);
+    match (unlock, secret) {
+        (
+            StoredUnlock::Passphrase {
+                salt,
+                memory,
+                iterations,
+                parallelism,
+            },
+            CredentialVaultSecret::Passphrase(passphrase),
+        ) => {
+            let corrupt =
+                |error: &dyn core::fmt::Display| CredentialVaultError::Corrupt(error.to_string());
+            let salt = BASE64_STANDARD
+                .decode(salt)
+                .map_err(|error| corrupt(&error))?;
+            let parallelism = (*parallelism).clamp(Params::MIN_P_COST, MAX_PARALLELISM);
+            let params = Params::new(
+                (*memory).clamp(Params::MIN_M_COST.max(8 * parallelism), MAX_MEMORY),
+                (*iterations).clamp(Params::MIN_T_COST, MAX_ITERATIONS),
+                parallelism,
+                Some(key.len()),
+            )
+            .map_err(|error| corrupt(&error))?;
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut())
+                .map_err(|error| corrupt(&error))?;
+        }
+        (StoredUnlock::Installation, CredentialVaultSecret::Installation) => {
+            *key = fs::installation_key(path)?.derive("neonex credential vault");
+        }
+        _ => return Err(CredentialVaultError::WrongSecretKind),
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault_path(dir: &tempfile::TempDir) -> PathBuf {
+        dir.path().join("credentials.vault")
+    }
+
+    #[test]
+    fn secrets_are_read_back_with_the_passphrase() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+        let passphrase = CredentialVaultSecret::passphrase("correct horse");
+
+        let mut vault = CredentialVault::open(&path, &passphrase).unwrap();
+        vault.set("LoginToken", "hunter2");
+        vault.save().unwrap();
+        assert!(!String::from_utf8_lossy(&std::fs::read(&path).unwrap()).contains("hunter2"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let vault = CredentialVault::open(&path, &passphrase).unwrap();
+        assert_eq!(vault.get("LoginToken"), Some("hunter2"));
+        assert_eq!(vault.names().collect::<Vec<_>>(), ["LoginToken"]);
+    }
+
+    #[test]
+    fn wrong_secrets_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+        let mut vault =
+            CredentialVault::open(&path, &CredentialVaultSecret::passphrase("right")).unwrap();
+        vault.set("LoginToken", "hunter2");
+        vault.save().unwrap();
+
+        let wrong = CredentialVault::open(&path, &CredentialVaultSecret::passphrase("wrong"));
+        assert!(matches!(wrong, Err(CredentialVaultError::WrongSecret)));
+        let wrong_kind = CredentialVault::open(&path, &CredentialVaultSecret::Installation);
+        assert!(matches!(
+            wrong_kind,
+            Err(CredentialVaultError::WrongSecretKind)
+        ));
+    }
+
+    #[test]
+    fn stored_argon2_params_are_clamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = vault_path(&dir);
+        // Would take forever without a bound on the iterations.
+        let stored = serde_json::json!({
+            "version": VERSION,
+            "unlock": {
+                "kind": "passphrase",
+                "salt": BASE64_STANDARD.encode([0; SALT_LEN]),
+                "memory": 0,
+                "iterations": u32::MAX,
+                "parallelism": 0,
+            },
+            "data": BASE64_STANDARD.encode([0; NONCE_LEN + 16]),
+        });
+        std::fs::write(&path, stored.to_string()).unwrap();
+
+        let vault = CredentialVault::open(&path, &CredentialVaultSecret::passphrase("any"));
+        assert!(matches!(vault, Err(CredentialVaultError::WrongSecret)));
+    }
+
+    #[test]
+    fn handed_off_secrets_are_only_read_once() {
+        let var = secret_var("HandoffTestToken");
+        // SAFETY: no other test reads or writes this variable.
+        unsafe { env::set_var(&var, "hunter2") };
+
+        assert_eq!(
+            handed_off_secret("HandoffTestToken")
+                .as_deref()
+                .map(String::as_str),
+            Some("hunter2")
+        );
+        assert!(env::var_os(&var).is_none());
+        assert!(handed_off_secret("HandoffTestToken").is_none());
+    }
+}
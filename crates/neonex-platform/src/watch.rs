@@ -0,0 +1,57 @@
+use core::{
+    any::Any,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bevy::{
+    ecs::resource::Resource,
+    platform::{
+        prelude::Box,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// Tells when the startup config has been modified outside of the app, e.g. by a child
+/// process or an external editor. Created by
+/// [`NeoNexPlatform::watch_startup_config`](crate::NeoNexPlatform::watch_startup_config).
+#[derive(Resource)]
+pub struct StartupConfigWatcher {
+    changed: Arc<AtomicBool>,
+    /// Whatever has to stay alive for the notifications to keep coming, like a file watcher.
+    _guard: Mutex<Option<Box<dyn Any + Send>>>,
+}
+
+impl StartupConfigWatcher {
+    /// Creates a watcher, along with the notifier the platform calls on each modification.
+    pub fn new() -> (Self, StartupConfigChangeNotifier) {
+        let changed = Arc::new(AtomicBool::new(false));
+        let watcher = Self {
+            changed: changed.clone(),
+            _guard: Mutex::new(None),
+        };
+        (watcher, StartupConfigChangeNotifier(changed))
+    }
+
+    /// Keeps `guard` alive as long as the watcher.
+    pub fn keep_alive(self, guard: impl Any + Send) -> Self {
+        Self {
+            _guard: Mutex::new(Some(Box::new(guard))),
+            ..self
+        }
+    }
+
+    /// Whether the startup config has been modified since the last call.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// Notifies a [`StartupConfigWatcher`], from any thread.
+#[derive(Clone)]
+pub struct StartupConfigChangeNotifier(Arc<AtomicBool>);
+
+impl StartupConfigChangeNotifier {
+    pub fn notify(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+}
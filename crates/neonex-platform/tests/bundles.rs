@@ -0,0 +1,68 @@
+//! Bundles exported then imported back, on the same installation.
+#![cfg(feature = "integrity")]
+
+use neonex_mockplatform::MockPlatform;
+use neonex_platform::{
+    MemoryStartupConfigStore, NeoNexConfig, export_startup_config_bundle,
+    preview_startup_config_bundle,
+};
+use neonex_shared::{UserStartupConfig, namespaces::NamespacedStartupConfigSet};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+enum LauncherConfig {
+    Volume(u16),
+    LoginToken(String),
+}
+
+struct Launcher;
+
+impl NeoNexConfig for Launcher {
+    type Platform = MockPlatform;
+    type StartupConfig = LauncherConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+    const STARTUP_CONFIG_ENCRYPTED_KEYS: &'static [&'static str] = &["LoginToken"];
+}
+
+#[test]
+fn encrypted_entries_are_kept_on_import() {
+    let mut store = NamespacedStartupConfigSet::default();
+    store.get_mut(None).set(LauncherConfig::Volume(3));
+    store
+        .get_mut(None)
+        .set(LauncherConfig::LoginToken("old".to_string()));
+    store
+        .get_mut(Some("game"))
+        .set(LauncherConfig::LoginToken("game".to_string()));
+    let bundle = export_startup_config_bundle::<Launcher>(&store).unwrap();
+    assert!(!String::from_utf8_lossy(&bundle).contains("LoginToken"));
+
+    // The token has been renewed since the export.
+    let mut current = store.clone();
+    current.get_mut(None).set(LauncherConfig::Volume(8));
+    current
+        .get_mut(None)
+        .set(LauncherConfig::LoginToken("new".to_string()));
+
+    let import = preview_startup_config_bundle::<Launcher>(&bundle, &current).unwrap();
+    let global = import.store.get(None).unwrap();
+    assert_eq!(
+        global.get_by_key("Volume"),
+        Some(&LauncherConfig::Volume(3).into())
+    );
+    assert_eq!(
+        global.get_by_key("LoginToken"),
+        Some(&LauncherConfig::LoginToken("new".to_string()).into())
+    );
+    assert_eq!(
+        import
+            .store
+            .get(Some("game"))
+            .unwrap()
+            .get_by_key("LoginToken"),
+        Some(&LauncherConfig::LoginToken("game".to_string()).into())
+    );
+    // Only the volume changes.
+    assert_eq!(import.changes.len(), 1);
+    assert_eq!(import.changes[0].1.len(), 1);
+}
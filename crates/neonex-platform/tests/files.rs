@@ -0,0 +1,158 @@
+//! Startup config files that can't be read as they are.
+#![cfg(feature = "std")]
+
+use std::{fs, path::Path};
+
+use neonex_mockplatform::MockPlatform;
+use neonex_platform::{
+    MemoryStartupConfigStore, NeoNexConfig, StartupConfigLoadError,
+    fs::{
+        back_up_startup_config, read_startup_config, reread_startup_config, write_startup_config,
+    },
+};
+use neonex_shared::{
+    NoStartupConfig, migration::StartupConfigMigration, namespaces::NamespacedStartupConfigSet,
+};
+
+struct App;
+
+impl NeoNexConfig for App {
+    type Platform = MockPlatform;
+    type StartupConfig = NoStartupConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+}
+
+/// The same app, once its schema has been migrated.
+struct NewerApp;
+
+impl NeoNexConfig for NewerApp {
+    type Platform = MockPlatform;
+    type StartupConfig = NoStartupConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+    const STARTUP_CONFIG_MIGRATIONS: &'static [StartupConfigMigration] = &[|_| {}];
+}
+
+fn backups(dir: &Path) -> Vec<String> {
+    let mut backups: Vec<_> = fs::read_dir(dir.join(".neonex-state"))
+        .into_iter()
+        .flatten()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.ends_with(".bak"))
+        .collect();
+    backups.sort();
+    backups
+}
+
+#[test]
+fn corrupt_files_are_moved_to_distinct_backups() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startup-config.json");
+
+    for _ in 0..2 {
+        fs::write(&path, b"{ not json").unwrap();
+        let error = read_startup_config::<App>(&path).unwrap_err();
+        assert!(matches!(
+            error,
+            StartupConfigLoadError::Corrupt {
+                backup: Some(_),
+                ..
+            }
+        ));
+        assert!(!path.exists());
+    }
+    assert_eq!(backups(dir.path()).len(), 2);
+}
+
+#[test]
+fn backups_of_the_same_second_are_distinct() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startup-config.json");
+    write_startup_config::<App>(&path, &NamespacedStartupConfigSet::default()).unwrap();
+
+    let first = back_up_startup_config(&path).unwrap().unwrap();
+    let second = back_up_startup_config(&path).unwrap().unwrap();
+    assert_ne!(first, second);
+    assert_eq!(backups(dir.path()).len(), 2);
+}
+
+#[test]
+fn files_of_newer_versions_are_left_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startup-config.json");
+    write_startup_config::<NewerApp>(&path, &NamespacedStartupConfigSet::default()).unwrap();
+    let data = fs::read(&path).unwrap();
+
+    let error = read_startup_config::<App>(&path).unwrap_err();
+    assert!(matches!(error, StartupConfigLoadError::TooNew(_)));
+    assert_eq!(fs::read(&path).unwrap(), data);
+    assert!(backups(dir.path()).is_empty());
+}
+
+#[test]
+fn corrupt_files_are_left_untouched_on_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startup-config.json");
+    fs::write(&path, b"{ not json").unwrap();
+
+    let error = reread_startup_config::<App>(&path).unwrap_err();
+    assert!(matches!(
+        error,
+        StartupConfigLoadError::Corrupt { backup: None, .. }
+    ));
+    assert!(path.exists());
+    assert!(backups(dir.path()).is_empty());
+}
+
+#[cfg(feature = "integrity")]
+#[test]
+fn unsigned_files_are_sealed_once_read() {
+    use neonex_platform::is_signed;
+    use neonex_shared::NeoNexStartupConfig;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startup-config.json");
+    let unsigned =
+        br#"{ "neonex_version": 1, "version": 0, "values": { "NativeTerminal": true } }"#;
+    fs::write(&path, unsigned).unwrap();
+
+    for _ in 0..2 {
+        let store = read_startup_config::<App>(&path).unwrap();
+        assert_eq!(
+            store.global().get_by_key("NativeTerminal"),
+            Some(&NeoNexStartupConfig::NativeTerminal(true))
+        );
+        assert!(is_signed(&fs::read(&path).unwrap()));
+    }
+    assert!(backups(dir.path()).is_empty());
+
+    // Unless it's been written by a newer version of the app.
+    let newer = br#"{ "neonex_version": 1, "version": 1, "values": {} }"#;
+    fs::write(&path, newer).unwrap();
+    let error = read_startup_config::<App>(&path).unwrap_err();
+    assert!(matches!(error, StartupConfigLoadError::TooNew(_)));
+    assert_eq!(fs::read(&path).unwrap(), newer);
+}
+
+#[cfg(not(feature = "toml"))]
+#[test]
+fn files_of_disabled_formats_are_left_untouched() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("startup-config.json");
+    let toml = dir.path().join("startup-config.toml");
+    fs::write(&toml, b"neonex_version = 1").unwrap();
+
+    assert!(
+        read_startup_config::<App>(&path)
+            .unwrap()
+            .global()
+            .is_empty()
+    );
+    write_startup_config::<App>(&path, &NamespacedStartupConfigSet::default()).unwrap();
+    assert!(toml.exists());
+
+    fs::write(&path, b"neonex_version = 1").unwrap();
+    let error = read_startup_config::<App>(&path).unwrap_err();
+    assert!(matches!(error, StartupConfigLoadError::Unsupported(_)));
+    assert!(path.exists());
+    assert!(backups(dir.path()).is_empty());
+}
@@ -0,0 +1,84 @@
+//! Startup config sets written and read back in every enabled format.
+
+use neonex_mockplatform::MockPlatform;
+#[cfg(feature = "toml")]
+use neonex_platform::StartupConfigFormat;
+use neonex_platform::{
+    MemoryStartupConfigStore, NeoNexConfig, decode_startup_config, encode_startup_config,
+};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum LauncherConfig {
+    Volume(u16),
+    /// A variant without a value, which not every format has a `null` for.
+    SkipIntro,
+}
+
+impl neonex_shared::UserStartupConfig for LauncherConfig {
+    fn key(&self) -> &'static str {
+        match self {
+            LauncherConfig::Volume(_) => "Volume",
+            LauncherConfig::SkipIntro => "SkipIntro",
+        }
+    }
+}
+
+fn store() -> NamespacedStartupConfigSet<LauncherConfig> {
+    let mut store = NamespacedStartupConfigSet::default();
+    store.global_mut().set(LauncherConfig::Volume(5));
+    store.global_mut().set(LauncherConfig::SkipIntro);
+    store.get_mut(Some("game")).set(LauncherConfig::SkipIntro);
+    store
+}
+
+struct JsonLauncher;
+
+impl NeoNexConfig for JsonLauncher {
+    type Platform = MockPlatform;
+    type StartupConfig = LauncherConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+}
+
+#[test]
+fn unit_variants_round_trip_in_json() {
+    let data = encode_startup_config::<JsonLauncher>(&store()).unwrap();
+    assert_eq!(
+        decode_startup_config::<JsonLauncher>(&data).unwrap(),
+        store()
+    );
+}
+
+#[test]
+fn unit_variants_written_as_null_are_still_read() {
+    let data = br#"{ "neonex_version": 1, "version": 0, "values": { "SkipIntro": null } }"#;
+    let store = decode_startup_config::<JsonLauncher>(data).unwrap();
+    assert!(store.global().dynamic().is_empty());
+    assert_eq!(store.global().len(), 1);
+}
+
+#[cfg(feature = "toml")]
+struct TomlLauncher;
+
+#[cfg(feature = "toml")]
+impl NeoNexConfig for TomlLauncher {
+    type Platform = MockPlatform;
+    type StartupConfig = LauncherConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+    const STARTUP_CONFIG_FORMAT: StartupConfigFormat = StartupConfigFormat::Toml;
+}
+
+#[cfg(feature = "toml")]
+#[test]
+fn unit_variants_round_trip_in_toml() {
+    let data = encode_startup_config::<TomlLauncher>(&store()).unwrap();
+    assert_eq!(
+        StartupConfigFormat::detect(&data).unwrap(),
+        StartupConfigFormat::Toml
+    );
+    assert_eq!(
+        decode_startup_config::<TomlLauncher>(&data).unwrap(),
+        store()
+    );
+}
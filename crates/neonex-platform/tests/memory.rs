@@ -0,0 +1,86 @@
+//! Startup configs kept in memory, as by the configs of the tests.
+
+use std::collections::BTreeMap;
+
+use neonex_mockplatform::MockPlatform;
+use neonex_platform::{
+    MemoryStartupConfigStore, NeoNexConfig, StartupConfigLoadError, StartupConfigStore,
+};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum LauncherConfig {
+    Volume(u16),
+    /// Tuples can't be the keys of a persisted map, so a set holding it can't be encoded.
+    Shortcuts(BTreeMap<(u8, u8), String>),
+}
+
+impl neonex_shared::UserStartupConfig for LauncherConfig {
+    fn key(&self) -> &'static str {
+        match self {
+            LauncherConfig::Volume(_) => "Volume",
+            LauncherConfig::Shortcuts(_) => "Shortcuts",
+        }
+    }
+}
+
+struct Launcher;
+
+impl NeoNexConfig for Launcher {
+    type Platform = MockPlatform;
+    type StartupConfig = LauncherConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+    const APP_ID: &'static str = "org.neonex.test.memory-launcher";
+}
+
+struct CorruptLauncher;
+
+impl NeoNexConfig for CorruptLauncher {
+    type Platform = MockPlatform;
+    type StartupConfig = LauncherConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+    const APP_ID: &'static str = "org.neonex.test.memory-corrupt";
+}
+
+type Store = <Launcher as NeoNexConfig>::StartupConfigStore;
+
+#[test]
+fn saved_sets_are_loaded_back_and_unencodable_ones_are_refused() {
+    let mut saved = NamespacedStartupConfigSet::default();
+    saved.global_mut().set(LauncherConfig::Volume(5));
+    saved.get_mut(Some("game")).set(LauncherConfig::Volume(2));
+    Store::save::<Launcher>(&saved).unwrap();
+    assert_eq!(Store::load::<Launcher>().unwrap(), saved);
+
+    let shortcuts = BTreeMap::from([((1, 2), "quit".into())]);
+    let mut unencodable = saved.clone();
+    unencodable
+        .global_mut()
+        .set(LauncherConfig::Shortcuts(shortcuts));
+    let error = Store::save::<Launcher>(&unencodable)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("key must be a string"), "{error}");
+    assert_eq!(Store::load::<Launcher>().unwrap(), saved);
+}
+
+#[test]
+fn corrupt_sets_are_reset() {
+    MemoryStartupConfigStore::set_data::<CorruptLauncher>(b"{ \"version\": ".to_vec());
+    assert!(matches!(
+        MemoryStartupConfigStore::reload::<CorruptLauncher>(),
+        Err(StartupConfigLoadError::Corrupt { backup: None, .. })
+    ));
+    assert!(MemoryStartupConfigStore::data::<CorruptLauncher>().is_some());
+
+    assert!(matches!(
+        MemoryStartupConfigStore::load::<CorruptLauncher>(),
+        Err(StartupConfigLoadError::Corrupt { backup: None, .. })
+    ));
+    assert_eq!(MemoryStartupConfigStore::data::<CorruptLauncher>(), None);
+    assert_eq!(
+        MemoryStartupConfigStore::load::<CorruptLauncher>().unwrap(),
+        NamespacedStartupConfigSet::default()
+    );
+}
@@ -0,0 +1,69 @@
+//! Startup config files written by older versions of NeoNex, upgraded through the migrations.
+
+use neonex_mockplatform::MockPlatform;
+use neonex_platform::{MemoryStartupConfigStore, NeoNexConfig, load_startup_config_fixture};
+use neonex_shared::{NativeTerminal, migration::StartupConfigMigration, startup_config_key};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum LauncherConfig {
+    Volume(u16),
+}
+
+impl neonex_shared::UserStartupConfig for LauncherConfig {
+    fn key(&self) -> &'static str {
+        "Volume"
+    }
+}
+
+startup_config_key!(Volume => LauncherConfig::Volume(u16));
+
+/// Version 1 of the launcher renamed `Vol` to `Volume`.
+fn rename_vol(entries: &mut Vec<Value>) {
+    for entry in entries {
+        if let Some(volume) = entry.as_object_mut().and_then(|entry| entry.remove("Vol")) {
+            *entry = json!({ "Volume": volume });
+        }
+    }
+}
+
+struct Launcher;
+
+impl NeoNexConfig for Launcher {
+    type Platform = MockPlatform;
+    type StartupConfig = LauncherConfig;
+    type StartupConfigStore = MemoryStartupConfigStore;
+    const STARTUP_CONFIG_MIGRATIONS: &'static [StartupConfigMigration] = &[rename_vol];
+}
+
+#[test]
+fn v0_placeholder_entries_are_dropped() {
+    let store = load_startup_config_fixture::<Launcher>(include_bytes!("fixtures/v0.json"));
+
+    assert_eq!(store.global().get::<NativeTerminal>(), Some(&true));
+    assert_eq!(store.global().len(), 1);
+    // Dropped by the migration rather than kept aside as unknown entries.
+    assert!(store.global().dynamic().is_empty());
+    assert_eq!(store.namespaces().count(), 0);
+}
+
+#[test]
+fn user_migrations_run_from_the_persisted_version() {
+    let v0 = br#"{ "neonex_version": 1, "version": 0, "values": { "Vol": 5 } }"#;
+    let store = load_startup_config_fixture::<Launcher>(v0);
+    assert_eq!(store.global().get::<Volume>(), Some(&5));
+
+    // Already upgraded, `Vol` is an unknown entry.
+    let v1 = br#"{ "neonex_version": 1, "version": 1, "values": { "Vol": 5 } }"#;
+    let store = load_startup_config_fixture::<Launcher>(v1);
+    assert_eq!(store.global().get::<Volume>(), None);
+    assert_eq!(store.global().dynamic().get("Vol"), Some(&json!(5)));
+}
+
+#[test]
+fn unversioned_keyed_files_run_every_migration() {
+    let store = load_startup_config_fixture::<Launcher>(br#"{ "values": { "Vol": 5 } }"#);
+    assert_eq!(store.global().get::<Volume>(), Some(&5));
+    assert!(store.global().dynamic().is_empty());
+}
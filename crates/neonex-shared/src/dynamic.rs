@@ -0,0 +1,191 @@
+//! String-keyed startup config entries, and the JSON layout the startup config is persisted
+//! with, so that launchers written in any language can read and write it.
+//!
+//! The layout is stable: entries are keyed by their key, see [`UserStartupConfig::key`],
+//! and hold the value of their variant. The global namespace is under `values`, the other
+//! namespaces under `namespaces`:
+//! ```json
+//! {
+//!   "neonex_version": 1,
+//!   "version": 2,
+//!   "values": { "NativeTerminal": false, "Volume": 5, "Theme": "Dark" },
+//!   "namespaces": { "game": { "Volume": 8 } }
+//! }
+//! ```
+//!
+//! - `neonex_version` and `version` are the versions of the schemas of the NeoNex entries
+//!   and of the app entries. Launchers keep them as they are. When they're missing, the
+//!   entries are read as version 0, and go through every migration.
+//! - Entries whose key isn't known by the app, e.g. the settings of the launcher itself,
+//!   are kept as they are, see [`NeoNexStartupConfigSet::dynamic`]. So are the entries whose
+//!   value doesn't match the schema of the app, which falls back to its default meanwhile.
+//! - Variants without a value are `true`, as some formats, e.g. TOML, have no `null`.
+//!   `null` is still read.
+//!
+//! Files written before this layout, with `values` holding a list of `{ "Key": value }`
+//! entries, are still read. With the `integrity` feature of `neonex-platform`, the file is
+//! signed and can't be edited outside of the app.
+
+use alloc::collections::BTreeMap;
+use bevy::platform::prelude::{String, Vec};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{NeoNexStartupConfigSet, UserStartupConfig, migration::PersistedStartupConfig};
+
+/// Entries of a startup config set addressed by a key only known at runtime, holding their
+/// value as persisted, e.g. `5` for `{ "Volume": 5 }`.
+///
+/// Serialized as a JSON object, like the `values` of the persisted layout.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DynamicStartupConfigSet {
+    values: BTreeMap<String, Value>,
+}
+
+impl DynamicStartupConfigSet {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.values.get(key)
+    }
+
+    /// Sets an entry, returning the previous value with the same key.
+    pub fn set(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.values.insert(key.into(), value)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.values.remove(key)
+    }
+
+    /// All the entries, by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Every entry of `set` along with its own dynamic entries, e.g. to hand them to a
+    /// launcher that doesn't know the schema of the app.
+    pub fn from_set<U: UserStartupConfig>(
+        set: &NeoNexStartupConfigSet<U>,
+    ) -> serde_json::Result<Self> {
+        let mut dynamic = set.dynamic().clone();
+        for entry in set.iter() {
+            if let Some((key, value)) = split_entry(serde_json::to_value(entry)?) {
+                dynamic.set(key, value);
+            }
+        }
+        Ok(dynamic)
+    }
+
+    /// Entries of `persisted` seen by an app of `namespace`, i.e. the global ones overridden
+    /// by the ones of the namespace, without upgrading nor parsing them, e.g. for apps that
+    /// don't know the schema of the entries.
+    pub fn from_persisted(persisted: &PersistedStartupConfig, namespace: Option<&str>) -> Self {
+        let namespace = namespace.and_then(|name| persisted.namespaces.get(name));
+        persisted
+            .values
+            .iter()
+            .chain(namespace.into_iter().flatten())
+            .filter_map(|entry| split_entry(entry.clone()))
+            .collect()
+    }
+
+    /// Parses the entries into a set, the ones that don't match the schema of `U` being kept
+    /// as its dynamic entries.
+    pub fn into_set<U: UserStartupConfig>(self) -> NeoNexStartupConfigSet<U> {
+        let mut set = NeoNexStartupConfigSet::default();
+        for (key, value) in self.values {
+            set.insert_persisted(key, value);
+        }
+        set
+    }
+}
+
+impl FromIterator<(String, Value)> for DynamicStartupConfigSet {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        Self {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Splits a persisted entry, e.g. `{ "Volume": 5 }`, into its key and value. Variants without
+/// a value are serialized as their key, and have a `true` value.
+pub(crate) fn split_entry(entry: Value) -> Option<(String, Value)> {
+    match entry {
+        Value::Object(object) if object.len() == 1 => object.into_iter().next(),
+        Value::String(key) => Some((key, Value::Bool(true))),
+        _ => None,
+    }
+}
+
+/// The persisted entry with `key` and `value`, see [`split_entry`].
+pub(crate) fn join_entry(key: String, value: Value) -> Value {
+    Value::Object(Map::from_iter([(key, value)]))
+}
+
+/// Persisted entries as written by the layout, keyed, or by older versions, listed.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum PersistedEntries {
+    Keyed(Map<String, Value>),
+    Listed(Vec<Value>),
+}
+
+impl Default for PersistedEntries {
+    fn default() -> Self {
+        PersistedEntries::Keyed(Map::new())
+    }
+}
+
+impl PersistedEntries {
+    /// The entries, listed as `{ "Key": value }` objects.
+    pub(crate) fn into_entries(self) -> Vec<Value> {
+        match self {
+            PersistedEntries::Keyed(object) => object
+                .into_iter()
+                .map(|(key, value)| join_entry(key, value))
+                .collect(),
+            PersistedEntries::Listed(entries) => entries,
+        }
+    }
+}
+
+/// Serializes listed entries with the keyed layout. Entries that aren't `{ "Key": value }`
+/// objects are left out.
+pub(crate) fn serialize_keyed<S: serde::Serializer>(
+    entries: &[Value],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        entries
+            .iter()
+            .filter_map(|entry| split_entry(entry.clone())),
+    )
+}
+
+pub(crate) fn serialize_keyed_namespaces<S: serde::Serializer>(
+    namespaces: &BTreeMap<String, Vec<Value>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    struct Keyed<'a>(&'a [Value]);
+
+    impl Serialize for Keyed<'_> {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_keyed(self.0, serializer)
+        }
+    }
+
+    serializer.collect_map(
+        namespaces
+            .iter()
+            .map(|(name, entries)| (name, Keyed(entries))),
+    )
+}
@@ -0,0 +1,262 @@
+use core::marker::PhantomData;
+
+use crate::{NeoNexStartupConfig, NeoNexStartupConfigSet, UserStartupConfig};
+
+/// Typed access to one entry of a [`NeoNexStartupConfigSet`], used by `get::<K>()`,
+/// `remove::<K>()` and `entry::<K>()`.
+///
+/// Keys of user entries are declared with [`startup_config_key!`](crate::startup_config_key).
+pub trait StartupConfigKey<U> {
+    /// Key of the entry, as returned by [`NeoNexStartupConfig::key`].
+    const KEY: &'static str;
+    /// Value held by the entry.
+    type Value;
+
+    fn value(entry: &NeoNexStartupConfig<U>) -> Option<&Self::Value>;
+    fn value_mut(entry: &mut NeoNexStartupConfig<U>) -> Option<&mut Self::Value>;
+    fn into_entry(value: Self::Value) -> NeoNexStartupConfig<U>;
+}
+
+/// Key of [`NeoNexStartupConfig::NativeTerminal`].
+pub struct NativeTerminal;
+
+impl<U> StartupConfigKey<U> for NativeTerminal {
+    const KEY: &'static str = "NativeTerminal";
+    type Value = bool;
+
+    fn value(entry: &NeoNexStartupConfig<U>) -> Option<&bool> {
+        match entry {
+            NeoNexStartupConfig::NativeTerminal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn value_mut(entry: &mut NeoNexStartupConfig<U>) -> Option<&mut bool> {
+        match entry {
+            NeoNexStartupConfig::NativeTerminal(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn into_entry(value: bool) -> NeoNexStartupConfig<U> {
+        NeoNexStartupConfig::NativeTerminal(value)
+    }
+}
+
+/// Declares the key type of a variant of a user startup config enum:
+/// ```ignore
+/// startup_config_key!(pub Volume => LauncherConfig::Volume(u16));
+///
+/// let volume: Option<&u16> = set.get::<Volume>();
+/// ```
+///
+/// The key is the name of the variant, so [`UserStartupConfig::key`] must return it, as the
+/// derived implementation does. Debug builds check it whenever an entry is created from its
+/// value.
+#[macro_export]
+macro_rules! startup_config_key {
+    ($vis:vis $name:ident => $config:ident :: $variant:ident ($value:ty)) => {
+        $vis struct $name;
+
+        #[allow(unreachable_patterns)]
+        impl $crate::StartupConfigKey<$config> for $name {
+            const KEY: &'static str = stringify!($variant);
+            type Value = $value;
+
+            fn value(entry: &$crate::NeoNexStartupConfig<$config>) -> Option<&$value> {
+                match entry {
+                    $crate::NeoNexStartupConfig::User($config::$variant(value)) => Some(value),
+                    _ => None,
+                }
+            }
+
+            fn value_mut(
+                entry: &mut $crate::NeoNexStartupConfig<$config>,
+            ) -> Option<&mut $value> {
+                match entry {
+                    $crate::NeoNexStartupConfig::User($config::$variant(value)) => Some(value),
+                    _ => None,
+                }
+            }
+
+            fn into_entry(value: $value) -> $crate::NeoNexStartupConfig<$config> {
+                let entry = $config::$variant(value);
+                debug_assert_eq!(
+                    $crate::UserStartupConfig::key(&entry),
+                    stringify!($variant),
+                    "`UserStartupConfig::key` must return the name of the variant",
+                );
+                $crate::NeoNexStartupConfig::User(entry)
+            }
+        }
+    };
+}
+
+/// In-place access to the entry `K` of a set, see [`NeoNexStartupConfigSet::entry`].
+pub struct StartupConfigEntry<'a, U, K> {
+    set: &'a mut NeoNexStartupConfigSet<U>,
+    key: PhantomData<K>,
+}
+
+impl<'a, U: UserStartupConfig, K: StartupConfigKey<U>> StartupConfigEntry<'a, U, K> {
+    pub(crate) fn new(set: &'a mut NeoNexStartupConfigSet<U>) -> Self {
+        Self {
+            set,
+            key: PhantomData,
+        }
+    }
+
+    /// Value of the entry, inserting `default` first if the entry isn't set.
+    pub fn or_insert(self, default: K::Value) -> &'a mut K::Value {
+        self.or_insert_with(|| default)
+    }
+
+    /// Value of the entry, inserting the result of `default` first if the entry isn't set.
+    ///
+    /// Another variant can only be set under the key of `K` when its
+    /// [`UserStartupConfig::key`] doesn't return the name of its variant: debug builds panic,
+    /// others replace it.
+    pub fn or_insert_with(self, default: impl FnOnce() -> K::Value) -> &'a mut K::Value {
+        let values = &mut self.set.values;
+        let mismatching = values
+            .get(K::KEY)
+            .is_some_and(|entry| K::value(entry).is_none());
+        debug_assert!(
+            !mismatching,
+            "the startup config entry set under {} is another variant",
+            K::KEY
+        );
+        if mismatching {
+            values.remove(K::KEY);
+        }
+        let entry = values
+            .entry(K::KEY)
+            .or_insert_with(|| K::into_entry(default()));
+        K::value_mut(entry).expect("the entry is a value of its key")
+    }
+
+    /// Modifies the value of the entry, if it's set.
+    pub fn and_modify(self, f: impl FnOnce(&mut K::Value)) -> Self {
+        if let Some(value) = self.set.values.get_mut(K::KEY).and_then(K::value_mut) {
+            f(value);
+        }
+        self
+    }
+}
+
+impl<'a, U: UserStartupConfig, K: StartupConfigKey<U>> StartupConfigEntry<'a, U, K>
+where
+    K::Value: Default,
+{
+    pub fn or_default(self) -> &'a mut K::Value {
+        self.or_insert_with(Default::default)
+    }
+}
+
+/// Names of the variants of `U`, as declared to serde, i.e. the keys of its entries. Empty
+/// when `U` isn't deserialized as an enum.
+pub(crate) fn variant_names<U: UserStartupConfig>() -> &'static [&'static str] {
+    /// Only records the variants `U` asks for, failing right after.
+    struct VariantNames<'a>(&'a mut &'static [&'static str]);
+
+    impl<'de> serde::Deserializer<'de> for VariantNames<'_> {
+        type Error = serde_json::Error;
+
+        fn deserialize_any<V: serde::de::Visitor<'de>>(
+            self,
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            Err(serde::de::Error::custom("not an enum"))
+        }
+
+        fn deserialize_enum<V: serde::de::Visitor<'de>>(
+            self,
+            _name: &'static str,
+            variants: &'static [&'static str],
+            _visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            *self.0 = variants;
+            Err(serde::de::Error::custom("only the variants are needed"))
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map struct
+            identifier ignored_any
+        }
+    }
+
+    let mut variants: &'static [&'static str] = &[];
+    let _ = U::deserialize(VariantNames(&mut variants));
+    variants
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum TestConfig {
+        Volume(u16),
+        /// Wrongly shares the key of `Volume`.
+        OldVolume(u8),
+    }
+
+    impl UserStartupConfig for TestConfig {
+        fn key(&self) -> &'static str {
+            "Volume"
+        }
+    }
+
+    crate::startup_config_key!(Volume => TestConfig::Volume(u16));
+
+    #[test]
+    fn or_insert_inserts_then_modifies() {
+        let mut set = NeoNexStartupConfigSet::<TestConfig>::default();
+        *set.entry::<Volume>().or_insert(1) += 1;
+        *set.entry::<Volume>().or_insert(1) += 1;
+        assert_eq!(set.get::<Volume>(), Some(&3));
+        assert_eq!(set.entry::<NativeTerminal>().or_default(), &mut false);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "is another variant")]
+    fn or_insert_panics_on_mismatching_entries() {
+        let mut set = NeoNexStartupConfigSet::<TestConfig>::default();
+        set.set(TestConfig::OldVolume(3));
+        set.entry::<Volume>().or_insert(1);
+    }
+
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn or_insert_replaces_mismatching_entries() {
+        let mut set = NeoNexStartupConfigSet::<TestConfig>::default();
+        set.set(TestConfig::OldVolume(3));
+        assert_eq!(set.entry::<Volume>().or_insert(1), &mut 1);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "must return the name of the variant")]
+    fn keys_of_other_variants_are_caught() {
+        crate::startup_config_key!(OldVolume => TestConfig::OldVolume(u8));
+
+        let mut set = NeoNexStartupConfigSet::<TestConfig>::default();
+        set.entry::<OldVolume>().or_insert(1);
+    }
+
+    #[test]
+    fn keys_are_the_variant_names() {
+        assert_eq!(
+            NeoNexStartupConfig::<TestConfig>::keys(),
+            ["NativeTerminal", "Volume", "OldVolume"]
+        );
+        assert_eq!(
+            NeoNexStartupConfig::<crate::NoStartupConfig>::keys(),
+            ["NativeTerminal"]
+        );
+    }
+}
@@ -0,0 +1,580 @@
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use serde_json::{Map, Value};
+
+use crate::{
+    NeoNexStartupConfig, NeoNexStartupConfigSet, NoStartupConfig, UserStartupConfig,
+    validation::StartupConfigViolation,
+};
+
+/// Where an entry of the startup config comes from. Each layer overrides the entries of the
+/// previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StartupConfigLayer {
+    /// Compiled into the app, with `NeoNexConfig::default_startup_config`.
+    Default,
+    /// Shipped next to the app, e.g. by an operator setting defaults for a fleet.
+    System,
+    /// Persisted by the app, the only layer it writes back.
+    User,
+    /// `NEONEX_*` environment variables, e.g. `NEONEX_NATIVE_TERMINAL=true`.
+    Environment,
+    /// `--neonex-*` command-line flags, e.g. `--neonex-native-terminal=true`, or the
+    /// `neonex.*` parameters of the URL on the web, see [`url_override_args`].
+    CommandLine,
+    /// Shipped next to the app like the system layer, e.g. by an operator pinning values for
+    /// a fleet. Nothing overrides it, not even the command line.
+    Enforced,
+}
+
+impl StartupConfigLayer {
+    /// Every layer, from the lowest to the highest priority.
+    pub const ALL: [StartupConfigLayer; 6] = [
+        StartupConfigLayer::Default,
+        StartupConfigLayer::System,
+        StartupConfigLayer::User,
+        StartupConfigLayer::Environment,
+        StartupConfigLayer::CommandLine,
+        StartupConfigLayer::Enforced,
+    ];
+}
+
+/// The layers the effective startup config set is merged from, see [`StartupConfigLayer`].
+///
+/// Modifications of the effective set are kept in the user layer, even for entries coming
+/// from another layer. As the other layers aren't written back, removing an entry they hold
+/// only removes it from the user layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayeredStartupConfigSet<U = NoStartupConfig> {
+    layers: [NeoNexStartupConfigSet<U>; 6],
+    /// Keys of the invalid entries of each layer, left out of the effective set, see
+    /// [`skip_invalid`](Self::skip_invalid).
+    skipped: [Vec<&'static str>; 6],
+}
+
+impl<U> Default for LayeredStartupConfigSet<U> {
+    fn default() -> Self {
+        Self {
+            layers: Default::default(),
+            skipped: Default::default(),
+        }
+    }
+}
+
+impl<U: UserStartupConfig> LayeredStartupConfigSet<U> {
+    pub fn layer(&self, layer: StartupConfigLayer) -> &NeoNexStartupConfigSet<U> {
+        &self.layers[layer as usize]
+    }
+
+    /// Replaces the entries of `layer`, every one of them being valid until
+    /// [`skip_invalid`](Self::skip_invalid) is called again.
+    pub fn set_layer(&mut self, layer: StartupConfigLayer, set: NeoNexStartupConfigSet<U>) {
+        self.layers[layer as usize] = set;
+        self.skipped[layer as usize].clear();
+    }
+
+    /// The set seen by the app: the valid entries of every layer, the highest layer winning
+    /// when several hold the same key.
+    pub fn effective(&self) -> NeoNexStartupConfigSet<U> {
+        self.layers
+            .iter()
+            .zip(&self.skipped)
+            .flat_map(|(layer, skipped)| {
+                layer
+                    .iter()
+                    .filter(|entry| !skipped.contains(&entry.key()))
+                    .cloned()
+            })
+            .collect()
+    }
+
+    /// The layer the effective entry with `key` comes from, if any.
+    pub fn source(&self, key: &str) -> Option<StartupConfigLayer> {
+        StartupConfigLayer::ALL.into_iter().rev().find(|layer| {
+            self.layer(*layer).get_by_key(key).is_some()
+                && !self.skipped[*layer as usize].contains(&key)
+        })
+    }
+
+    /// The user layer once the modifications turning the effective set into `effective`
+    /// have been applied to it, i.e. what has to be persisted.
+    pub fn user_layer_for(
+        &self,
+        effective: &NeoNexStartupConfigSet<U>,
+    ) -> NeoNexStartupConfigSet<U> {
+        let mut user = self.layer(StartupConfigLayer::User).clone();
+        for change in self.effective().diff(effective) {
+            match effective.get_by_key(change.key()) {
+                Some(entry) => user.set(entry.clone()),
+                None => user.remove_by_key(change.key()),
+            };
+        }
+        user
+    }
+
+    /// Leaves the invalid entries out of the effective set, so that it falls back to the
+    /// entries of the lower layers, returning why they were left out. The layers keep them:
+    /// e.g. a path that is only missing while its drive is unmounted is still saved. See
+    /// [`NeoNexStartupConfigSet::retain_valid`].
+    ///
+    /// Each layer is checked on its own first. Then an entry of the effective set still
+    /// breaking a cross rule is left out of every layer but the default one.
+    pub fn skip_invalid(&mut self) -> Vec<StartupConfigViolation> {
+        let mut violations = Vec::new();
+        // e.g. when several layers hold the same invalid value
+        let mut report = |violation| {
+            if !violations.contains(&violation) {
+                violations.push(violation);
+            }
+        };
+
+        for (layer, skipped) in self.layers.iter().zip(&mut self.skipped) {
+            *skipped = Vec::new();
+            for violation in layer.clone().retain_valid() {
+                skipped.push(violation.key);
+                report(violation);
+            }
+        }
+        loop {
+            let mut skipped_any = false;
+            for violation in self.effective().validate() {
+                for layer in StartupConfigLayer::System as usize..self.layers.len() {
+                    if self.layers[layer].get_by_key(violation.key).is_some()
+                        && !self.skipped[layer].contains(&violation.key)
+                    {
+                        self.skipped[layer].push(violation.key);
+                        skipped_any = true;
+                    }
+                }
+                report(violation);
+            }
+            if !skipped_any {
+                return violations;
+            }
+        }
+    }
+}
+
+/// An environment variable or command-line flag that isn't a valid startup config entry.
+#[derive(Debug)]
+pub struct StartupConfigOverrideError {
+    /// The variable or flag, e.g. `NEONEX_VOLUME`.
+    pub name: String,
+    pub error: serde_json::Error,
+}
+
+impl core::fmt::Display for StartupConfigOverrideError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "invalid startup config override {}: {}",
+            self.name, self.error
+        )
+    }
+}
+
+impl core::error::Error for StartupConfigOverrideError {}
+
+/// Parses the `NEONEX_*` environment variables into the entries of the environment layer.
+///
+/// The entry key is the rest of the variable name in upper snake case, e.g.
+/// `NEONEX_NATIVE_TERMINAL` for `NativeTerminal`, and the value is JSON, bare strings being
+/// accepted as is. The invalid variables are returned along with the valid entries. The
+/// variables that don't name an entry of the schema, see [`NeoNexStartupConfig::keys`], are
+/// ignored, as they may be meant for another app, like the ones handing secrets, see
+/// [`SECRET_VAR_PREFIX`].
+pub fn parse_env_overrides<U: UserStartupConfig>(
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> (NeoNexStartupConfigSet<U>, Vec<StartupConfigOverrideError>) {
+    let keys = NeoNexStartupConfig::<U>::keys();
+    parse_overrides(vars.into_iter().filter_map(|(name, value)| {
+        if name.starts_with(SECRET_VAR_PREFIX) {
+            return None;
+        }
+        let key = pascal_case(name.strip_prefix("NEONEX_")?, '_');
+        keys.contains(&key.as_str()).then_some((name, key, value))
+    }))
+}
+
+/// Parses the `--neonex-*` command-line flags into the entries of the command-line layer.
+///
+/// The entry key is the rest of the flag in kebab case, e.g. `--neonex-native-terminal` for
+/// `NativeTerminal`, and the value follows an `=`, as for [`parse_env_overrides`]. A flag
+/// without a value sets `true`. The other arguments are ignored.
+pub fn parse_arg_overrides<U: UserStartupConfig>(
+    args: impl IntoIterator<Item = String>,
+) -> (NeoNexStartupConfigSet<U>, Vec<StartupConfigOverrideError>) {
+    parse_overrides(args.into_iter().filter_map(|arg| {
+        let flag = arg.strip_prefix("--neonex-")?;
+        let (flag, value) = flag.split_once('=').unwrap_or((flag, "true"));
+        let name = arg.split_once('=').map_or(arg.as_str(), |(name, _)| name);
+        Some((name.to_string(), pascal_case(flag, '-'), value.to_string()))
+    }))
+}
+
+/// Prefixes the environment variables handing secrets to a launched app, e.g.
+/// `NEONEX_SECRET_LOGIN_TOKEN`. They're read by `neonex_platform::handed_off_secret`, never
+/// as startup config entries.
+pub const SECRET_VAR_PREFIX: &str = "NEONEX_SECRET_";
+
+/// Key of the `NEONEX_NAMESPACE` variable and `--neonex-namespace` flag, which choose the
+/// namespace of the app rather than setting an entry, see [`parse_namespace_override`].
+const NAMESPACE_KEY: &str = "Namespace";
+
+/// The namespace the app has been started in, e.g. by a launcher starting a target with
+/// `--neonex-namespace=game`, overriding `NeoNexConfig::STARTUP_CONFIG_NAMESPACE`. The flag
+/// wins over the `NEONEX_NAMESPACE` variable. An empty flag, `--neonex-namespace=`, is the
+/// global namespace, while an empty variable is ignored.
+///
+/// Returns `None` when the namespace isn't overridden, and `Some(None)` for the global
+/// namespace. See [`NamespacedStartupConfigSet`](crate::namespaces::NamespacedStartupConfigSet).
+pub fn parse_namespace_override(
+    vars: &[(String, String)],
+    args: &[String],
+) -> Option<Option<String>> {
+    let arg = args
+        .iter()
+        .rev()
+        .find_map(|arg| arg.strip_prefix("--neonex-namespace="));
+    let var = || {
+        vars.iter()
+            .rev()
+            .find(|(name, _)| name == "NEONEX_NAMESPACE")
+            .map(|(_, value)| value.as_str())
+            .filter(|namespace| !namespace.is_empty())
+    };
+    arg.or_else(var)
+        .map(|namespace| (!namespace.is_empty()).then(|| namespace.to_string()))
+}
+
+/// The `--neonex-*` flags setting the entries of `startup_config_set`, to be parsed with
+/// [`parse_arg_overrides`] by an app started by this one, e.g. to hand it the merged view of
+/// its namespace.
+pub fn override_args<U: UserStartupConfig>(
+    startup_config_set: &NeoNexStartupConfigSet<U>,
+) -> Vec<String> {
+    startup_config_set
+        .iter()
+        .filter_map(|entry| match serde_json::to_value(entry).ok()? {
+            Value::Object(entry) => Some(entry),
+            _ => None,
+        })
+        .flatten()
+        .map(|(key, value)| format!("--neonex-{}={value}", kebab_case(&key)))
+        .collect()
+}
+
+/// Turns the `neonex.<key>=<value>` parameters of a URL query and fragment into
+/// `--neonex-*` flags, to be parsed with [`parse_arg_overrides`], so that a link can open the
+/// app preconfigured, e.g. `?neonex.native-terminal=false#neonex.volume=8`.
+///
+/// `search` and `hash` are the query and the fragment, with or without their leading `?`
+/// and `#`. The key is the entry key, e.g. `NativeTerminal`, or its kebab case, and the value
+/// is percent-encoded. A parameter without a value sets `true`, and `neonex.namespace` chooses
+/// the namespace, see [`parse_namespace_override`]. The fragment wins over the query.
+///
+/// As anyone can craft a link, only the keys of `allowed` are kept, e.g.
+/// `NeoNexConfig::STARTUP_CONFIG_URL_OVERRIDES`, `Namespace` allowing to choose the namespace.
+/// The other parameters are ignored.
+pub fn url_override_args(search: &str, hash: &str, allowed: &[&str]) -> Vec<String> {
+    let search = search.strip_prefix('?').unwrap_or(search);
+    let hash = hash.strip_prefix('#').unwrap_or(hash);
+    search
+        .split('&')
+        .chain(hash.split('&'))
+        .filter_map(|parameter| {
+            let (name, value) = match parameter.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (parameter, None),
+            };
+            let key = percent_decode(name);
+            let key = kebab_case(key.strip_prefix("neonex.")?);
+            if !allowed.iter().any(|allowed| kebab_case(allowed) == key) {
+                return None;
+            }
+            let mut arg = format!("--neonex-{key}");
+            if let Some(value) = value {
+                arg.push('=');
+                arg.push_str(&percent_decode(value));
+            }
+            Some(arg)
+        })
+        .collect()
+}
+
+/// Decodes a `application/x-www-form-urlencoded` component: `+` is a space, and `%XX` the
+/// byte `XX`. Malformed escapes are kept as they are.
+fn percent_decode(component: &str) -> String {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(core::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[index], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (b'+', None) => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            (byte, None) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// `NativeTerminal` to `native-terminal`, kebab case being kept as is.
+fn kebab_case(key: &str) -> String {
+    let mut kebab = String::with_capacity(key.len());
+    for (index, c) in key.chars().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 && !kebab.ends_with('-') {
+            kebab.push('-');
+        }
+        kebab.push(c.to_ascii_lowercase());
+    }
+    kebab
+}
+
+/// Parses `(name, key, value)` overrides into entries.
+fn parse_overrides<U: UserStartupConfig>(
+    overrides: impl Iterator<Item = (String, String, String)>,
+) -> (NeoNexStartupConfigSet<U>, Vec<StartupConfigOverrideError>) {
+    let mut set = NeoNexStartupConfigSet::default();
+    let mut errors = Vec::new();
+    for (name, key, value) in overrides {
+        if key == NAMESPACE_KEY {
+            continue;
+        }
+        let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+        let entry = Value::Object(Map::from_iter([(key, value)]));
+        match serde_json::from_value::<NeoNexStartupConfig<U>>(entry) {
+            Ok(entry) => {
+                set.set(entry);
+            }
+            Err(error) => errors.push(StartupConfigOverrideError { name, error }),
+        }
+    }
+    (set, errors)
+}
+
+/// `native_terminal` or `NATIVE-TERMINAL` to `NativeTerminal`.
+fn pascal_case(name: &str, separator: char) -> String {
+    name.split(separator)
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|first| first.to_ascii_uppercase());
+            first
+                .into_iter()
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::NativeTerminal;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+    enum TestConfig {
+        #[startup_config(min = 0, max = 10)]
+        Volume(u16),
+    }
+
+    crate::startup_config_key!(Volume => TestConfig::Volume(u16));
+
+    fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn only_variables_of_known_keys_are_parsed() {
+        let (set, errors) = parse_env_overrides::<TestConfig>(vars(&[
+            ("NEONEX_VOLUME", "8"),
+            ("NEONEX_NATIVE_TERMINAL", "false"),
+            ("NEONEX_HOME", "/opt/neonex"),
+            ("NEONEX_SECRET_LOGIN_TOKEN", "hunter2"),
+            ("PATH", "/usr/bin"),
+        ]));
+        assert!(errors.is_empty());
+        assert_eq!(set.get::<Volume>(), Some(&8));
+        assert_eq!(set.get::<NativeTerminal>(), Some(&false));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn invalid_values_of_known_keys_are_reported() {
+        let (set, errors) = parse_env_overrides::<TestConfig>(vars(&[("NEONEX_VOLUME", "-1")]));
+        assert!(set.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].name, "NEONEX_VOLUME");
+    }
+
+    #[test]
+    fn enforced_entries_win_over_every_layer() {
+        let mut layers = LayeredStartupConfigSet::<TestConfig>::default();
+        for (layer, volume) in StartupConfigLayer::ALL.into_iter().zip(1..) {
+            let mut set = NeoNexStartupConfigSet::default();
+            set.set(TestConfig::Volume(volume));
+            layers.set_layer(layer, set);
+        }
+        assert_eq!(layers.effective().get::<Volume>(), Some(&6));
+        assert_eq!(layers.source("Volume"), Some(StartupConfigLayer::Enforced));
+
+        // Modifying an enforced entry only modifies the user layer.
+        let mut effective = layers.effective();
+        effective.set(TestConfig::Volume(0));
+        let user = layers.user_layer_for(&effective);
+        assert_eq!(user.get::<Volume>(), Some(&0));
+        layers.set_layer(StartupConfigLayer::User, user);
+        assert_eq!(layers.effective().get::<Volume>(), Some(&6));
+    }
+
+    #[test]
+    fn invalid_entries_are_only_skipped_in_the_effective_set() {
+        let mut layers = LayeredStartupConfigSet::<TestConfig>::default();
+        let mut default = NeoNexStartupConfigSet::default();
+        default.set(TestConfig::Volume(5));
+        layers.set_layer(StartupConfigLayer::Default, default);
+        let mut user = NeoNexStartupConfigSet::default();
+        user.set(TestConfig::Volume(50));
+        layers.set_layer(StartupConfigLayer::User, user.clone());
+
+        let violations = layers.skip_invalid();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].key, "Volume");
+        assert_eq!(layers.effective().get::<Volume>(), Some(&5));
+        assert_eq!(layers.source("Volume"), Some(StartupConfigLayer::Default));
+        assert_eq!(layers.layer(StartupConfigLayer::User), &user);
+
+        // Saving the effective set keeps the invalid entry, until it's modified.
+        let mut effective = layers.effective();
+        assert_eq!(layers.user_layer_for(&effective), user);
+        effective.set(TestConfig::Volume(7));
+        assert_eq!(layers.user_layer_for(&effective).get::<Volume>(), Some(&7));
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn namespace_flags_win_over_variables() {
+        let env = vars(&[("NEONEX_NAMESPACE", "kiosk")]);
+        assert_eq!(
+            parse_namespace_override(&env, &args(&["--neonex-namespace=game"])),
+            Some(Some("game".to_string()))
+        );
+        assert_eq!(
+            parse_namespace_override(&env, &[]),
+            Some(Some("kiosk".to_string()))
+        );
+        assert_eq!(parse_namespace_override(&[], &[]), None);
+    }
+
+    #[test]
+    fn empty_namespace_flags_are_the_global_namespace() {
+        let env = vars(&[("NEONEX_NAMESPACE", "kiosk")]);
+        assert_eq!(
+            parse_namespace_override(&env, &args(&["--neonex-namespace="])),
+            Some(None)
+        );
+        // Unlike empty variables.
+        let env = vars(&[("NEONEX_NAMESPACE", "")]);
+        assert_eq!(parse_namespace_override(&env, &[]), None);
+    }
+
+    #[test]
+    fn override_args_are_parsed_back() {
+        let mut set = NeoNexStartupConfigSet::<TestConfig>::default();
+        set.set(TestConfig::Volume(8));
+        set.set(NeoNexStartupConfig::NativeTerminal(false));
+
+        let args = override_args(&set);
+        assert!(args.contains(&"--neonex-volume=8".to_string()));
+        assert!(args.contains(&"--neonex-native-terminal=false".to_string()));
+        let (parsed, errors) = parse_arg_overrides::<TestConfig>(args);
+        assert!(errors.is_empty());
+        assert_eq!(parsed, set);
+    }
+
+    const URL_OVERRIDES: &[&str] = &["NativeTerminal", "Volume", "Username", "Namespace"];
+
+    #[test]
+    fn url_values_are_percent_decoded() {
+        assert_eq!(
+            url_override_args(
+                "?neonex.username=Ada%20L+Lovelace%3D%F0%9F%98%80",
+                "",
+                URL_OVERRIDES
+            ),
+            ["--neonex-username=Ada L Lovelace=\u{1F600}"]
+        );
+        // Keys too, and without a value.
+        assert_eq!(
+            url_override_args("", "#neonex%2ENativeTerminal", URL_OVERRIDES),
+            ["--neonex-native-terminal"]
+        );
+    }
+
+    #[test]
+    fn malformed_url_escapes_are_kept() {
+        assert_eq!(
+            url_override_args("neonex.username=100%&neonex.volume=%4", "", URL_OVERRIDES),
+            ["--neonex-username=100%", "--neonex-volume=%4"]
+        );
+        assert_eq!(
+            url_override_args("neonex.username=%zz%E2%82", "", URL_OVERRIDES),
+            ["--neonex-username=%zz\u{FFFD}"]
+        );
+    }
+
+    #[test]
+    fn repeated_url_keys_are_won_by_the_last_one() {
+        let args = url_override_args(
+            "?neonex.volume=2&neonex.volume=4",
+            "#neonex.volume=6",
+            URL_OVERRIDES,
+        );
+        assert_eq!(
+            args,
+            [
+                "--neonex-volume=2",
+                "--neonex-volume=4",
+                "--neonex-volume=6"
+            ]
+        );
+        let (set, errors) = parse_arg_overrides::<TestConfig>(args);
+        assert!(errors.is_empty());
+        assert_eq!(set.get::<Volume>(), Some(&6));
+    }
+
+    #[test]
+    fn only_allowed_neonex_url_parameters_are_kept() {
+        let args = url_override_args(
+            "?utm_source=mail&volume=3&neonex.=1&neonex.volume=5",
+            "#section&neonex.native-terminal=false&neonex.namespace=kiosk",
+            &["Volume"],
+        );
+        assert_eq!(args, ["--neonex-volume=5"]);
+        assert_eq!(parse_namespace_override(&[], &args), None);
+
+        let args = url_override_args("neonex.namespace=kiosk", "", URL_OVERRIDES);
+        assert_eq!(
+            parse_namespace_override(&[], &args),
+            Some(Some("kiosk".to_string()))
+        );
+        assert!(url_override_args("neonex.volume=5", "", &[]).is_empty());
+    }
+}
@@ -1,10 +1,29 @@
 #![no_std]
 
-use core::hash::Hasher;
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+// For the code generated by the derive of `UserStartupConfig`.
+extern crate self as neonex_shared;
 
-use bevy::{ecs::resource::Resource, platform::{collections::HashSet, prelude::String}};
-use serde::{Deserialize, Serialize};
-use core::hash::Hash;
+use core::fmt::Debug;
+
+use alloc::collections::BTreeMap;
+use bevy::{ecs::resource::Resource, platform::prelude::{String, Vec}};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::DeserializeOwned, ser::SerializeStruct};
+use serde_json::Value;
+
+pub use crate::dynamic::DynamicStartupConfigSet;
+pub use crate::keys::{NativeTerminal, StartupConfigEntry, StartupConfigKey};
+pub use neonex_macros::UserStartupConfig;
+
+pub mod dynamic;
+mod keys;
+pub mod layers;
+pub mod migration;
+pub mod namespaces;
+pub mod schema;
+pub mod validation;
 
 /// At launch, before that NeoNex starts its instance, it retrieves a Startup Config,
 /// located differently in each platform (Desktop, Mobile, Web).
@@ -12,31 +31,337 @@ use core::hash::Hash;
 /// Following this Startup Config, the app can be customized even more, while remaining
 /// only one binary, and not requiring a reboot.
 ///
-/// On Desktop and Mobile, this would be saved in a persistent temp file.
+/// On Desktop and Mobile, this would be saved in a file named after the app identifier
+/// (`NeoNexConfig::APP_ID`), in the XDG config dir on Linux and in the temp dir elsewhere.
 /// On Web, this would be saved in a localStorage location, that can be accessed with a key from Rust
 /// (and js if you want for example to do a launcher in HTML/CSS/JS that launches NeoNex with a startup config).
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
-pub struct NeoNexStartupConfigSet {
-    pub values: HashSet<NeoNexStartupConfig>,
+/// Its JSON layout is documented in [`dynamic`], for launchers written in other languages.
+///
+/// The set holds the entries NeoNex needs itself, plus the entries of `U`, the user-defined
+/// startup config chosen with `NeoNexConfig::StartupConfig`. It holds at most one entry per
+/// key: setting an entry replaces the previous one with the same key. Entries are kept sorted
+/// by key, so that they're always persisted in the same order.
+/// ```ignore
+/// set.set(NeoNexStartupConfig::NativeTerminal(true));
+/// set.set(LauncherConfig::Volume(5));
+/// assert_eq!(set.get::<NativeTerminal>(), Some(&true));
+/// *set.entry::<Volume>().or_insert(0) += 1;
+/// ```
+///
+/// Persisted entries that don't match `U` are kept aside, see
+/// [`dynamic`](NeoNexStartupConfigSet::dynamic).
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeoNexStartupConfigSet<U = NoStartupConfig> {
+    values: BTreeMap<&'static str, NeoNexStartupConfig<U>>,
+    dynamic: DynamicStartupConfigSet,
+}
+
+impl<U> Default for NeoNexStartupConfigSet<U> {
+    fn default() -> Self {
+        Self {
+            values: BTreeMap::new(),
+            dynamic: DynamicStartupConfigSet::default(),
+        }
+    }
+}
+
+impl<U: UserStartupConfig> NeoNexStartupConfigSet<U> {
+    /// Value of the entry `K`, if set.
+    pub fn get<K: StartupConfigKey<U>>(&self) -> Option<&K::Value> {
+        self.values.get(K::KEY).and_then(K::value)
+    }
+
+    pub fn get_mut<K: StartupConfigKey<U>>(&mut self) -> Option<&mut K::Value> {
+        self.values.get_mut(K::KEY).and_then(K::value_mut)
+    }
+
+    /// Sets an entry, replacing and returning the previous entry with the same key. A dynamic
+    /// entry with the same key is dropped.
+    pub fn set(&mut self, entry: impl Into<NeoNexStartupConfig<U>>) -> Option<NeoNexStartupConfig<U>> {
+        let entry = entry.into();
+        self.dynamic.remove(entry.key());
+        self.values.insert(entry.key(), entry)
+    }
+
+    /// Removes the entry `K`, returning it if it was set.
+    pub fn remove<K: StartupConfigKey<U>>(&mut self) -> Option<NeoNexStartupConfig<U>> {
+        self.values.remove(K::KEY)
+    }
+
+    /// In-place access to the entry `K`, whether it's set or not.
+    pub fn entry<K: StartupConfigKey<U>>(&mut self) -> StartupConfigEntry<'_, U, K> {
+        StartupConfigEntry::new(self)
+    }
+
+    pub fn contains<K: StartupConfigKey<U>>(&self) -> bool {
+        self.values.contains_key(K::KEY)
+    }
+
+    /// Entry with the given key, for when the key is only known at runtime.
+    pub fn get_by_key(&self, key: &str) -> Option<&NeoNexStartupConfig<U>> {
+        self.values.get(key)
+    }
+
+    /// Removes the entry with the given key, for when the key is only known at runtime.
+    pub fn remove_by_key(&mut self, key: &str) -> Option<NeoNexStartupConfig<U>> {
+        self.values.remove(key)
+    }
+
+    /// All the entries of the set, sorted by key.
+    pub fn iter(&self) -> impl Iterator<Item = &NeoNexStartupConfig<U>> {
+        self.values.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The persisted entries that don't match `U`, by key: entries written by a launcher
+    /// for itself, or with a value the app can't read. They're written back as they are,
+    /// unless an entry with the same key is set.
+    ///
+    /// Only the persisted sets hold them, the startup config set of the app doesn't.
+    pub fn dynamic(&self) -> &DynamicStartupConfigSet {
+        &self.dynamic
+    }
+
+    pub fn dynamic_mut(&mut self) -> &mut DynamicStartupConfigSet {
+        &mut self.dynamic
+    }
+
+    /// Parses a persisted entry, keeping it as a dynamic entry when it doesn't match `U`.
+    pub(crate) fn insert_persisted(&mut self, key: String, value: Value) {
+        let mut entry: serde_json::Result<NeoNexStartupConfig<U>> =
+            serde_json::from_value(dynamic::join_entry(key.clone(), value.clone()));
+        if entry.is_err() && matches!(value, Value::Null | Value::Bool(true)) {
+            entry = serde_json::from_value(Value::String(key.clone()));
+        }
+        match entry {
+            Ok(entry) => {
+                self.set(entry);
+            }
+            Err(_) => {
+                self.dynamic.set(key, value);
+            }
+        }
+    }
+
+    /// Entries that differ between this set and `new`, the removed and modified ones first,
+    /// each sorted by key.
+    pub fn diff(&self, new: &Self) -> Vec<StartupConfigChange<U>> {
+        let mut changes: Vec<_> = self
+            .values
+            .iter()
+            .filter_map(|(key, old)| match new.values.get(key) {
+                None => Some(StartupConfigChange::Removed(old.clone())),
+                Some(new) if new != old => Some(StartupConfigChange::Modified {
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => None,
+            })
+            .collect();
+        changes.extend(
+            new.values
+                .iter()
+                .filter(|(key, _)| !self.values.contains_key(*key))
+                .map(|(_, new)| StartupConfigChange::Added(new.clone())),
+        );
+        changes
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub enum NeoNexStartupConfig {
+/// Difference of one entry between two sets, see [`NeoNexStartupConfigSet::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum StartupConfigChange<U = NoStartupConfig> {
+    Added(NeoNexStartupConfig<U>),
+    Removed(NeoNexStartupConfig<U>),
+    Modified {
+        old: NeoNexStartupConfig<U>,
+        new: NeoNexStartupConfig<U>,
+    },
+}
+
+impl<U: UserStartupConfig> StartupConfigChange<U> {
+    /// Key of the entry that changed.
+    pub fn key(&self) -> &'static str {
+        match self {
+            StartupConfigChange::Added(entry)
+            | StartupConfigChange::Removed(entry)
+            | StartupConfigChange::Modified { new: entry, .. } => entry.key(),
+        }
+    }
+}
+
+impl<U: UserStartupConfig> FromIterator<NeoNexStartupConfig<U>> for NeoNexStartupConfigSet<U> {
+    /// Collects entries into a set, the last entry winning when several share a key.
+    fn from_iter<I: IntoIterator<Item = NeoNexStartupConfig<U>>>(iter: I) -> Self {
+        let mut set = Self::default();
+        for entry in iter {
+            set.set(entry);
+        }
+        set
+    }
+}
+
+// The set is persisted as `{ "values": [entry, ...] }`, just like when it was backed by a
+// `HashSet`, so that files written by older versions can still be read.
+impl<U: UserStartupConfig> Serialize for NeoNexStartupConfigSet<U> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        struct Entries<'a, U>(&'a BTreeMap<&'static str, NeoNexStartupConfig<U>>);
+
+        impl<U: UserStartupConfig> Serialize for Entries<'_, U> {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_seq(self.0.values())
+            }
+        }
+
+        let mut state = serializer.serialize_struct("NeoNexStartupConfigSet", 1)?;
+        state.serialize_field("values", &Entries(&self.values))?;
+        state.end()
+    }
+}
+
+impl<'de, U: UserStartupConfig> Deserialize<'de> for NeoNexStartupConfigSet<U> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(bound = "U: UserStartupConfig")]
+        struct Persisted<U> {
+            values: Vec<NeoNexStartupConfig<U>>,
+        }
+
+        let persisted = Persisted::<U>::deserialize(deserializer)?;
+        Ok(persisted.values.into_iter().collect())
+    }
+}
+
+/// User-defined startup config entries, carried by the [`NeoNexStartupConfigSet`] next to
+/// the entries of NeoNex itself.
+///
+/// This is usually an enum with one variant per setting, each variant being an entry, and
+/// deriving its keys and [`SCHEMA`](Self::SCHEMA) from its variants:
+/// ```ignore
+/// #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+/// enum LauncherConfig {
+///     #[startup_config(min = 0, max = 100)]
+///     Volume(u16),
+///     #[startup_config(label = "User name")]
+///     Username(String),
+/// }
+/// ```
+/// See [the derive](neonex_macros::UserStartupConfig) for its options.
+pub trait UserStartupConfig:
+    Serialize + DeserializeOwned + Debug + Clone + PartialEq + Send + Sync + 'static
+{
+    /// Identifies the entry within a set: a set holds at most one entry per key.
+    /// It must not collide with the keys of the NeoNex entries, like `NativeTerminal`,
+    /// and should be the name of the variant, as keys declared with
+    /// [`startup_config_key!`] are.
+    fn key(&self) -> &'static str;
+
+    /// Fields of the entries that can be edited by the user, see
+    /// [`StartupConfigField`](schema::StartupConfigField). Generated by the derive.
+    const SCHEMA: &'static [schema::StartupConfigField] = &[];
+
+    /// Rules between several entries, see
+    /// [`StartupConfigCrossRule`](validation::StartupConfigCrossRule).
+    const CROSS_RULES: &'static [validation::StartupConfigCrossRule] = &[];
+}
+
+/// User startup config without any entry, for apps only relying on the NeoNex entries.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum NoStartupConfig {}
+
+impl UserStartupConfig for NoStartupConfig {
+    fn key(&self) -> &'static str {
+        match *self {}
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(bound = "U: UserStartupConfig")]
+pub enum NeoNexStartupConfig<U = NoStartupConfig> {
     NativeTerminal(bool),
-    Test1(u16),
-    Bla(String),
+    /// Serialized as-is, so that user entries look just like the NeoNex ones.
+    #[serde(untagged)]
+    User(U),
 }
 
+impl<U: UserStartupConfig> NeoNexStartupConfig<U> {
+    /// Identifies the entry within a set, see [`UserStartupConfig::key`].
+    pub fn key(&self) -> &'static str {
+        match self {
+            NeoNexStartupConfig::NativeTerminal(_) => "NativeTerminal",
+            NeoNexStartupConfig::User(user) => user.key(),
+        }
+    }
+}
 
-impl PartialEq for NeoNexStartupConfig {
-    fn eq(&self, other: &Self) -> bool {
-        core::mem::discriminant(self) == core::mem::discriminant(other)
+impl<U: UserStartupConfig> NeoNexStartupConfig<U> {
+    /// Keys of every entry of the schema, NeoNex ones first, e.g. to tell an unknown key from
+    /// an invalid value. The keys of `U` are the names of its variants, as declared to serde.
+    pub fn keys() -> Vec<&'static str> {
+        let mut keys = Vec::from(["NativeTerminal"]);
+        keys.extend(keys::variant_names::<U>());
+        keys
     }
 }
-impl Eq for NeoNexStartupConfig {}
 
-impl Hash for NeoNexStartupConfig {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        core::mem::discriminant(self).hash(state);
+impl<U: UserStartupConfig> From<U> for NeoNexStartupConfig<U> {
+    fn from(user: U) -> Self {
+        NeoNexStartupConfig::User(user)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum TestConfig {
+        Volume(u16),
+        Name(String),
+        Speed(u8),
+    }
+
+    impl UserStartupConfig for TestConfig {
+        fn key(&self) -> &'static str {
+            match self {
+                TestConfig::Volume(_) => "Volume",
+                TestConfig::Name(_) => "Name",
+                TestConfig::Speed(_) => "Speed",
+            }
+        }
+    }
+
+    #[test]
+    fn entries_are_serialized_sorted_by_key() {
+        let entries = [
+            TestConfig::Volume(5).into(),
+            NeoNexStartupConfig::NativeTerminal(true),
+            TestConfig::Speed(2).into(),
+            TestConfig::Name("a".into()).into(),
+        ];
+        let forward: NeoNexStartupConfigSet<TestConfig> = entries.iter().cloned().collect();
+        let backward: NeoNexStartupConfigSet<TestConfig> = entries.into_iter().rev().collect();
+
+        let json = serde_json::to_string(&forward).unwrap();
+        assert_eq!(
+            json,
+            r#"{"values":[{"Name":"a"},{"NativeTerminal":true},{"Speed":2},{"Volume":5}]}"#
+        );
+        assert_eq!(serde_json::to_string(&backward).unwrap(), json);
+    }
+
+    #[test]
+    fn setting_an_entry_replaces_the_one_with_the_same_key() {
+        let mut set = NeoNexStartupConfigSet::<TestConfig>::default();
+        set.set(TestConfig::Volume(1));
+        assert_eq!(set.set(TestConfig::Volume(2)), Some(TestConfig::Volume(1).into()));
+        assert_eq!(set.len(), 1);
+    }
+}
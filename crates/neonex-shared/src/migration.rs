@@ -0,0 +1,194 @@
+use alloc::collections::BTreeMap;
+use bevy::platform::prelude::{String, ToString, Vec};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::{
+    NeoNexStartupConfigSet, UserStartupConfig,
+    dynamic::{self, PersistedEntries},
+    namespaces::NamespacedStartupConfigSet,
+};
+
+/// Upgrades the persisted entries of a startup config set from one version of its schema
+/// to the next one.
+///
+/// Entries are given as they were persisted (e.g. `{"Volume": 5}`), so they can be renamed,
+/// converted or dropped before being parsed with the current schema.
+pub type StartupConfigMigration = fn(entries: &mut Vec<Value>);
+
+/// Migrations of the entries NeoNex defines itself. The migration at index N upgrades
+/// from version N to version N + 1.
+pub const NEONEX_STARTUP_CONFIG_MIGRATIONS: &[StartupConfigMigration] = &[drop_placeholder_entries];
+
+/// Version 0 came with placeholder entries, which don't exist anymore.
+fn drop_placeholder_entries(entries: &mut Vec<Value>) {
+    entries.retain(|entry| {
+        !matches!(entry, Value::Object(object) if object.contains_key("Test1") || object.contains_key("Bla"))
+    });
+}
+
+/// A startup config store as it's persisted: its entries, along with the versions of the
+/// schemas they were written with. Serialized with the layout of [`dynamic`](crate::dynamic).
+///
+/// Files without versions, written before the schemas were versioned or by a launcher that
+/// didn't set them, are read as version 0 and go through every migration.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "StoredStartupConfig")]
+pub struct PersistedStartupConfig {
+    /// Version of the NeoNex entries, see [`NEONEX_STARTUP_CONFIG_MIGRATIONS`].
+    pub neonex_version: u32,
+    /// Version of the user-defined entries, i.e. the number of migrations of the user
+    /// schema known by the app that wrote them.
+    pub version: u32,
+    /// Entries of the global namespace, as `{ "Key": value }` objects.
+    #[serde(serialize_with = "dynamic::serialize_keyed")]
+    pub values: Vec<Value>,
+    /// Entries of the other namespaces, by name. Files written before namespaces existed
+    /// only have global entries.
+    #[serde(serialize_with = "dynamic::serialize_keyed_namespaces")]
+    pub namespaces: BTreeMap<String, Vec<Value>>,
+}
+
+/// [`PersistedStartupConfig`] as stored, whatever its layout.
+#[derive(Deserialize)]
+struct StoredStartupConfig {
+    #[serde(default, deserialize_with = "stored_version")]
+    neonex_version: Option<u32>,
+    #[serde(default, deserialize_with = "stored_version")]
+    version: Option<u32>,
+    #[serde(default)]
+    values: PersistedEntries,
+    #[serde(default)]
+    namespaces: BTreeMap<String, PersistedEntries>,
+}
+
+/// A version that is present, written as a plain number in every format.
+fn stored_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    u32::deserialize(deserializer).map(Some)
+}
+
+impl From<StoredStartupConfig> for PersistedStartupConfig {
+    fn from(stored: StoredStartupConfig) -> Self {
+        Self {
+            neonex_version: stored.neonex_version.unwrap_or(0),
+            version: stored.version.unwrap_or(0),
+            values: stored.values.into_entries(),
+            namespaces: stored
+                .namespaces
+                .into_iter()
+                .map(|(name, entries)| (name, entries.into_entries()))
+                .collect(),
+        }
+    }
+}
+
+/// Why persisted entries couldn't be turned back into a set.
+#[derive(Debug)]
+pub enum MigrationError {
+    /// The entries were written by a newer version of the app, which can't be downgraded.
+    TooNew { found: u32, supported: u32 },
+}
+
+impl core::fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            MigrationError::TooNew { found, supported } => write!(
+                f,
+                "startup config schema version {found} is newer than the supported version {supported}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for MigrationError {}
+
+impl PersistedStartupConfig {
+    /// Prepares a store to be persisted, at the latest versions of both schemas. The dynamic
+    /// entries of its sets are persisted too, unless an entry with the same key is set.
+    pub fn from_set<U: UserStartupConfig>(
+        store: &NamespacedStartupConfigSet<U>,
+        user_migrations: &[StartupConfigMigration],
+    ) -> serde_json::Result<Self> {
+        let values = |set: &NeoNexStartupConfigSet<U>| {
+            let mut values = set
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<serde_json::Result<Vec<_>>>()?;
+            values.extend(
+                set.dynamic()
+                    .iter()
+                    .filter(|(key, _)| set.get_by_key(key).is_none())
+                    .map(|(key, value)| dynamic::join_entry(key.to_string(), value.clone())),
+            );
+            Ok::<_, serde_json::Error>(values)
+        };
+
+        Ok(Self {
+            neonex_version: NEONEX_STARTUP_CONFIG_MIGRATIONS.len() as u32,
+            version: user_migrations.len() as u32,
+            values: values(store.global())?,
+            namespaces: store
+                .namespaces()
+                .map(|(name, set)| Ok((name.to_string(), values(set)?)))
+                .collect::<serde_json::Result<_>>()?,
+        })
+    }
+
+    /// Upgrades the entries of every namespace to the latest versions of both schemas, then
+    /// parses them.
+    ///
+    /// The entries that still don't match the current schema are kept aside as dynamic
+    /// entries, see [`NeoNexStartupConfigSet::dynamic`], so that each of them falls back to
+    /// its default rather than the whole set.
+    pub fn into_set<U: UserStartupConfig>(
+        self,
+        user_migrations: &[StartupConfigMigration],
+    ) -> Result<NamespacedStartupConfigSet<U>, MigrationError> {
+        let upgrade = |mut values: Vec<Value>| {
+            migrate(
+                &mut values,
+                self.neonex_version,
+                NEONEX_STARTUP_CONFIG_MIGRATIONS,
+            )?;
+            migrate(&mut values, self.version, user_migrations)?;
+            let mut set = NeoNexStartupConfigSet::default();
+            for (key, value) in values.into_iter().filter_map(dynamic::split_entry) {
+                set.insert_persisted(key, value);
+            }
+            Ok::<_, MigrationError>(set)
+        };
+
+        let mut store = NamespacedStartupConfigSet::from(upgrade(self.values)?);
+        for (name, values) in self.namespaces {
+            *store.get_mut(Some(&name)) = upgrade(values)?;
+        }
+        Ok(store)
+    }
+
+    /// Every persisted entry, whatever its namespace.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.values
+            .iter_mut()
+            .chain(self.namespaces.values_mut().flatten())
+    }
+}
+
+/// Runs the `migrations` needed to upgrade `entries` from `version` to the latest version.
+fn migrate(
+    entries: &mut Vec<Value>,
+    version: u32,
+    migrations: &[StartupConfigMigration],
+) -> Result<(), MigrationError> {
+    let pending = migrations
+        .get(version as usize..)
+        .ok_or(MigrationError::TooNew {
+            found: version,
+            supported: migrations.len() as u32,
+        })?;
+
+    for migration in pending {
+        migration(entries);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use alloc::collections::BTreeMap;
+use bevy::platform::prelude::{String, ToString};
+
+use crate::{NeoNexStartupConfigSet, NoStartupConfig, StartupConfigChange, UserStartupConfig};
+
+/// The persisted startup config, split into namespaces: the global one, shared by every app
+/// of the installation, and one per launch target or profile, e.g. `"game"` or `"kiosk"`.
+///
+/// An app sees the global entries, overridden by the ones of its namespace, see
+/// [`merged`](NamespacedStartupConfigSet::merged). Namespaces are addressed with an
+/// `Option<&str>`, `None` being the global namespace:
+/// ```ignore
+/// store.global_mut().set(LauncherConfig::Volume(5));
+/// store.get_mut(Some("game")).set(LauncherConfig::Volume(8));
+/// assert_eq!(store.merged(Some("game")).get::<Volume>(), Some(&8));
+/// assert_eq!(store.merged(Some("editor")).get::<Volume>(), Some(&5));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamespacedStartupConfigSet<U = NoStartupConfig> {
+    global: NeoNexStartupConfigSet<U>,
+    namespaces: BTreeMap<String, NeoNexStartupConfigSet<U>>,
+}
+
+impl<U> Default for NamespacedStartupConfigSet<U> {
+    fn default() -> Self {
+        Self {
+            global: NeoNexStartupConfigSet::default(),
+            namespaces: BTreeMap::new(),
+        }
+    }
+}
+
+impl<U: UserStartupConfig> NamespacedStartupConfigSet<U> {
+    pub fn global(&self) -> &NeoNexStartupConfigSet<U> {
+        &self.global
+    }
+
+    pub fn global_mut(&mut self) -> &mut NeoNexStartupConfigSet<U> {
+        &mut self.global
+    }
+
+    /// Entries of `namespace`, if it has any.
+    pub fn get(&self, namespace: Option<&str>) -> Option<&NeoNexStartupConfigSet<U>> {
+        match namespace {
+            None => Some(&self.global),
+            Some(name) => self.namespaces.get(name),
+        }
+    }
+
+    /// Entries of `namespace`, creating it when needed.
+    pub fn get_mut(&mut self, namespace: Option<&str>) -> &mut NeoNexStartupConfigSet<U> {
+        match namespace {
+            None => &mut self.global,
+            Some(name) => self.namespaces.entry(name.to_string()).or_default(),
+        }
+    }
+
+    /// Removes a namespace along with its entries, returning them.
+    pub fn remove_namespace(&mut self, name: &str) -> Option<NeoNexStartupConfigSet<U>> {
+        self.namespaces.remove(name)
+    }
+
+    /// The namespaces other than the global one, by name.
+    pub fn namespaces(&self) -> impl Iterator<Item = (&str, &NeoNexStartupConfigSet<U>)> {
+        self.namespaces
+            .iter()
+            .map(|(name, set)| (name.as_str(), set))
+    }
+
+    /// The set seen by an app of `namespace`: the global entries, overridden by the ones of
+    /// the namespace.
+    pub fn merged(&self, namespace: Option<&str>) -> NeoNexStartupConfigSet<U> {
+        let mut merged = self.global.clone();
+        if let Some(set) = namespace.and_then(|name| self.namespaces.get(name)) {
+            for entry in set.iter() {
+                merged.set(entry.clone());
+            }
+            for (key, value) in set.dynamic().iter() {
+                merged.dynamic_mut().set(key, value.clone());
+            }
+        }
+        merged
+    }
+
+    /// Applies modifications of the [`merged`](NamespacedStartupConfigSet::merged) set of
+    /// `namespace` to the namespace itself.
+    ///
+    /// Removing a global entry from another namespace only removes the entry of that
+    /// namespace, the global one still being seen.
+    pub fn apply(&mut self, namespace: Option<&str>, changes: &[StartupConfigChange<U>]) {
+        let set = self.get_mut(namespace);
+        for change in changes {
+            match change {
+                StartupConfigChange::Added(entry)
+                | StartupConfigChange::Modified { new: entry, .. } => {
+                    set.set(entry.clone());
+                }
+                StartupConfigChange::Removed(entry) => {
+                    set.remove_by_key(entry.key());
+                }
+            }
+        }
+        if let Some(name) = namespace
+            && self
+                .namespaces
+                .get(name)
+                .is_some_and(|set| set.is_empty() && set.dynamic().is_empty())
+        {
+            self.namespaces.remove(name);
+        }
+    }
+}
+
+impl<U> From<NeoNexStartupConfigSet<U>> for NamespacedStartupConfigSet<U> {
+    /// A store only holding global entries.
+    fn from(global: NeoNexStartupConfigSet<U>) -> Self {
+        Self {
+            global,
+            namespaces: BTreeMap::new(),
+        }
+    }
+}
@@ -0,0 +1,722 @@
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use serde_json::{Map, Value, json};
+
+use crate::{
+    UserStartupConfig,
+    validation::{StartupConfigCrossRule, StartupConfigRule},
+};
+
+/// Describes an entry of the startup config, so that it can be edited without knowing its
+/// type, e.g. by the settings screen of `neonex-core`. Generated from the variants of the
+/// entries by the derive of [`UserStartupConfig`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StartupConfigField {
+    /// Key of the entry, see [`UserStartupConfig::key`](crate::UserStartupConfig::key).
+    pub key: &'static str,
+    /// Shown to the user.
+    pub label: &'static str,
+    pub kind: StartupConfigFieldKind,
+    /// Checked on top of the kind, see [`StartupConfigRule`].
+    pub rules: &'static [StartupConfigRule],
+}
+
+impl StartupConfigField {
+    pub const fn new(key: &'static str, label: &'static str, kind: StartupConfigFieldKind) -> Self {
+        Self {
+            key,
+            label,
+            kind,
+            rules: &[],
+        }
+    }
+
+    pub const fn with_rules(mut self, rules: &'static [StartupConfigRule]) -> Self {
+        self.rules = rules;
+        self
+    }
+}
+
+/// The value held by an entry, i.e. what the variant of the entry wraps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartupConfigFieldKind {
+    /// A `bool`.
+    Toggle,
+    /// An integer, from `min` to `max` included.
+    Integer { min: i64, max: i64 },
+    /// A float, from `min` to `max` included, changed by `step` at a time.
+    Float { min: f64, max: f64, step: f64 },
+    /// A `String`.
+    Text,
+    /// One of `options`, e.g. the names of the unit variants of an enum.
+    Choice(&'static [&'static str]),
+}
+
+/// Fields of the entries NeoNex defines itself.
+pub const NEONEX_STARTUP_CONFIG_SCHEMA: &[StartupConfigField] = &[StartupConfigField::new(
+    "NativeTerminal",
+    "Native terminal",
+    StartupConfigFieldKind::Toggle,
+)];
+
+/// JSON Schema (draft 2020-12) of the startup config of `U`, as persisted with the JSON
+/// layout of [`dynamic`](crate::dynamic). Meant to be shipped along with apps written in other
+/// languages, e.g. to generate their bindings or to check what a launcher hands them.
+///
+/// Each entry is described by the type its variant wraps, as traced through serde, so that
+/// the variants without a field, e.g. skipped or unit ones, are described too. The bounds,
+/// labels and rules of the fields of [`UserStartupConfig::SCHEMA`] are added on top.
+///
+/// Entries without a variant are allowed, with any value. [`ExistingPath`](StartupConfigRule::ExistingPath)
+/// and [`NotGreater`](StartupConfigCrossRule::NotGreater) can't be expressed, and are left out.
+pub fn startup_config_json_schema<U: UserStartupConfig>() -> Value {
+    let entries = [("NativeTerminal", trace::trace_type::<bool>())]
+        .into_iter()
+        .chain(trace::trace_variants::<U>());
+    let properties: Map<String, Value> = entries
+        .map(|(key, mut schema)| {
+            let field = NEONEX_STARTUP_CONFIG_SCHEMA
+                .iter()
+                .chain(U::SCHEMA)
+                .find(|field| field.key == key);
+            if let (Some(field), Value::Object(schema)) = (field, &mut schema)
+                && let Value::Object(field) = field_json_schema(field)
+            {
+                schema.extend(field);
+            }
+            (key.to_string(), schema)
+        })
+        .collect();
+    let cross_rules: Vec<Value> = U::CROSS_RULES
+        .iter()
+        .filter_map(cross_rule_json_schema)
+        .collect();
+
+    let mut values = json!({ "type": "object", "properties": properties });
+    if !cross_rules.is_empty() {
+        values["allOf"] = Value::Array(cross_rules);
+    }
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "NeoNex startup config",
+        "type": "object",
+        "properties": {
+            "neonex_version": { "type": "integer", "minimum": 0 },
+            "version": { "type": "integer", "minimum": 0 },
+            "values": { "$ref": "#/$defs/values" },
+            "namespaces": {
+                "type": "object",
+                "additionalProperties": { "$ref": "#/$defs/values" },
+            },
+        },
+        "$defs": { "values": values },
+    })
+}
+
+fn field_json_schema(field: &StartupConfigField) -> Value {
+    let mut schema = match field.kind {
+        StartupConfigFieldKind::Toggle => json!({ "type": "boolean" }),
+        StartupConfigFieldKind::Integer { min, max } => {
+            json!({ "type": "integer", "minimum": min, "maximum": max })
+        }
+        StartupConfigFieldKind::Float { min, max, .. } => {
+            json!({ "type": "number", "minimum": min, "maximum": max })
+        }
+        StartupConfigFieldKind::Text => json!({ "type": "string" }),
+        StartupConfigFieldKind::Choice(options) => json!({ "enum": options }),
+    };
+    schema["title"] = Value::from(field.label);
+
+    // Each rule is a subschema of its own, so that it doesn't replace the bounds of the kind.
+    let rules: Vec<Value> = field
+        .rules
+        .iter()
+        .filter_map(|rule| match *rule {
+            StartupConfigRule::Range { min, max } => {
+                Some(json!({ "minimum": min, "maximum": max }))
+            }
+            StartupConfigRule::Length { min, max } => Some(json!({
+                "minLength": min,
+                "maxLength": max,
+                "minItems": min,
+                "maxItems": max,
+            })),
+            StartupConfigRule::Pattern(pattern) => {
+                Some(json!({ "pattern": format!("^(?:{pattern})$") }))
+            }
+            StartupConfigRule::ExistingPath => None,
+        })
+        .collect();
+    if !rules.is_empty() {
+        schema["allOf"] = Value::Array(rules);
+    }
+    schema
+}
+
+fn cross_rule_json_schema(rule: &StartupConfigCrossRule) -> Option<Value> {
+    match *rule {
+        StartupConfigCrossRule::Requires { key, requires } => Some(json!({
+            "if": {
+                "required": [key],
+                "properties": { key: { "not": { "const": false } } },
+            },
+            "then": { "required": [requires] },
+        })),
+        StartupConfigCrossRule::NotGreater { .. } => None,
+    }
+}
+
+/// JSON Schemas of types, traced by deserializing them from placeholder values.
+mod trace {
+    use bevy::platform::prelude::{String, ToString, Vec};
+    use serde::de::{
+        self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer,
+        MapAccess, SeqAccess, VariantAccess, Visitor, value::Error,
+    };
+    use serde_json::{Map, Value, json};
+
+    use crate::{UserStartupConfig, keys::variant_names};
+
+    /// The JSON Schema of the values of `T`, or `{}` when it can't be traced, e.g. when
+    /// `T` deserializes anything.
+    pub(super) fn trace_type<T: DeserializeOwned>() -> Value {
+        let mut schema = json!({});
+        match T::deserialize(Tracer(&mut schema)) {
+            Ok(_) => schema,
+            Err(_) => json!({}),
+        }
+    }
+
+    /// The JSON Schema of the value of each variant of `U`, by key. Variants without a value
+    /// are persisted as `true`, see [`dynamic`](crate::dynamic).
+    pub(super) fn trace_variants<U: UserStartupConfig>() -> Vec<(&'static str, Value)> {
+        variant_names::<U>()
+            .iter()
+            .map(|&variant| {
+                let mut schema = json!({});
+                let traced = U::deserialize(VariantTracer {
+                    variant,
+                    schema: &mut schema,
+                });
+                (variant, if traced.is_ok() { schema } else { json!({}) })
+            })
+            .collect()
+    }
+
+    /// Deserializes the variant named `variant` of an enum, tracing what it wraps.
+    struct VariantTracer<'a> {
+        variant: &'static str,
+        schema: &'a mut Value,
+    }
+
+    impl<'de> Deserializer<'de> for VariantTracer<'_> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+            Err(de::Error::custom("not an enum"))
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            visitor.visit_enum(self)
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+            byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map struct
+            identifier ignored_any
+        }
+    }
+
+    impl<'de, 'a> EnumAccess<'de> for VariantTracer<'a> {
+        type Error = Error;
+        type Variant = Self;
+
+        fn variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self), Error> {
+            let variant = seed.deserialize(self.variant.into_deserializer())?;
+            Ok((variant, self))
+        }
+    }
+
+    impl<'de> VariantAccess<'de> for VariantTracer<'_> {
+        type Error = Error;
+
+        fn unit_variant(self) -> Result<(), Error> {
+            *self.schema = json!({ "const": true });
+            Ok(())
+        }
+
+        fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Error> {
+            seed.deserialize(Tracer(self.schema))
+        }
+
+        fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+            Tracer(self.schema).deserialize_tuple(len, visitor)
+        }
+
+        fn struct_variant<V: Visitor<'de>>(
+            self,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            Tracer(self.schema).deserialize_struct("", fields, visitor)
+        }
+    }
+
+    /// Deserializes placeholder values, setting the JSON Schema of the type asking for them.
+    struct Tracer<'a>(&'a mut Value);
+
+    macro_rules! trace_integers {
+        ($($method:ident => $visit:ident($ty:ty)),* $(,)?) => {$(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                *self.0 = json!({ "type": "integer", "minimum": <$ty>::MIN, "maximum": <$ty>::MAX });
+                visitor.$visit(0)
+            }
+        )*};
+    }
+
+    impl<'de> Deserializer<'de> for Tracer<'_> {
+        type Error = Error;
+
+        fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({});
+            visitor.visit_unit()
+        }
+
+        fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "boolean" });
+            visitor.visit_bool(false)
+        }
+
+        trace_integers! {
+            deserialize_i8 => visit_i8(i8),
+            deserialize_i16 => visit_i16(i16),
+            deserialize_i32 => visit_i32(i32),
+            deserialize_i64 => visit_i64(i64),
+            deserialize_u8 => visit_u8(u8),
+            deserialize_u16 => visit_u16(u16),
+            deserialize_u32 => visit_u32(u32),
+            deserialize_u64 => visit_u64(u64),
+        }
+
+        /// Unbounded, as JSON numbers can't hold the bounds.
+        fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "integer" });
+            visitor.visit_i128(0)
+        }
+
+        fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "integer", "minimum": 0 });
+            visitor.visit_u128(0)
+        }
+
+        fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "number" });
+            visitor.visit_f32(0.0)
+        }
+
+        fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "number" });
+            visitor.visit_f64(0.0)
+        }
+
+        fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "string", "minLength": 1, "maxLength": 1 });
+            visitor.visit_char(' ')
+        }
+
+        fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "string" });
+            visitor.visit_str("")
+        }
+
+        fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.deserialize_str(visitor)
+        }
+
+        fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({
+                "type": "array",
+                "items": { "type": "integer", "minimum": 0, "maximum": 255 },
+            });
+            visitor.visit_bytes(&[])
+        }
+
+        fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            self.deserialize_bytes(visitor)
+        }
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let mut some = json!({});
+            let value = visitor.visit_some(Tracer(&mut some))?;
+            *self.0 = json!({ "anyOf": [some, { "type": "null" }] });
+            Ok(value)
+        }
+
+        fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            *self.0 = json!({ "type": "null" });
+            visitor.visit_unit()
+        }
+
+        fn deserialize_unit_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            self.deserialize_unit(visitor)
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            visitor.visit_newtype_struct(self)
+        }
+
+        /// Traces a single item, standing for all of them.
+        fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let mut items = Vec::new();
+            let value = visitor.visit_seq(SeqTracer {
+                schemas: &mut items,
+                remaining: 1,
+            })?;
+            let items = items.pop().unwrap_or_else(|| json!({}));
+            *self.0 = json!({ "type": "array", "items": items });
+            Ok(value)
+        }
+
+        fn deserialize_tuple<V: Visitor<'de>>(
+            self,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            let mut items = Vec::new();
+            let value = visitor.visit_seq(SeqTracer {
+                schemas: &mut items,
+                remaining: len,
+            })?;
+            *self.0 = json!({
+                "type": "array",
+                "prefixItems": items,
+                "minItems": len,
+                "maxItems": len,
+            });
+            Ok(value)
+        }
+
+        fn deserialize_tuple_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            self.deserialize_tuple(len, visitor)
+        }
+
+        /// Traces a single value, standing for all of them. Keys are strings in JSON.
+        fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+            let mut values = Vec::new();
+            let value = visitor.visit_map(MapTracer {
+                keys: &["key"],
+                schemas: &mut values,
+                traced_keys: true,
+            })?;
+            let values = values.pop().map_or_else(|| json!({}), |(_, schema)| schema);
+            *self.0 = json!({ "type": "object", "additionalProperties": values });
+            Ok(value)
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            let mut properties = Vec::new();
+            let value = visitor.visit_map(MapTracer {
+                keys: fields,
+                schemas: &mut properties,
+                traced_keys: false,
+            })?;
+            let properties: Map<String, Value> = properties
+                .into_iter()
+                .map(|(key, schema)| (key.to_string(), schema))
+                .collect();
+            *self.0 = json!({ "type": "object", "properties": properties });
+            Ok(value)
+        }
+
+        /// Only the names of the variants are known, as a single one can be traced.
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Error> {
+            let variant = variants.first().ok_or(de::Error::custom("empty enum"))?;
+            let value = visitor.visit_enum(VariantTracer {
+                variant,
+                schema: &mut json!({}),
+            })?;
+            *self.0 = json!({
+                "anyOf": [
+                    { "enum": variants },
+                    {
+                        "type": "object",
+                        "propertyNames": { "enum": variants },
+                        "minProperties": 1,
+                        "maxProperties": 1,
+                    },
+                ],
+            });
+            Ok(value)
+        }
+
+        serde::forward_to_deserialize_any! { identifier ignored_any }
+    }
+
+    /// Gives `remaining` placeholder items, tracing each of them.
+    struct SeqTracer<'a> {
+        schemas: &'a mut Vec<Value>,
+        remaining: usize,
+    }
+
+    impl<'de> SeqAccess<'de> for SeqTracer<'_> {
+        type Error = Error;
+
+        fn next_element_seed<S: DeserializeSeed<'de>>(
+            &mut self,
+            seed: S,
+        ) -> Result<Option<S::Value>, Error> {
+            if self.remaining == 0 {
+                return Ok(None);
+            }
+            self.remaining -= 1;
+            let mut schema = json!({});
+            let element = seed.deserialize(Tracer(&mut schema))?;
+            self.schemas.push(schema);
+            Ok(Some(element))
+        }
+    }
+
+    /// Gives a placeholder value for each of `keys`, tracing each of them. With
+    /// `traced_keys`, the keys are placeholders too.
+    struct MapTracer<'a> {
+        keys: &'static [&'static str],
+        schemas: &'a mut Vec<(&'static str, Value)>,
+        traced_keys: bool,
+    }
+
+    impl<'de> MapAccess<'de> for MapTracer<'_> {
+        type Error = Error;
+
+        fn next_key_seed<S: DeserializeSeed<'de>>(
+            &mut self,
+            seed: S,
+        ) -> Result<Option<S::Value>, Error> {
+            let Some((key, keys)) = self.keys.split_first() else {
+                return Ok(None);
+            };
+            self.keys = keys;
+            self.schemas.push((key, json!({})));
+            match self.traced_keys {
+                true => seed.deserialize(Tracer(&mut json!({}))).map(Some),
+                false => seed.deserialize(key.into_deserializer()).map(Some),
+            }
+        }
+
+        fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Error> {
+            let (_, schema) = self
+                .schemas
+                .last_mut()
+                .ok_or(de::Error::custom("value without a key"))?;
+            seed.deserialize(Tracer(schema))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    enum Theme {
+        Dark,
+        Light,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+    #[startup_config(cross_rules = &[StartupConfigCrossRule::Requires { key: "AutoLogin", requires: "Username" }])]
+    enum TestConfig {
+        AutoLogin(bool),
+        Volume(u8),
+        #[startup_config(min = -10, max = 10)]
+        Offset(i32),
+        #[startup_config(min = 0.5, max = 2.5)]
+        Scale(f32),
+        #[startup_config(label = "User name", rules = &[StartupConfigRule::Length { min: 1, max: 8 }])]
+        Username(String),
+        #[startup_config(options = &["Dark", "Light"])]
+        Theme(Theme),
+        #[startup_config(skip)]
+        LastTheme(Theme),
+        Reset,
+    }
+
+    #[test]
+    fn keys_are_derived_from_the_variants() {
+        assert_eq!(TestConfig::Volume(5).key(), "Volume");
+        assert_eq!(TestConfig::LastTheme(Theme::Light).key(), "LastTheme");
+        assert_eq!(TestConfig::Reset.key(), "Reset");
+    }
+
+    #[test]
+    fn schema_is_derived_from_the_variants() {
+        assert_eq!(
+            TestConfig::SCHEMA,
+            &[
+                StartupConfigField::new("AutoLogin", "Auto login", StartupConfigFieldKind::Toggle),
+                StartupConfigField::new(
+                    "Volume",
+                    "Volume",
+                    StartupConfigFieldKind::Integer { min: 0, max: 255 }
+                ),
+                StartupConfigField::new(
+                    "Offset",
+                    "Offset",
+                    StartupConfigFieldKind::Integer { min: -10, max: 10 }
+                ),
+                StartupConfigField::new(
+                    "Scale",
+                    "Scale",
+                    StartupConfigFieldKind::Float {
+                        min: 0.5,
+                        max: 2.5,
+                        step: 0.02
+                    }
+                ),
+                StartupConfigField::new("Username", "User name", StartupConfigFieldKind::Text)
+                    .with_rules(&[StartupConfigRule::Length { min: 1, max: 8 }]),
+                StartupConfigField::new(
+                    "Theme",
+                    "Theme",
+                    StartupConfigFieldKind::Choice(&["Dark", "Light"])
+                ),
+            ]
+        );
+        assert_eq!(
+            TestConfig::CROSS_RULES,
+            &[StartupConfigCrossRule::Requires {
+                key: "AutoLogin",
+                requires: "Username"
+            }]
+        );
+    }
+
+    #[test]
+    fn wide_integers_are_bounded_by_i64() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+        enum WideConfig {
+            Size(u64),
+        }
+
+        assert_eq!(
+            WideConfig::SCHEMA[0].kind,
+            StartupConfigFieldKind::Integer {
+                min: 0,
+                max: i64::MAX
+            }
+        );
+    }
+
+    #[test]
+    fn json_schema_is_traced_from_the_variants() {
+        let schema = startup_config_json_schema::<TestConfig>();
+        let properties = &schema["$defs"]["values"]["properties"];
+
+        assert_eq!(
+            properties["NativeTerminal"],
+            json!({ "type": "boolean", "title": "Native terminal" })
+        );
+        assert_eq!(
+            properties["Offset"],
+            json!({ "type": "integer", "minimum": -10, "maximum": 10, "title": "Offset" })
+        );
+        assert_eq!(
+            properties["Username"],
+            json!({
+                "type": "string",
+                "title": "User name",
+                "allOf": [{ "minLength": 1, "maxLength": 8, "minItems": 1, "maxItems": 8 }],
+            })
+        );
+        assert_eq!(properties["Theme"]["enum"], json!(["Dark", "Light"]));
+        // Variants without a field are described by their type only.
+        assert_eq!(
+            properties["LastTheme"]["anyOf"][0],
+            json!({ "enum": ["Dark", "Light"] })
+        );
+        assert_eq!(properties["Reset"], json!({ "const": true }));
+        assert_eq!(
+            schema["$defs"]["values"]["allOf"][0]["then"],
+            json!({ "required": ["Username"] })
+        );
+    }
+
+    #[test]
+    fn nested_types_are_traced() {
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+        struct Window {
+            width: u16,
+            title: Option<String>,
+        }
+
+        #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+        enum NestedConfig {
+            #[startup_config(skip)]
+            Window(Window),
+            #[startup_config(skip)]
+            Recent(Vec<String>),
+            Size(u16, u16),
+        }
+
+        let properties = trace::trace_variants::<NestedConfig>();
+        assert_eq!(
+            properties,
+            [
+                (
+                    "Window",
+                    json!({
+                        "type": "object",
+                        "properties": {
+                            "width": { "type": "integer", "minimum": 0, "maximum": 65535 },
+                            "title": { "anyOf": [{ "type": "string" }, { "type": "null" }] },
+                        },
+                    })
+                ),
+                (
+                    "Recent",
+                    json!({ "type": "array", "items": { "type": "string" } })
+                ),
+                (
+                    "Size",
+                    json!({
+                        "type": "array",
+                        "prefixItems": [
+                            { "type": "integer", "minimum": 0, "maximum": 65535 },
+                            { "type": "integer", "minimum": 0, "maximum": 65535 },
+                        ],
+                        "minItems": 2,
+                        "maxItems": 2,
+                    })
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Declarative validation of the startup config entries.
+//!
+//! Each field of the schema is checked against its kind, e.g. the range of an
+//! [`Integer`](StartupConfigFieldKind::Integer), and against its own
+//! [`rules`](StartupConfigField::rules). The rules involving several entries are given with
+//! `UserStartupConfig::CROSS_RULES`, both given to the derive of `UserStartupConfig`:
+//! ```ignore
+//! #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, UserStartupConfig)]
+//! #[startup_config(cross_rules = &[StartupConfigCrossRule::NotGreater { lower: "MinVolume", upper: "MaxVolume" }])]
+//! enum LauncherConfig {
+//!     #[startup_config(label = "User name", rules = &[StartupConfigRule::Pattern("[a-z_][a-z0-9_]*")])]
+//!     Username(String),
+//!     #[startup_config(label = "Games directory", rules = &[StartupConfigRule::ExistingPath])]
+//!     GamesDir(String),
+//!     MinVolume(u8),
+//!     MaxVolume(u8),
+//! }
+//! ```
+//!
+//! [`Pattern`](StartupConfigRule::Pattern) and [`ExistingPath`](StartupConfigRule::ExistingPath)
+//! need the `std` feature, and always pass without it.
+
+use bevy::platform::prelude::{String, ToString, Vec, format};
+use serde_json::Value;
+
+use crate::{
+    NeoNexStartupConfig, NeoNexStartupConfigSet, UserStartupConfig,
+    schema::{NEONEX_STARTUP_CONFIG_SCHEMA, StartupConfigField, StartupConfigFieldKind},
+};
+
+/// A rule the value of a single entry must follow, on top of the kind of its field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartupConfigRule {
+    /// A number from `min` to `max` included.
+    Range { min: f64, max: f64 },
+    /// A string of `min` to `max` characters, or a list of `min` to `max` items.
+    Length { min: usize, max: usize },
+    /// A string entirely matched by the regex.
+    Pattern(&'static str),
+    /// A string naming an existing file or directory.
+    ExistingPath,
+}
+
+/// A rule between several entries, only checked when the entries are set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartupConfigCrossRule {
+    /// When `key` is set to anything but `false`, `requires` has to be set too.
+    Requires {
+        key: &'static str,
+        requires: &'static str,
+    },
+    /// The number of `lower` can't be greater than the one of `upper`.
+    NotGreater {
+        lower: &'static str,
+        upper: &'static str,
+    },
+}
+
+/// An entry breaking a rule, meant to be shown next to its field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartupConfigViolation {
+    /// Key of the entry, see [`UserStartupConfig::key`].
+    pub key: &'static str,
+    /// What the value should be, e.g. `must be between 0 and 100`.
+    pub message: String,
+}
+
+impl core::fmt::Display for StartupConfigViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}: {}", self.key, self.message)
+    }
+}
+
+impl core::error::Error for StartupConfigViolation {}
+
+impl<U: UserStartupConfig> NeoNexStartupConfigSet<U> {
+    /// The entries breaking the rules of their field, or a cross rule.
+    pub fn validate(&self) -> Vec<StartupConfigViolation> {
+        let mut violations: Vec<_> = self.iter().filter_map(validate_entry).collect();
+        violations.extend(
+            U::CROSS_RULES
+                .iter()
+                .filter_map(|rule| self.validate_cross_rule(rule)),
+        );
+        violations
+    }
+
+    /// Removes the entries breaking the rules of their field, then the entries still
+    /// breaking a cross rule, returning why they were removed.
+    pub fn retain_valid(&mut self) -> Vec<StartupConfigViolation> {
+        let mut violations = Vec::new();
+        loop {
+            let found = self.validate();
+            if found.is_empty() {
+                return violations;
+            }
+            for violation in &found {
+                self.remove_by_key(violation.key);
+            }
+            violations.extend(found);
+        }
+    }
+
+    fn validate_cross_rule(&self, rule: &StartupConfigCrossRule) -> Option<StartupConfigViolation> {
+        match *rule {
+            StartupConfigCrossRule::Requires { key, requires } => {
+                let enabled = entry_value(self.get_by_key(key)?) != Some(Value::Bool(false));
+                (enabled && self.get_by_key(requires).is_none()).then(|| StartupConfigViolation {
+                    key,
+                    message: format!("requires {requires} to be set"),
+                })
+            }
+            StartupConfigCrossRule::NotGreater { lower, upper } => {
+                let number = |key| entry_value(self.get_by_key(key)?)?.as_f64();
+                (number(lower)? > number(upper)?).then(|| StartupConfigViolation {
+                    key: lower,
+                    message: format!("can't be greater than {upper}"),
+                })
+            }
+        }
+    }
+}
+
+/// Why `entry` breaks the rules of its field, if it does. Entries without a field in the
+/// schema are always valid.
+pub fn validate_entry<U: UserStartupConfig>(
+    entry: &NeoNexStartupConfig<U>,
+) -> Option<StartupConfigViolation> {
+    let field = NEONEX_STARTUP_CONFIG_SCHEMA
+        .iter()
+        .chain(U::SCHEMA)
+        .find(|field| field.key == entry.key())?;
+    let value = entry_value(entry)?;
+    let message = validate_value(field, &value).err()?;
+    Some(StartupConfigViolation {
+        key: field.key,
+        message,
+    })
+}
+
+/// The value wrapped by `entry`, as entries are serialized as `{"Key": value}`.
+fn entry_value<U: UserStartupConfig>(entry: &NeoNexStartupConfig<U>) -> Option<Value> {
+    match serde_json::to_value(entry).ok()? {
+        Value::Object(mut object) => object.remove(entry.key()),
+        _ => None,
+    }
+}
+
+fn validate_value(field: &StartupConfigField, value: &Value) -> Result<(), String> {
+    match field.kind {
+        StartupConfigFieldKind::Integer { min, max } => check_range(value, min as f64, max as f64)?,
+        StartupConfigFieldKind::Float { min, max, .. } => check_range(value, min, max)?,
+        StartupConfigFieldKind::Choice(options) => {
+            if !value.as_str().is_some_and(|value| options.contains(&value)) {
+                return Err(format!("must be one of {}", options.join(", ")));
+            }
+        }
+        StartupConfigFieldKind::Toggle | StartupConfigFieldKind::Text => {}
+    }
+
+    for rule in field.rules {
+        match *rule {
+            StartupConfigRule::Range { min, max } => check_range(value, min, max)?,
+            StartupConfigRule::Length { min, max } => {
+                let length = match value {
+                    Value::String(string) => string.chars().count(),
+                    Value::Array(items) => items.len(),
+                    _ => return Err("must be text or a list".to_string()),
+                };
+                if !(min..=max).contains(&length) {
+                    return Err(format!("must be {min} to {max} long"));
+                }
+            }
+            StartupConfigRule::Pattern(pattern) => {
+                let text = value.as_str().ok_or("must be text".to_string())?;
+                if !matches_pattern(pattern, text)? {
+                    return Err(format!("must match {pattern}"));
+                }
+            }
+            StartupConfigRule::ExistingPath => {
+                let path = value.as_str().ok_or("must be a path".to_string())?;
+                if !path_exists(path) {
+                    return Err("must be an existing path".to_string());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_range(value: &Value, min: f64, max: f64) -> Result<(), String> {
+    let number = value.as_f64().ok_or("must be a number".to_string())?;
+    match (min..=max).contains(&number) {
+        true => Ok(()),
+        false => Err(format!("must be between {min} and {max}")),
+    }
+}
+
+/// The patterns are compiled once, then kept for the next validations.
+#[cfg(feature = "std")]
+fn matches_pattern(pattern: &'static str, text: &str) -> Result<bool, String> {
+    use alloc::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    static REGEXES: Mutex<BTreeMap<&'static str, Result<regex_lite::Regex, String>>> =
+        Mutex::new(BTreeMap::new());
+
+    let mut regexes = REGEXES.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let regex = regexes.entry(pattern).or_insert_with(|| {
+        regex_lite::Regex::new(&format!("^(?:{pattern})$"))
+            .map_err(|error| format!("has an invalid pattern: {error}"))
+    });
+    match regex {
+        Ok(regex) => Ok(regex.is_match(text)),
+        Err(error) => Err(error.clone()),
+    }
+}
+
+#[cfg(not(feature = "std"))]
+fn matches_pattern(_pattern: &'static str, _text: &str) -> Result<bool, String> {
+    Ok(true)
+}
+
+#[cfg(feature = "std")]
+fn path_exists(path: &str) -> bool {
+    std::path::Path::new(path).exists()
+}
+
+#[cfg(not(feature = "std"))]
+fn path_exists(_path: &str) -> bool {
+    true
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_are_matched_entirely() {
+        assert_eq!(matches_pattern("[a-z]+", "neonex"), Ok(true));
+        assert_eq!(matches_pattern("[a-z]+", "neonex2"), Ok(false));
+        // Once compiled.
+        assert_eq!(matches_pattern("[a-z]+", "2"), Ok(false));
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported_every_time() {
+        for _ in 0..2 {
+            assert!(matches_pattern("[a-z", "neonex").is_err());
+        }
+    }
+}
@@ -0,0 +1,43 @@
+//! Measures how many firmware calls a full redraw costs, without UEFI hardware.
+//!
+//! Run with `cargo run -p neonex-uefi --example redraw_calls`.
+
+use neonex_uefi::{UefiOutputBackend, mock_output::MockOutput};
+use ratatui::{
+    Terminal,
+    style::{Color, Stylize},
+    text::Line,
+    widgets::{Block, Paragraph},
+};
+
+const COLUMNS: u16 = 80;
+const ROWS: u16 = 25;
+
+fn main() -> std::io::Result<()> {
+    let backend = UefiOutputBackend::new(MockOutput::new(COLUMNS as usize, ROWS as usize));
+    let mut terminal = Terminal::new(backend)?;
+
+    terminal.draw(|frame| {
+        let lines: Vec<Line> = (0..ROWS)
+            .map(|row| match row % 3 {
+                0 => Line::from("NeoNex - plain text line"),
+                1 => Line::from(vec!["Key: ".bold(), "value".fg(Color::Yellow)]),
+                _ => Line::from("selected entry".reversed()),
+            })
+            .collect();
+        let widget = Paragraph::new(lines).block(Block::bordered().title("Redraw"));
+        frame.render_widget(widget, frame.area());
+    })?;
+
+    // The first frame redraws every cell of the screen.
+    let cells = COLUMNS as usize * ROWS as usize;
+    let calls = terminal.backend().output().calls();
+    println!("cells drawn:              {cells}");
+    println!("per-cell calls (before):  {}", cells * 3);
+    println!("batched calls:            {}", calls.total());
+    println!("  cursor moves:           {}", calls.cursor_moves);
+    println!("  color changes:          {}", calls.color_changes);
+    println!("  string writes:          {}", calls.writes);
+
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use std::panic::PanicHookInfo;
+
+use bevy::app::AppExit;
+use neonex_platform::UefiExitAction;
+use uefi::{
+    Status, boot,
+    proto::console::text::Color,
+    runtime::{self, ResetType},
+};
+
+/// Performs the setup the `uefi` crate needs before using any of its services, from the
+/// system table and image handle that `std` received at the entry point.
+///
+/// Called by [`UefiPlatform`](crate::UefiPlatform) before the startup config is retrieved,
+/// calling it again is harmless.
+pub fn setup_uefi_crate() {
+    #[cfg(target_os = "uefi")]
+    {
+        use std::os::uefi as uefi_std;
+
+        let st = uefi_std::env::system_table();
+        let ih = uefi_std::env::image_handle();
+
+        // SAFETY: both pointers come from the firmware, through `std`, and stay valid as
+        // long as boot services aren't exited.
+        unsafe {
+            uefi::table::set_system_table(st.as_ptr().cast());
+
+            let ih = uefi::Handle::from_ptr(ih.as_ptr().cast())
+                .expect("The image handle provided by std is null");
+            boot::set_image_handle(ih);
+        }
+    }
+}
+
+/// Replaces the panic hook by one clearing the console and showing a readable panic
+/// screen. Once a key is pressed, `exit_action` is applied.
+pub fn setup_panic_screen(exit_action: UefiExitAction) {
+    std::panic::set_hook(Box::new(move |info| {
+        show_panic_screen(info);
+        wait_for_key();
+        if exit_action != UefiExitAction::ReturnToFirmware {
+            apply_exit_action(exit_action, Status::ABORTED);
+        }
+        // Otherwise, `std` aborts and the firmware gets control back.
+    }));
+}
+
+fn show_panic_screen(info: &PanicHookInfo) {
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("<no message>");
+
+    uefi::system::with_stdout(|stdout| {
+        // Errors are ignored: there is no other way left to report them.
+        let _ = stdout.set_color(Color::White, Color::Red);
+        let _ = stdout.clear();
+        let _ = stdout.enable_cursor(false);
+
+        let mut lines = vec![String::from("NeoNex panicked!"), String::new()];
+        lines.push(format!("Message:  {message}"));
+        match info.location() {
+            Some(location) => lines.push(format!(
+                "Location: {}:{}:{}",
+                location.file(),
+                location.line(),
+                location.column()
+            )),
+            None => lines.push(String::from("Location: unknown")),
+        }
+        lines.push(String::new());
+        lines.push(String::from("Press any key to continue."));
+
+        for (row, line) in lines.iter().enumerate() {
+            let _ = stdout.set_cursor_position(2, row + 1);
+            let _ = core::fmt::Write::write_str(stdout, line);
+        }
+    });
+}
+
+/// Blocks until a key is pressed, if the firmware provides a keyboard.
+fn wait_for_key() {
+    uefi::system::with_stdin(|stdin| {
+        // Drop the keys pressed before the panic.
+        while let Ok(Some(_)) = stdin.read_key() {}
+
+        if let Some(event) = stdin.wait_for_key_event() {
+            let _ = boot::wait_for_event(&mut [event]);
+        }
+    });
+}
+
+/// Applies the exit action once the app is done, returning the app exit status when the
+/// application should return to the firmware.
+pub fn exit(exit_action: UefiExitAction, app_exit: AppExit) -> AppExit {
+    uefi::system::with_stdout(|stdout| {
+        let _ = stdout.set_color(Color::LightGray, Color::Black);
+        let _ = stdout.clear();
+        let _ = stdout.enable_cursor(true);
+    });
+
+    let status = match app_exit {
+        AppExit::Success => Status::SUCCESS,
+        AppExit::Error(_) => Status::ABORTED,
+    };
+    apply_exit_action(exit_action, status);
+
+    app_exit
+}
+
+/// Resets or shuts down the machine, or does nothing when returning to the firmware.
+fn apply_exit_action(exit_action: UefiExitAction, status: Status) {
+    match exit_action {
+        UefiExitAction::ReturnToFirmware => {}
+        UefiExitAction::Reboot => runtime::reset(ResetType::COLD, status, None),
+        UefiExitAction::Shutdown => runtime::reset(ResetType::SHUTDOWN, status, None),
+    }
+}
@@ -0,0 +1,194 @@
+use core::ops::Range;
+
+use ratatui::{buffer::Cell, prelude::Backend};
+use soft_ratatui::SoftBackend;
+use uefi::{
+    boot::ScopedProtocol,
+    proto::console::gop::{BltOp, BltPixel, BltRegion, GraphicsOutput},
+};
+
+/// Font embedded into the binary, as there are no system fonts before the OS boots. It's
+/// the copy the web crate ships for the same reason.
+static FONT_DATA: &[u8] = include_bytes!("../../neonex-web/DejaVuSansMono.ttf");
+const FONT_SIZE: i32 = 16;
+
+/// Ratatui backend drawing into the Graphics Output Protocol framebuffer.
+///
+/// Cells are rendered by `soft_ratatui` into a pixmap (just like the softatui context does
+/// on desktop), whose rows drawn since the last flush are then copied to the framebuffer.
+/// Unlike the text console, this supports truecolor and every glyph of the embedded font.
+pub struct GopBackend {
+    soft: SoftBackend,
+    gop: ScopedProtocol<GraphicsOutput>,
+    /// Framebuffer-formatted copy of the pixmap, kept to avoid reallocating it on each flush.
+    pixels: Vec<BltPixel>,
+    dirty_rows: DirtyRows,
+}
+
+impl GopBackend {
+    /// Creates a backend covering the whole screen, with as many cells as the resolution allows.
+    pub fn new(gop: ScopedProtocol<GraphicsOutput>) -> Self {
+        let (width, height) = gop.current_mode_info().resolution();
+        let mut soft = SoftBackend::new_with_font(1, 1, FONT_SIZE, FONT_DATA);
+        let columns = width / (soft.char_width as usize).max(1);
+        let rows = height / (soft.char_height as usize).max(1);
+        soft.resize(columns as u16, rows as u16);
+
+        Self {
+            soft,
+            gop,
+            pixels: Vec::new(),
+            dirty_rows: DirtyRows::new(rows),
+        }
+    }
+
+    /// Copies the rows of the rendered pixmap drawn since the last flush to the framebuffer,
+    /// centered on the screen.
+    fn present(&mut self) -> std::io::Result<()> {
+        let width = self.soft.get_pixmap_width();
+        let height = self.soft.get_pixmap_height();
+        let char_height = self.soft.char_height as usize;
+        let (screen_width, screen_height) = self.gop.current_mode_info().resolution();
+        let left = screen_width.saturating_sub(width) / 2;
+        let top = screen_height.saturating_sub(height) / 2;
+
+        self.pixels.resize(width * height, BltPixel::new(0, 0, 0));
+        let data = self.soft.get_pixmap_data();
+        for rows in self.dirty_rows.take() {
+            let first = (rows.start * char_height).min(height);
+            let last = (rows.end * char_height).min(height);
+            if first == last {
+                continue;
+            }
+
+            let pixels = &mut self.pixels[first * width..last * width];
+            let rgb = data[first * width * 3..last * width * 3].chunks_exact(3);
+            for (pixel, rgb) in pixels.iter_mut().zip(rgb) {
+                *pixel = BltPixel::new(rgb[0], rgb[1], rgb[2]);
+            }
+
+            self.gop
+                .blt(BltOp::BufferToVideo {
+                    buffer: &self.pixels,
+                    src: BltRegion::SubRectangle {
+                        coords: (0, first),
+                        px_stride: width,
+                    },
+                    dest: (left, top + first),
+                    dims: (width, last - first),
+                })
+                .map_err(|_| std::io::Error::other("Failed to copy to the framebuffer"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Cell rows drawn since the last flush, the only ones worth copying to the framebuffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DirtyRows(Vec<bool>);
+
+impl DirtyRows {
+    /// Every row starts dirty, so that the first flush covers the whole screen.
+    fn new(rows: usize) -> Self {
+        Self(vec![true; rows])
+    }
+
+    fn mark(&mut self, row: u16) {
+        if let Some(dirty) = self.0.get_mut(row as usize) {
+            *dirty = true;
+        }
+    }
+
+    fn mark_all(&mut self) {
+        self.0.fill(true);
+    }
+
+    /// Runs of consecutive dirty rows, each copied at once, leaving every row clean.
+    fn take(&mut self) -> Vec<Range<usize>> {
+        let mut runs: Vec<Range<usize>> = Vec::new();
+        for (row, dirty) in self.0.iter_mut().enumerate() {
+            if !core::mem::take(dirty) {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(run) if run.end == row => run.end += 1,
+                _ => runs.push(row..row + 1),
+            }
+        }
+        runs
+    }
+}
+
+impl Backend for GopBackend {
+    fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        let dirty_rows = &mut self.dirty_rows;
+        self.soft
+            .draw(content.inspect(|&(_, row, _)| dirty_rows.mark(row)))
+    }
+
+    fn hide_cursor(&mut self) -> std::io::Result<()> {
+        self.soft.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> std::io::Result<()> {
+        self.soft.show_cursor()
+    }
+
+    fn get_cursor_position(&mut self) -> std::io::Result<ratatui::prelude::Position> {
+        self.soft.get_cursor_position()
+    }
+
+    fn set_cursor_position<P: Into<ratatui::prelude::Position>>(
+        &mut self,
+        position: P,
+    ) -> std::io::Result<()> {
+        self.soft.set_cursor_position(position)
+    }
+
+    fn clear(&mut self) -> std::io::Result<()> {
+        self.dirty_rows.mark_all();
+        self.soft.clear()
+    }
+
+    fn size(&self) -> std::io::Result<ratatui::prelude::Size> {
+        self.soft.size()
+    }
+
+    fn window_size(&mut self) -> std::io::Result<ratatui::backend::WindowSize> {
+        self.soft.window_size()
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.soft.flush()?;
+        self.present()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_drawn_rows_are_copied() {
+        let mut dirty_rows = DirtyRows::new(6);
+        assert_eq!(dirty_rows.take(), [Range { start: 0, end: 6 }]);
+        assert!(dirty_rows.take().is_empty());
+
+        for row in [1, 2, 4, 2, 9] {
+            dirty_rows.mark(row);
+        }
+        assert_eq!(dirty_rows.take(), [1..3, 4..5]);
+        assert!(dirty_rows.take().is_empty());
+    }
+
+    #[test]
+    fn clearing_copies_every_row() {
+        let mut dirty_rows = DirtyRows::new(3);
+        dirty_rows.take();
+        dirty_rows.mark_all();
+        assert_eq!(dirty_rows.take(), [Range { start: 0, end: 3 }]);
+    }
+}
@@ -1,17 +1,30 @@
+#![cfg_attr(target_os = "uefi", feature(uefi_std))]
+
 use bevy::{
-    app::App,
+    MinimalPlugins,
+    app::{App, PluginGroup, ScheduleRunnerPlugin},
     ecs::error::BevyError,
     prelude::{Deref, DerefMut},
 };
-use neonex_platform::{NeoNexConfig, NeoNexPlatform};
-use neonex_shared::NeoNexStartupConfigSet;
+use neonex_platform::{NeoNexConfig, NeoNexPlatform, StartupConfigSetOf};
 use neonex_terminal::{RatatuiContext, TerminalContext};
-use ratatui::Terminal;
+use ratatui::{Terminal, buffer::Cell, prelude::Backend};
 use uefi::proto::console;
 
-use crate::ratatui_uefi::UefiOutputBackend;
+pub use crate::entry_uefi::{exit, setup_panic_screen, setup_uefi_crate};
+#[cfg(feature = "gop")]
+pub use crate::gop_uefi::GopBackend;
+pub use crate::ratatui_uefi::{TextOutput, UefiOutputBackend, select_text_mode, text_modes};
+pub use crate::store_uefi::{UefiStartupConfigStoreError, UefiVariableStartupConfigStore};
+pub use crate::time_uefi::{install_time_source, paced_runner};
+mod entry_uefi;
+#[cfg(feature = "gop")]
+mod gop_uefi;
+pub mod mock_output;
 mod ratatui_uefi;
+mod store_uefi;
 mod terminput_uefi;
+mod time_uefi;
 
 pub struct UefiPlatform;
 
@@ -20,47 +33,144 @@ impl NeoNexPlatform for UefiPlatform {
 
     type StartupConfigRetrieveKeyType = ();
 
+    /// The startup config is read from a UEFI variable, so the `uefi` crate must be set up
+    /// before it's retrieved, and a panic while reading it must already show the panic screen.
+    fn pre_init<CONFIG: NeoNexConfig>() {
+        setup_uefi_crate();
+        setup_panic_screen(CONFIG::UEFI_EXIT_ACTION);
+    }
+
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: neonex_shared::NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> Result<(), BevyError> {
-        app.set_runner(|mut app| {
-            loop {
-                app.update();
-                if let Some(exit) = app.should_exit() {
-                    return exit;
-                }
-            }
-        });
-        app.insert_non_send_resource(RatatuiContext::init(Self::RatatuiContextGenerics::init()?));
+        // Must happen before `TimePlugin` reads the first `Instant`.
+        install_time_source();
+        app.add_plugins(MinimalPlugins.build().disable::<ScheduleRunnerPlugin>());
+        let runner = paced_runner(CONFIG::UEFI_FRAME_RATE);
+        app.set_runner(move |app| exit(CONFIG::UEFI_EXIT_ACTION, runner(app)));
+        app.insert_non_send_resource(RatatuiContext::init(UefiTerminalContext::init_with(
+            CONFIG::UEFI_TEXT_MODE,
+            CONFIG::UEFI_GRAPHICS_OUTPUT,
+        )?));
         Ok(())
     }
 
-    // CAUTION UEFI Doesn't support startup config! No filesystem... Maybe later..? :)
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType {}
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        NeoNexStartupConfigSet::default()
-    }
-    type UpdateResult = ();
-    fn update_startup_config(startup_config_set: NeoNexStartupConfigSet) -> Self::UpdateResult {}
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType {}
+    type StartupConfigStore = UefiVariableStartupConfigStore;
+    type StorageError = UefiStartupConfigStoreError;
 
-    type RatatuiContextBackend = UefiOutputBackend;
+    type RatatuiContextBackend = UefiBackend;
     type RatatuiContextGenerics = UefiTerminalContext;
 }
 
+/// Backend used by [`UefiTerminalContext`], chosen at runtime between the text console
+/// and the Graphics Output Protocol framebuffer (with the `gop` feature).
+pub enum UefiBackend {
+    Text(UefiOutputBackend),
+    #[cfg(feature = "gop")]
+    Graphics(GopBackend),
+}
+
+/// Forwards a call to whichever backend is in use.
+macro_rules! delegate {
+    ($self:ident, $backend:ident => $call:expr) => {
+        match $self {
+            UefiBackend::Text($backend) => $call,
+            #[cfg(feature = "gop")]
+            UefiBackend::Graphics($backend) => $call,
+        }
+    };
+}
+
+impl Backend for UefiBackend {
+    fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a Cell)>,
+    {
+        delegate!(self, backend => backend.draw(content))
+    }
+
+    fn hide_cursor(&mut self) -> std::io::Result<()> {
+        delegate!(self, backend => backend.hide_cursor())
+    }
+
+    fn show_cursor(&mut self) -> std::io::Result<()> {
+        delegate!(self, backend => backend.show_cursor())
+    }
+
+    fn get_cursor_position(&mut self) -> std::io::Result<ratatui::prelude::Position> {
+        delegate!(self, backend => backend.get_cursor_position())
+    }
+
+    fn set_cursor_position<P: Into<ratatui::prelude::Position>>(
+        &mut self,
+        position: P,
+    ) -> std::io::Result<()> {
+        delegate!(self, backend => backend.set_cursor_position(position))
+    }
+
+    fn clear(&mut self) -> std::io::Result<()> {
+        delegate!(self, backend => backend.clear())
+    }
+
+    fn size(&self) -> std::io::Result<ratatui::prelude::Size> {
+        delegate!(self, backend => backend.size())
+    }
+
+    fn window_size(&mut self) -> std::io::Result<ratatui::backend::WindowSize> {
+        delegate!(self, backend => backend.window_size())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        delegate!(self, backend => backend.flush())
+    }
+}
+
 #[derive(Deref, DerefMut)]
-pub struct UefiTerminalContext(Terminal<UefiOutputBackend>);
+pub struct UefiTerminalContext(Terminal<UefiBackend>);
+
+impl UefiTerminalContext {
+    /// Inits the context, drawing into the GOP framebuffer when `graphics` is set and the
+    /// firmware provides one. Otherwise, the text console is switched to the given text
+    /// mode (columns, rows), see [`select_text_mode`].
+    pub fn init_with(
+        text_mode: Option<(usize, usize)>,
+        graphics: bool,
+    ) -> bevy::ecs::error::Result<Self> {
+        #[cfg(feature = "gop")]
+        if graphics {
+            if let Some(backend) = Self::graphics_backend() {
+                let mut terminal = Terminal::new(UefiBackend::Graphics(backend))?;
+                if let Err(e) = terminal.clear() {}
+                return Ok(Self(terminal));
+            }
+        }
 
-impl TerminalContext<UefiOutputBackend> for UefiTerminalContext {
-    fn init() -> bevy::ecs::error::Result<Self> {
         let output_handle = uefi::boot::get_handle_for_protocol::<console::text::Output>()?;
-        let output = uefi::boot::open_protocol_exclusive::<console::text::Output>(output_handle)?;
-        let backend = UefiOutputBackend::new(output);
+        let mut output =
+            uefi::boot::open_protocol_exclusive::<console::text::Output>(output_handle)?;
+        select_text_mode(&mut output, text_mode)?;
+        let backend = UefiBackend::Text(UefiOutputBackend::new(output));
         let mut terminal = Terminal::new(backend)?;
         if let Err(e) = terminal.clear() {}
         Ok(Self(terminal))
     }
 
+    /// Opens the Graphics Output Protocol, if the firmware provides it.
+    #[cfg(feature = "gop")]
+    fn graphics_backend() -> Option<GopBackend> {
+        let handle = uefi::boot::get_handle_for_protocol::<console::gop::GraphicsOutput>().ok()?;
+        let gop = uefi::boot::open_protocol_exclusive::<console::gop::GraphicsOutput>(handle).ok()?;
+        Some(GopBackend::new(gop))
+    }
+}
+
+impl TerminalContext<UefiBackend> for UefiTerminalContext {
+    fn init() -> bevy::ecs::error::Result<Self> {
+        Self::init_with(None, true)
+    }
+
     fn restore() -> bevy::ecs::error::Result<()> {
         Ok(())
     }
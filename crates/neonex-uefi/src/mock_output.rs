@@ -0,0 +1,163 @@
+use core::fmt::Write;
+
+use uefi::proto::console;
+
+use crate::ratatui_uefi::TextOutput;
+
+/// Text output that doesn't talk to any firmware, but counts the calls that
+/// [`UefiOutputBackend`](crate::UefiOutputBackend) makes.
+///
+/// Each call to a real firmware console is expensive, so these counters are a
+/// hardware-independent way to measure what a redraw costs:
+/// ```ignore
+/// let backend = UefiOutputBackend::new(MockOutput::new(80, 25));
+/// let mut terminal = Terminal::new(backend)?;
+/// terminal.draw(|frame| frame.render_widget(Paragraph::new("Hello"), frame.area()))?;
+/// let calls = terminal.backend().output().calls();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockOutput {
+    columns: usize,
+    rows: usize,
+    cursor: (usize, usize),
+    calls: MockOutputCalls,
+}
+
+/// Number of calls made to a [`MockOutput`], per kind of call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MockOutputCalls {
+    pub cursor_moves: usize,
+    pub color_changes: usize,
+    pub writes: usize,
+    pub clears: usize,
+}
+
+impl MockOutputCalls {
+    /// Total number of firmware calls.
+    pub fn total(&self) -> usize {
+        self.cursor_moves + self.color_changes + self.writes + self.clears
+    }
+}
+
+impl MockOutput {
+    pub fn new(columns: usize, rows: usize) -> Self {
+        Self {
+            columns,
+            rows,
+            ..Default::default()
+        }
+    }
+
+    pub fn calls(&self) -> MockOutputCalls {
+        self.calls
+    }
+
+    /// Forget about the calls made so far, e.g. between two measured frames.
+    pub fn reset_calls(&mut self) {
+        self.calls = MockOutputCalls::default();
+    }
+}
+
+impl Write for MockOutput {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.calls.writes += 1;
+        // Mimic the firmware, which wraps to the next row after the last column.
+        let position = self.cursor.1 * self.columns + self.cursor.0 + s.chars().count();
+        self.cursor = (position % self.columns.max(1), position / self.columns.max(1));
+        Ok(())
+    }
+}
+
+impl TextOutput for MockOutput {
+    fn set_cursor_position(&mut self, column: usize, row: usize) -> uefi::Result {
+        self.calls.cursor_moves += 1;
+        self.cursor = (column, row);
+        Ok(())
+    }
+
+    fn cursor_position(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    fn set_color(&mut self, _fg: console::text::Color, _bg: console::text::Color) -> uefi::Result {
+        self.calls.color_changes += 1;
+        Ok(())
+    }
+
+    fn enable_cursor(&mut self, _visible: bool) -> uefi::Result {
+        Ok(())
+    }
+
+    fn clear(&mut self) -> uefi::Result {
+        self.calls.clears += 1;
+        self.cursor = (0, 0);
+        Ok(())
+    }
+
+    fn size(&self) -> uefi::Result<(usize, usize)> {
+        Ok((self.columns, self.rows))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{Frame, Terminal, style::Stylize, text::Line, widgets::Paragraph};
+
+    use super::*;
+    use crate::UefiOutputBackend;
+
+    const COLUMNS: usize = 20;
+    const ROWS: usize = 4;
+
+    fn terminal() -> Terminal<UefiOutputBackend<MockOutput>> {
+        Terminal::new(UefiOutputBackend::new(MockOutput::new(COLUMNS, ROWS))).unwrap()
+    }
+
+    /// Fills every cell of the screen, so that the whole screen is redrawn.
+    fn fill(frame: &mut Frame) {
+        let lines: Vec<Line> = (0..ROWS).map(|_| Line::from("x".repeat(COLUMNS))).collect();
+        frame.render_widget(Paragraph::new(lines), frame.area());
+    }
+
+    #[test]
+    fn same_colour_rows_are_written_at_once() {
+        let mut terminal = terminal();
+        terminal.draw(fill).unwrap();
+
+        // One run per row, the bottom-right cell being skipped, instead of a cursor move, a
+        // colour change and a write per cell.
+        let calls = terminal.backend().output().calls();
+        assert_eq!(calls.writes, ROWS);
+        assert_eq!(calls.cursor_moves, ROWS);
+        assert_eq!(calls.color_changes, 1);
+        assert_eq!(calls.total(), 2 * ROWS + 1);
+    }
+
+    #[test]
+    fn colour_changes_split_the_runs() {
+        let mut terminal = terminal();
+        terminal
+            .draw(|frame| {
+                fill(frame);
+                let line = Line::from(vec!["key".into(), "value".reversed(), "end".into()]);
+                frame.render_widget(Paragraph::new(line), frame.area());
+            })
+            .unwrap();
+
+        let calls = terminal.backend().output().calls();
+        // The first row holds 3 runs, and the cursor is already in place for the 2nd and 3rd.
+        assert_eq!(calls.writes, ROWS + 2);
+        assert_eq!(calls.cursor_moves, ROWS);
+        assert_eq!(calls.color_changes, 3);
+    }
+
+    #[test]
+    fn unchanged_frames_make_no_calls() {
+        let mut terminal = terminal();
+        terminal.draw(fill).unwrap();
+        terminal.backend_mut().output_mut().reset_calls();
+        terminal.draw(fill).unwrap();
+
+        assert_eq!(terminal.backend().output().calls().total(), 0);
+    }
+}
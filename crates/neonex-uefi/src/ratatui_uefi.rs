@@ -3,15 +3,167 @@ use core::fmt::Write;
 use ratatui::{backend::WindowSize, layout::Size, prelude::Backend};
 use uefi::{Error, Status, boot::ScopedProtocol, proto::console};
 
+/// Text console operations [`UefiOutputBackend`] relies on.
+///
+/// Implemented for the firmware's text output protocol, and for
+/// [`MockOutput`](crate::mock_output::MockOutput) which counts the calls, so that the cost
+/// of a redraw can be measured without real hardware.
+pub trait TextOutput: Write {
+    fn set_cursor_position(&mut self, column: usize, row: usize) -> uefi::Result;
+    fn cursor_position(&self) -> (usize, usize);
+    fn set_color(&mut self, fg: console::text::Color, bg: console::text::Color) -> uefi::Result;
+    fn enable_cursor(&mut self, visible: bool) -> uefi::Result;
+    fn clear(&mut self) -> uefi::Result;
+    /// Columns and rows of the current text mode.
+    fn size(&self) -> uefi::Result<(usize, usize)>;
+}
+
+impl TextOutput for ScopedProtocol<console::text::Output> {
+    fn set_cursor_position(&mut self, column: usize, row: usize) -> uefi::Result {
+        console::text::Output::set_cursor_position(self, column, row)
+    }
+
+    fn cursor_position(&self) -> (usize, usize) {
+        console::text::Output::cursor_position(self)
+    }
+
+    fn set_color(&mut self, fg: console::text::Color, bg: console::text::Color) -> uefi::Result {
+        console::text::Output::set_color(self, fg, bg)
+    }
+
+    fn enable_cursor(&mut self, visible: bool) -> uefi::Result {
+        console::text::Output::enable_cursor(self, visible)
+    }
+
+    fn clear(&mut self) -> uefi::Result {
+        console::text::Output::clear(self)
+    }
+
+    fn size(&self) -> uefi::Result<(usize, usize)> {
+        let mode = self
+            .current_mode()?
+            .ok_or_else(|| Error::from(Status::UNSUPPORTED))?;
+        Ok((mode.columns(), mode.rows()))
+    }
+}
+
+/// Lists the text modes (columns, rows) the firmware console supports.
+pub fn text_modes(output: &mut console::text::Output) -> Vec<(usize, usize)> {
+    output
+        .modes()
+        .map(|mode| (mode.columns(), mode.rows()))
+        .collect()
+}
+
+/// Switches the console to the `wanted` text mode (columns, rows).
+///
+/// Many firmwares start in 80x25 even when bigger modes are available, so when `wanted`
+/// is `None` or isn't supported, the largest mode is picked instead.
+pub fn select_text_mode(
+    output: &mut console::text::Output,
+    wanted: Option<(usize, usize)>,
+) -> uefi::Result {
+    let modes: Vec<console::text::OutputMode> = output.modes().collect();
+
+    let requested = wanted.and_then(|(columns, rows)| {
+        modes
+            .iter()
+            .find(|mode| mode.columns() == columns && mode.rows() == rows)
+    });
+    let largest = modes
+        .iter()
+        .max_by_key(|mode| mode.columns() * mode.rows());
+
+    match requested.or(largest) {
+        Some(mode) => output.set_mode(*mode),
+        // Only the current mode is usable, keep it.
+        None => Ok(()),
+    }
+}
+
 /// Implements a backend for the `ratatui` crate suitable for use in a UEFI application
 /// or loader.
-pub struct UefiOutputBackend {
-    output: ScopedProtocol<console::text::Output>,
+///
+/// Firmware console calls are slow, so `draw` merges adjacent cells of a row sharing the
+/// same colours into a single string write, and only moves the cursor or changes the
+/// colours when the previous write didn't already leave the console in the needed state.
+///
+/// The reported size is the exact geometry of the text mode. Writing the bottom-right cell
+/// would make the firmware scroll the whole screen up, so that single cell is never drawn.
+pub struct UefiOutputBackend<O: TextOutput = ScopedProtocol<console::text::Output>> {
+    output: O,
+    /// Where the firmware cursor is known to be, `None` when unknown.
+    cursor: Option<(u16, u16)>,
+    /// Colours last sent to the firmware, `None` when unknown.
+    colors: Option<(console::text::Color, console::text::Color)>,
+}
+
+impl<O: TextOutput> UefiOutputBackend<O> {
+    pub fn new(output: O) -> Self {
+        Self {
+            output,
+            cursor: None,
+            colors: None,
+        }
+    }
+
+    /// Access the underlying output, e.g. to read the counters of a mock output.
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    /// Mutable access to the underlying output, e.g. to reset the counters of a mock output.
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    /// Writes one run of cells, all located on the same row and sharing the same colours.
+    fn write_run(&mut self, run: &Run, columns: usize) -> std::io::Result<()> {
+        if self.cursor != Some((run.x, run.y)) {
+            self.output
+                .set_cursor_position(run.x as usize, run.y as usize)
+                .map_err(|_| std::io::Error::other("Failed to set cursor"))?;
+        }
+
+        if self.colors != Some((run.fg, run.bg)) {
+            self.output
+                .set_color(run.fg, run.bg)
+                .map_err(|_| std::io::Error::other("Failed to set color"))?;
+            self.colors = Some((run.fg, run.bg));
+        }
+
+        // Until the write succeeds, the cursor position isn't known anymore.
+        self.cursor = None;
+        self.output
+            .write_str(&run.text)
+            .map_err(|_| std::io::Error::other("Failed to write character"))?;
+
+        // Writing the last column makes the firmware wrap (or even scroll),
+        // so only keep track of the cursor when it stays on the same row.
+        let end = run.x + run.len;
+        if (end as usize) < columns {
+            self.cursor = Some((end, run.y));
+        }
+
+        Ok(())
+    }
+}
+
+/// Adjacent cells of a row, drawn with the same colours.
+struct Run {
+    x: u16,
+    y: u16,
+    /// Number of cells of the run.
+    len: u16,
+    fg: console::text::Color,
+    bg: console::text::Color,
+    text: String,
 }
 
-impl UefiOutputBackend {
-    pub fn new(output: ScopedProtocol<console::text::Output>) -> Self {
-        Self { output }
+impl Run {
+    /// Whether the cell at (`x`, `y`) with the given colours directly continues the run.
+    fn continues(&self, x: u16, y: u16, fg: console::text::Color, bg: console::text::Color) -> bool {
+        self.y == y && self.x + self.len == x && self.fg == fg && self.bg == bg
     }
 }
 
@@ -39,12 +191,19 @@ fn to_uefi_color(color: ratatui::style::Color) -> Option<console::text::Color> {
     }
 }
 
-impl ratatui::backend::Backend for UefiOutputBackend {
+impl<O: TextOutput> ratatui::backend::Backend for UefiOutputBackend<O> {
     fn draw<'a, I>(&mut self, content: I) -> std::io::Result<()>
     where
         I: Iterator<Item = (u16, u16, &'a ratatui::buffer::Cell)>,
     {
+        let (columns, rows) = self.output.size().unwrap_or((0, 0));
+        let mut run: Option<Run> = None;
+
         for (x, y, cell) in content {
+            if x as usize + 1 == columns && y as usize + 1 == rows {
+                continue;
+            }
+
             let mut fg = to_uefi_color(cell.fg).unwrap_or(console::text::Color::White);
             let mut bg = to_uefi_color(cell.bg).unwrap_or(console::text::Color::Black);
 
@@ -53,17 +212,29 @@ impl ratatui::backend::Backend for UefiOutputBackend {
                 std::mem::swap(&mut fg, &mut bg);
             }
 
-            self.output
-                .set_cursor_position(x as usize, y as usize)
-                .map_err(|_| std::io::Error::other("Failed to set cursor"))?;
-
-            self.output
-                .set_color(fg, bg)
-                .map_err(|_| std::io::Error::other("Failed to set color"))?;
+            match &mut run {
+                Some(current) if current.continues(x, y, fg, bg) => {
+                    current.text.push_str(cell.symbol());
+                    current.len += 1;
+                }
+                _ => {
+                    if let Some(finished) = run.take() {
+                        self.write_run(&finished, columns)?;
+                    }
+                    run = Some(Run {
+                        x,
+                        y,
+                        len: 1,
+                        fg,
+                        bg,
+                        text: String::from(cell.symbol()),
+                    });
+                }
+            }
+        }
 
-            self.output
-                .write_str(cell.symbol())
-                .map_err(|_| std::io::Error::other("Failed to write character"))?;
+        if let Some(finished) = run {
+            self.write_run(&finished, columns)?;
         }
 
         Ok(())
@@ -98,28 +269,33 @@ impl ratatui::backend::Backend for UefiOutputBackend {
     ) -> std::io::Result<()> {
         let pos = position.into();
 
+        self.cursor = None;
         self.output
             .set_cursor_position(pos.x as usize, pos.y as usize)
-            .map_err(|_| std::io::Error::other("Failed to set cursor position"))
+            .map_err(|_| std::io::Error::other("Failed to set cursor position"))?;
+        self.cursor = Some((pos.x, pos.y));
+
+        Ok(())
     }
 
     fn clear(&mut self) -> std::io::Result<()> {
+        // Clearing moves the cursor back home, and some firmwares reset the colours too.
+        self.cursor = None;
+        self.colors = None;
         self.output
             .clear()
             .map_err(|_| std::io::Error::other("Failed to clear"))
     }
 
     fn size(&self) -> std::io::Result<ratatui::prelude::Size> {
-        let mode = self
+        let (columns, rows) = self
             .output
-            .current_mode()
+            .size()
             .map_err(|_| std::io::Error::other("Failed to get current mode"))?;
 
-        let mode = mode.ok_or_else(|| std::io::Error::other("No current mode available"))?;
-
         Ok(ratatui::prelude::Size {
-            width: mode.columns() as u16,
-            height: (mode.rows() - 2) as u16,
+            width: columns as u16,
+            height: rows as u16,
         })
     }
 
@@ -139,4 +315,4 @@ impl ratatui::backend::Backend for UefiOutputBackend {
         // No-op?
         Ok(())
     }
-}
\ No newline at end of file
+}
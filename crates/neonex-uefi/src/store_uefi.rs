@@ -0,0 +1,124 @@
+use neonex_platform::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, StartupConfigFormatError, StartupConfigLoadError,
+    StartupConfigStore, decode_startup_config, encode_startup_config, startup_config_key,
+};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+use uefi::{
+    CString16, Status, guid,
+    runtime::{self, VariableAttributes, VariableVendor},
+};
+
+/// Vendor of the variables written by NeoNex, so that they don't clash with the firmware ones.
+const NEONEX_VENDOR: VariableVendor = VariableVendor(guid!("6e656f6e-6578-4e58-8000-737461727475"));
+
+/// Kept across reboots, and readable by the OS once booted.
+const ATTRIBUTES: VariableAttributes = VariableAttributes::NON_VOLATILE
+    .union(VariableAttributes::BOOTSERVICE_ACCESS)
+    .union(VariableAttributes::RUNTIME_ACCESS);
+
+/// Keeps the startup config in a non-volatile UEFI variable of the NeoNex vendor, named
+/// after [`startup_config_key`]. Firmwares only have a few dozen KiB for all of their
+/// variables, so a compact `NeoNexConfig::STARTUP_CONFIG_FORMAT` is preferred.
+pub struct UefiVariableStartupConfigStore;
+
+impl UefiVariableStartupConfigStore {
+    /// The name of the variable holding the startup config of `CONFIG`.
+    pub fn variable_name<CONFIG: NeoNexConfig>() -> String {
+        format!("{}.neonex-startup-config", startup_config_key::<CONFIG>())
+    }
+}
+
+/// Why [`UefiVariableStartupConfigStore`] couldn't read or write the startup config.
+#[derive(Debug)]
+pub enum UefiStartupConfigStoreError {
+    /// The firmware refused to read or write the variable.
+    Uefi(uefi::Error),
+    /// The startup config couldn't be encoded, so nothing has been written.
+    Format(StartupConfigFormatError),
+}
+
+impl From<uefi::Error> for UefiStartupConfigStoreError {
+    fn from(error: uefi::Error) -> Self {
+        UefiStartupConfigStoreError::Uefi(error)
+    }
+}
+
+impl From<StartupConfigFormatError> for UefiStartupConfigStoreError {
+    fn from(error: StartupConfigFormatError) -> Self {
+        UefiStartupConfigStoreError::Format(error)
+    }
+}
+
+impl core::fmt::Display for UefiStartupConfigStoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            UefiStartupConfigStoreError::Uefi(error) => write!(f, "{error}"),
+            UefiStartupConfigStoreError::Format(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl StartupConfigStore for UefiVariableStartupConfigStore {
+    type Error = UefiStartupConfigStoreError;
+
+    fn load<CONFIG: NeoNexConfig>() -> Result<
+        NamespacedStartupConfigSetOf<CONFIG>,
+        StartupConfigLoadError<UefiStartupConfigStoreError>,
+    > {
+        let name = Self::variable_name::<CONFIG>();
+        let Some(data) =
+            read(&name).map_err(|error| StartupConfigLoadError::Storage(error.into()))?
+        else {
+            return Ok(NamespacedStartupConfigSet::default());
+        };
+        decode_startup_config::<CONFIG>(&data)
+            .map_err(|error| StartupConfigLoadError::from_decode_error(error, || back_up(&name)))
+    }
+
+    fn save<CONFIG: NeoNexConfig>(
+        startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+    ) -> Result<(), UefiStartupConfigStoreError> {
+        let data = encode_startup_config::<CONFIG>(startup_config_set)?;
+        Ok(write(&Self::variable_name::<CONFIG>(), &data)?)
+    }
+
+    fn back_up<CONFIG: NeoNexConfig>() -> Result<Option<String>, UefiStartupConfigStoreError> {
+        Ok(copy_aside(&Self::variable_name::<CONFIG>())?)
+    }
+}
+
+fn variable(name: &str) -> Result<CString16, uefi::Error> {
+    CString16::try_from(name).map_err(|_| uefi::Error::from(Status::INVALID_PARAMETER))
+}
+
+/// The data of the variable `name`, or `None` when there's no such variable.
+fn read(name: &str) -> Result<Option<Box<[u8]>>, uefi::Error> {
+    match runtime::get_variable_boxed(&variable(name)?, &NEONEX_VENDOR) {
+        Ok((data, _)) => Ok(Some(data)),
+        Err(error) if error.status() == Status::NOT_FOUND => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+fn write(name: &str, data: &[u8]) -> Result<(), uefi::Error> {
+    runtime::set_variable(&variable(name)?, &NEONEX_VENDOR, ATTRIBUTES, data)
+}
+
+/// Moves the variable `name` to `<name>.bak`, returning the new name.
+fn back_up(name: &str) -> Option<String> {
+    let backup = copy_aside(name).ok().flatten()?;
+    runtime::delete_variable(&variable(name).ok()?, &NEONEX_VENDOR).ok()?;
+    Some(backup)
+}
+
+/// Copies the variable `name` to `<name>.bak`, returning the new name. Firmwares have no
+/// room for a history, so the previous copy is replaced. Returns `None` when there's no such
+/// variable.
+fn copy_aside(name: &str) -> Result<Option<String>, uefi::Error> {
+    let Some(data) = read(name)? else {
+        return Ok(None);
+    };
+    let backup = format!("{name}.bak");
+    write(&backup, &data)?;
+    Ok(Some(backup))
+}
@@ -0,0 +1,106 @@
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use bevy::{
+    app::{App, AppExit},
+    platform::time::Instant,
+};
+use uefi::boot::{self, EventType, TimerTrigger, Tpl};
+
+/// How long the counter is measured against the firmware's `Stall()` to calibrate it.
+const CALIBRATION_MICROS: usize = 50_000;
+
+/// Counter ticks per microsecond, measured by [`install_time_source`].
+static TICKS_PER_MICRO: AtomicU64 = AtomicU64::new(0);
+/// Counter value when the time source has been installed.
+static START_TICKS: AtomicU64 = AtomicU64::new(0);
+
+/// Reads the CPU's free-running counter.
+fn counter() -> u64 {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "x86")] {
+            // SAFETY: the time stamp counter is available on every x86 CPU able to run UEFI
+            unsafe { core::arch::x86::_rdtsc() }
+        } else if #[cfg(target_arch = "x86_64")] {
+            // SAFETY: the time stamp counter is available on every x86_64 CPU
+            unsafe { core::arch::x86_64::_rdtsc() }
+        } else if #[cfg(target_arch = "aarch64")] {
+            let ticks: u64;
+            // SAFETY: the virtual counter is readable from EL1, where UEFI applications run
+            unsafe { core::arch::asm!("mrs {}, cntvct_el0", out(reg) ticks) };
+            ticks
+        } else {
+            compile_error!("neonex-uefi doesn't know how to read a counter on this architecture")
+        }
+    }
+}
+
+fn elapsed() -> Duration {
+    let ticks = counter().saturating_sub(START_TICKS.load(Ordering::Relaxed));
+    let ticks_per_micro = TICKS_PER_MICRO.load(Ordering::Relaxed).max(1);
+    Duration::from_micros(ticks / ticks_per_micro)
+}
+
+/// Provides bevy's `Instant` (and so `Time`) with a time source.
+///
+/// Without `std`, bevy reads the raw CPU counter as if it were counting nanoseconds, so
+/// time runs several times too fast. The counter is calibrated here against the firmware's
+/// `Stall()` service, which has a microsecond precision.
+pub fn install_time_source() {
+    let before = counter();
+    boot::stall(CALIBRATION_MICROS);
+    let after = counter();
+
+    let ticks_per_micro = (after.saturating_sub(before) / CALIBRATION_MICROS as u64).max(1);
+    TICKS_PER_MICRO.store(ticks_per_micro, Ordering::Relaxed);
+    START_TICKS.store(after, Ordering::Relaxed);
+
+    // SAFETY: `elapsed` is monotonic, and is a plain function valid for the whole program
+    unsafe { Instant::set_elapsed(elapsed) };
+}
+
+/// Builds a bevy runner updating the app `frame_rate` times per second.
+///
+/// Between two updates, the CPU waits for a periodic firmware timer event instead of
+/// spinning, so an idle menu doesn't keep the CPU (and the fans) at 100%.
+pub fn paced_runner(frame_rate: u32) -> impl FnOnce(App) -> AppExit {
+    move |mut app| {
+        // UEFI timers count in units of 100ns.
+        let period = 10_000_000 / u64::from(frame_rate.max(1));
+        // SAFETY: no notification function is registered, so there's nothing to keep valid
+        let timer = unsafe { boot::create_event(EventType::TIMER, Tpl::APPLICATION, None, None) };
+
+        let Ok(timer) = timer else {
+            return busy_runner(app);
+        };
+        if boot::set_timer(&timer, TimerTrigger::Periodic(period)).is_err() {
+            return busy_runner(app);
+        }
+
+        let mut events = [timer];
+        loop {
+            app.update();
+            if let Some(exit) = app.should_exit() {
+                let [timer] = events;
+                let _ = boot::set_timer(&timer, TimerTrigger::Cancel);
+                let _ = boot::close_event(timer);
+                return exit;
+            }
+            // When the update took longer than a frame, the event is already
+            // signaled and the next update starts right away.
+            let _ = boot::wait_for_event(&mut events);
+        }
+    }
+}
+
+/// Fallback runner, when the firmware refuses to create the frame timer.
+fn busy_runner(mut app: App) -> AppExit {
+    loop {
+        app.update();
+        if let Some(exit) = app.should_exit() {
+            return exit;
+        }
+    }
+}
@@ -1,16 +1,18 @@
 use bevy::{
     app::{App, PluginGroup, Update}, ecs::{error::BevyError, system::Res}, log::{info, warn}, prelude::{Deref, DerefMut}, render::texture::ImagePlugin, text::DEFAULT_FONT_DATA, utils::default, window::{Window, WindowPlugin}, DefaultPlugins
 };
-use gloo_storage::{LocalStorage, Storage};
-use neonex_platform::{NeoNexConfig, NeoNexPlatform};
-use neonex_shared::NeoNexStartupConfigSet;
+use gloo_storage::errors::StorageError;
+use neonex_platform::{NeoNexConfig, NeoNexPlatform, StartupConfigSetOf};
+use neonex_shared::layers::url_override_args;
 use neonex_terminal::{RatatuiContext, TerminalContext};
 use ratatui::Terminal;
-use serde_json::Error;
 use soft_ratatui::SoftBackend;
 
+mod store;
 mod windowed_plugins;
 
+pub use crate::store::LocalStorageStartupConfigStore;
+
 pub struct WebPlatform;
 
 impl NeoNexPlatform for WebPlatform {
@@ -22,11 +24,11 @@ impl NeoNexPlatform for WebPlatform {
         }
     }
 
-    type StartupConfigRetrieveKeyType = &'static str;
+    type StartupConfigRetrieveKeyType = String;
 
     fn setup_bevy<CONFIG: NeoNexConfig>(
         app: &mut App,
-        startup_config_set: neonex_shared::NeoNexStartupConfigSet,
+        startup_config_set: StartupConfigSetOf<CONFIG>,
     ) -> Result<(), BevyError> {
         let sc = startup_config_set.clone();
         app.add_plugins(DefaultPlugins.set(WindowPlugin {
@@ -44,26 +46,26 @@ impl NeoNexPlatform for WebPlatform {
         Ok(())
     }
 
-    fn retrieve_startup_config_key() -> Self::StartupConfigRetrieveKeyType {
-        Self::STARTUP_CONFIG_RANDOM_KEY
+    fn retrieve_startup_config_key<CONFIG: NeoNexConfig>() -> Self::StartupConfigRetrieveKeyType {
+        LocalStorageStartupConfigStore::key::<CONFIG>()
     }
 
-    fn retrieve_startup_config() -> NeoNexStartupConfigSet {
-        let key = Self::retrieve_startup_config_key();
-        if let Ok(sc) = LocalStorage::get(key) {
-            return sc;
-        } else {
-            let default = NeoNexStartupConfigSet::default();
-            Self::update_startup_config(default.clone()).expect("Unable to update the startup config LocalStorage to create or correct the corrupted data");
-            return default;
-        }
-    }
+    type StartupConfigStore = LocalStorageStartupConfigStore;
 
-    type UpdateResult = gloo_storage::Result<()>;
+    type StorageError = StorageError;
 
-    fn update_startup_config(sc: neonex_shared::NeoNexStartupConfigSet) -> Self::UpdateResult {
-        let key = Self::retrieve_startup_config_key();
-        LocalStorage::set(key, &sc)
+    /// The `neonex.*` parameters of the URL query and fragment allowed by
+    /// `NeoNexConfig::STARTUP_CONFIG_URL_OVERRIDES`, as `--neonex-*` flags, see
+    /// [`url_override_args`]. They're never written to the localStorage.
+    fn startup_config_args<CONFIG: NeoNexConfig>() -> Vec<String> {
+        let Some(location) = web_sys::window().map(|window| window.location()) else {
+            return Vec::new();
+        };
+        url_override_args(
+            &location.search().unwrap_or_default(),
+            &location.hash().unwrap_or_default(),
+            CONFIG::STARTUP_CONFIG_URL_OVERRIDES,
+        )
     }
 
     type RatatuiContextBackend = SoftBackend;
@@ -0,0 +1,158 @@
+use base64::{Engine, prelude::BASE64_STANDARD};
+use gloo_storage::{LocalStorage, Storage, errors::StorageError};
+use gloo_utils::errors::JsError;
+use neonex_platform::{
+    NamespacedStartupConfigSetOf, NeoNexConfig, StartupConfigLoadError, StartupConfigStore,
+    StartupConfigWatcher, decode_startup_config, encode_startup_config,
+    is_legacy_startup_config_key, startup_config_key,
+};
+use neonex_shared::namespaces::NamespacedStartupConfigSet;
+use serde::ser::Error as _;
+use serde_json::Error;
+use wasm_bindgen::{JsCast, JsValue, closure::Closure};
+use web_sys::StorageEvent;
+
+/// Keeps the startup config in the localStorage of the origin, under [`Self::key`]. Binary
+/// formats are stored as base64.
+pub struct LocalStorageStartupConfigStore;
+
+impl LocalStorageStartupConfigStore {
+    /// The localStorage key of the startup config of `CONFIG`.
+    pub fn key<CONFIG: NeoNexConfig>() -> String {
+        format!("{}.neonex-startup-config", startup_config_key::<CONFIG>())
+    }
+}
+
+impl StartupConfigStore for LocalStorageStartupConfigStore {
+    type Error = StorageError;
+
+    fn load<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<StorageError>> {
+        remove_legacy_startup_configs();
+        let key = Self::key::<CONFIG>();
+        let Some(stored) = get(&key)? else {
+            return Ok(NamespacedStartupConfigSet::default());
+        };
+        decode_startup_config::<CONFIG>(&stored_bytes(stored))
+            .map_err(|error| StartupConfigLoadError::from_decode_error(error, || back_up(&key)))
+    }
+
+    fn reload<CONFIG: NeoNexConfig>()
+    -> Result<NamespacedStartupConfigSetOf<CONFIG>, StartupConfigLoadError<StorageError>> {
+        let Some(stored) = get(&Self::key::<CONFIG>())? else {
+            return Ok(NamespacedStartupConfigSet::default());
+        };
+        decode_startup_config::<CONFIG>(&stored_bytes(stored))
+            .map_err(|error| StartupConfigLoadError::from_decode_error(error, || None))
+    }
+
+    fn save<CONFIG: NeoNexConfig>(
+        startup_config_set: &NamespacedStartupConfigSetOf<CONFIG>,
+    ) -> Result<(), StorageError> {
+        let data = encode_startup_config::<CONFIG>(startup_config_set)
+            .map_err(|error| StorageError::SerdeError(Error::custom(error)))?;
+        // localStorage only holds strings, so binary formats are stored as base64.
+        let stored = String::from_utf8(data).unwrap_or_else(|error| {
+            format!(
+                "{BASE64_PREFIX}{}",
+                BASE64_STANDARD.encode(error.into_bytes())
+            )
+        });
+        LocalStorage::raw()
+            .set_item(&Self::key::<CONFIG>(), &stored)
+            .map_err(js_to_error)
+    }
+
+    fn back_up<CONFIG: NeoNexConfig>() -> Result<Option<String>, StorageError> {
+        copy_aside(&Self::key::<CONFIG>())
+    }
+
+    fn watch<CONFIG: NeoNexConfig>() -> Option<StartupConfigWatcher> {
+        let key = Self::key::<CONFIG>();
+        let (watcher, notifier) = StartupConfigWatcher::new();
+        // Only fired for the modifications made by the other documents of the same origin,
+        // e.g. a launcher opened in another tab.
+        let listener = Closure::<dyn FnMut(StorageEvent)>::new(move |event: StorageEvent| {
+            // The key is `None` when the whole storage has been cleared.
+            if event.key().is_none_or(|changed| changed == key) {
+                notifier.notify();
+            }
+        });
+        web_sys::window()?
+            .add_event_listener_with_callback("storage", listener.as_ref().unchecked_ref())
+            .ok()?;
+        // JS closures can't be moved to another thread, so it can't be kept by the watcher:
+        // the listener lives as long as the page instead.
+        listener.forget();
+        Some(watcher)
+    }
+}
+
+/// The item stored under `key`, if any.
+fn get(key: &str) -> Result<Option<String>, StartupConfigLoadError<StorageError>> {
+    LocalStorage::raw()
+        .get_item(key)
+        .map_err(|error| StartupConfigLoadError::Storage(js_to_error(error)))
+}
+
+/// Prefixes the startup configs stored as base64, i.e. in a binary format.
+const BASE64_PREFIX: &str = "base64:";
+
+fn stored_bytes(stored: String) -> Vec<u8> {
+    match stored.strip_prefix(BASE64_PREFIX) {
+        // Invalid base64 is kept as is, to be reported as a corrupt startup config.
+        Some(encoded) => BASE64_STANDARD
+            .decode(encoded)
+            .unwrap_or_else(|_| stored.into_bytes()),
+        None => stored.into_bytes(),
+    }
+}
+
+/// Storage methods only throw `Error`s, e.g. when the quota is exceeded.
+fn js_to_error(error: JsValue) -> StorageError {
+    match error.dyn_into::<js_sys::Error>() {
+        Ok(error) => StorageError::JsError(JsError::from(error)),
+        Err(error) => StorageError::SerdeError(Error::custom(format!("{error:?}"))),
+    }
+}
+
+/// Moves the startup config stored under `key` to its backup key, see [`copy_aside`],
+/// returning the new key.
+fn back_up(key: &str) -> Option<String> {
+    let backup = copy_aside(key).ok().flatten()?;
+    LocalStorage::raw().remove_item(key).ok()?;
+    Some(backup)
+}
+
+/// Copies the item under `key` to `<key>.<unix timestamp>.bak`, followed by a counter when
+/// there's already a backup from the same second, returning the new key. Returns `None` when
+/// there's no such item.
+fn copy_aside(key: &str) -> Result<Option<String>, StorageError> {
+    let storage = LocalStorage::raw();
+    let Some(data) = storage.get_item(key).map_err(js_to_error)? else {
+        return Ok(None);
+    };
+    let timestamp = (js_sys::Date::now() / 1000.0) as u64;
+    let mut backup = format!("{key}.{timestamp}.bak");
+    for counter in 1.. {
+        if storage.get_item(&backup).map_err(js_to_error)?.is_none() {
+            break;
+        }
+        backup = format!("{key}.{timestamp}-{counter}.bak");
+    }
+    storage.set_item(&backup, &data).map_err(js_to_error)?;
+    Ok(Some(backup))
+}
+
+/// Removes the startup configs left in the localStorage under random keys, see
+/// [`is_legacy_startup_config_key`].
+fn remove_legacy_startup_configs() {
+    let storage = LocalStorage::raw();
+    let keys: Vec<String> = (0..storage.length().unwrap_or(0))
+        .filter_map(|index| storage.key(index).ok().flatten())
+        .filter(|key| is_legacy_startup_config_key(key))
+        .collect();
+    for key in keys {
+        let _ = storage.remove_item(&key);
+    }
+}